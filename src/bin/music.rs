@@ -1,11 +1,56 @@
 #![warn(clippy::pedantic)]
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
+use crossterm::{
+	event::{
+		self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+		PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+	},
+	execute, terminal,
+};
 use music::{
-	output::MusicMaker,
-	theory::{piano_key::PianoKey, pitch::Pitch, scale::Scale},
+	config::{Config, Settings},
+	output::{
+		rhythm::RhythmSource,
+		seed::{
+			Center, ChordMelody, DegreeWeights, Form, Markov, MarkovModel, Phrases, Random,
+			RowSource, Sequence, Serial, Walk,
+		},
+		write_wav, ChordPlayer, Envelope, Limit, MusicMaker, NoteEvent, PianoRoll, Synth,
+		SynthKeys, Waveform,
+	},
+	qwerty::Qwerty,
+	repl::{Action, Session},
+	theory::{
+		chorale::{Chorale, Voice},
+		chord::{Chord, Progression, RomanNumeral},
+		counterpoint::{Exercise, Placement, Species},
+		hertz::Hertz,
+		key::Key,
+		key_signature::KeySignature,
+		melody::{parse_melody, MelodyNote},
+		piano_key::PianoKey,
+		pitch::Pitch,
+		scale::Scale,
+		tone_row::RowForm,
+	},
+};
+use rodio::{
+	source::{SineWave, Source},
+	OutputStream, Sink,
+};
+use std::{
+	collections::HashMap,
+	env,
+	ffi::OsString,
+	io::{self, IsTerminal, Write},
+	path::PathBuf,
+	process,
+	str::FromStr,
+	sync::mpsc::{self, Receiver},
+	thread,
+	time::{Duration, Instant},
 };
-use rodio::{source::SineWave, OutputStream, Sink};
 
 /// `music` is a procedural single-tone melody generator.
 #[derive(clap::Parser, Debug)]
@@ -16,10 +61,147 @@ use rodio::{source::SineWave, OutputStream, Sink};
 	verbatim_doc_comment,
 	version = env!("CARGO_PKG_VERSION"),
 )]
-struct Args {
-	/// Single-pitch mode
-	#[arg(short, long)]
-	pitch_mode: bool,
+struct Cli {
+	/// What to do, playing generated music if left out
+	#[command(subcommand)]
+	command: Option<Command>,
+	/// Read default settings from this TOML file instead of ~/.config/music/config.toml
+	#[arg(long, global = true, value_name = "FILE", display_order = 100)]
+	config: Option<PathBuf>,
+	/// Use the settings of a named preset from the config file
+	#[arg(long, global = true, value_name = "NAME", display_order = 100)]
+	preset: Option<String>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+	/// Generate music and play it live
+	Play {
+		/// Keep quiet instead of printing each note as it plays
+		#[arg(short, long)]
+		quiet: bool,
+		/// Draw the notes on a piano roll over a keyboard, or print them plainly when not in a terminal
+		#[arg(long)]
+		roll: bool,
+		#[command(flatten)]
+		generate: Generate,
+	},
+	/// Play a single pitch
+	Tone {
+		/// The key to play
+		#[arg(default_value = "C4")]
+		note: PianoKey,
+		/// The frequency A4 sounds at, moving every other key with it
+		#[arg(long, value_name = "HZ", default_value = "440", value_parser = frequency)]
+		tuning: f64,
+	},
+	/// Generate music and write it to a WAV file
+	Render {
+		/// The WAV file to write
+		output: PathBuf,
+		#[command(flatten)]
+		generate: Generate,
+	},
+	/// Type commands like "key D dorian", "tempo 90", "play" or "chord Am7" and hear them at once
+	Repl {
+		/// The base note to start from
+		#[arg(short, long, default_value = "C4")]
+		base_note: PianoKey,
+		/// The scale to start in
+		#[arg(short, long, default_value = "Ionian")]
+		scale: Scale,
+		/// Number of octaves to start over
+		#[arg(short, long, default_value = "1")]
+		octaves: u8,
+	},
+	/// Play the computer keyboard like a piano: Z to M and Q to U are the white keys of two octaves
+	Keyboard {
+		/// The key to spell notes in, starting in the octave of its base note
+		#[arg(short, long, default_value = "C4")]
+		base_note: PianoKey,
+		/// The scale of the key
+		#[arg(short, long, default_value = "Ionian")]
+		scale: Scale,
+		/// Move notes outside the key to the nearest one in it
+		#[arg(long)]
+		snap: bool,
+		#[command(flatten)]
+		sound: Sound,
+	},
+	/// Print the notes of a key
+	Scale {
+		/// The base note to calculate the scale from
+		#[arg(default_value = "C4")]
+		base_note: PianoKey,
+		/// The series of intervals from the base note to use per octave
+		#[arg(short, long, default_value = "Ionian")]
+		scale: Scale,
+		/// Number of octaves to print
+		#[arg(short, long, default_value = "1")]
+		octaves: u8,
+	},
+	/// Spell a chord symbol like Cmaj7, or a Roman numeral like V7 in a key
+	Chord {
+		/// The chord symbol or Roman numeral
+		chord: String,
+		/// The base note of the key Roman numerals are in
+		#[arg(short, long, default_value = "C4")]
+		base_note: PianoKey,
+		/// The scale of the key Roman numerals are in
+		#[arg(short, long, default_value = "Ionian")]
+		scale: Scale,
+	},
+	/// Name the interval between two keys
+	Interval {
+		/// The first key, like C4
+		from: PianoKey,
+		/// The second key, like E4
+		to: PianoKey,
+	},
+	/// Show the frequency and MIDI number of a key
+	Info {
+		/// The key to describe, like A4
+		note: PianoKey,
+		/// The frequency A4 sounds at, moving every other key with it
+		#[arg(long, value_name = "HZ", default_value = "440", value_parser = frequency)]
+		tuning: f64,
+	},
+	/// Check a counterpoint of notes and lengths against a cantus firmus, listing the rules it breaks
+	Check {
+		/// The cantus firmus in whole notes, like "D4 F4 E4 D4 G4 F4 A4 G4 F4 E4 D4"
+		cantus: String,
+		/// The counterpoint, like "A4:16 A4:16 G4:16"
+		counterpoint: String,
+		/// Species of the counterpoint, from 1 for note against note to 5 for florid
+		#[arg(long, default_value = "1")]
+		species: Species,
+		/// Whether the counterpoint is "above" or "below" the cantus
+		#[arg(long, default_value = "above")]
+		placement: Placement,
+	},
+	/// Harmonize a soprano melody for four voices and print the chorale
+	Chorale {
+		/// The soprano melody of notes and lengths, like "E4:4 D4:4 C4:8"
+		melody: String,
+		/// The base note of the key to harmonize in
+		#[arg(short, long, default_value = "C4")]
+		base_note: PianoKey,
+		/// The scale of the key to harmonize in
+		#[arg(short, long, default_value = "Ionian")]
+		scale: Scale,
+		/// Write the chorale to this MIDI file, one track per voice
+		#[arg(long, value_name = "FILE")]
+		midi: Option<PathBuf>,
+		/// Beats per minute of the MIDI file
+		#[arg(long, default_value = "60")]
+		tempo: f64,
+	},
+}
+
+/// The options for generating music, shared by playing and rendering
+#[derive(clap::Parser, Debug)]
+#[allow(clippy::struct_excessive_bools)]
+struct Generate {
 	/// The base note to calculate the scale from
 	#[arg(short, long, default_value = "C4")]
 	base_note: PianoKey,
@@ -29,33 +211,816 @@ struct Args {
 	/// Number of octaves over which to range, anything over 8 gets parsed as 8
 	#[arg(short, long, default_value = "1")]
 	octaves: u8,
+	/// Play a twelve-tone row instead of random notes: "random", "all-interval", or twelve pitch classes or notes
+	#[arg(short, long, group = "seed")]
+	row: Option<RowSource>,
+	/// Comma-separated row forms to cycle through, like P0,R5,I0,RI3
+	#[arg(long, value_delimiter = ',', default_value = "P0", requires = "row")]
+	forms: Vec<RowForm>,
+	/// Print the twelve-tone matrix before playing a row
+	#[arg(long, requires = "row")]
+	matrix: bool,
+	/// Train a Markov model on these MIDI files or note lists, one melody per line like "C4:2 D4:2 E4:4"
+	#[arg(short, long, value_delimiter = ',', group = "seed")]
+	train: Vec<PathBuf>,
+	/// Load a saved Markov model, training it further on any --train files
+	#[arg(
+		short,
+		long,
+		conflicts_with_all = ["row", "form", "progression", "cantus", "harmonize", "walk"]
+	)]
+	model: Option<PathBuf>,
+	/// Number of previous notes a new Markov model looks back over
+	#[arg(long, default_value = "2", requires = "train")]
+	order: usize,
+	/// Save the Markov model after training
+	#[arg(long)]
+	save_model: Option<PathBuf>,
+	/// Walk up and down the scale a few notes at a time instead of jumping anywhere
+	#[arg(short, long, group = "seed")]
+	walk: bool,
+	/// Comma-separated weights for each step size of the walk, starting with repeating a note
+	#[arg(
+		long,
+		value_delimiter = ',',
+		default_value = "0.5,6,3,1.5,1,0.5,0.25,0.5"
+	)]
+	steps: Vec<f64>,
+	/// Largest leap the walk may take, in scale steps
+	#[arg(long, default_value = "7")]
+	max_leap: usize,
+	/// How strongly the walk is pulled back to its center, 0 for not at all
+	#[arg(long, default_value = "0.5")]
+	gravity: f64,
+	/// Where the walk is pulled back to: "tonic" or "middle"
+	#[arg(long, default_value = "tonic")]
+	center: Center,
+	/// Step back the other way after a leap larger than this many scale steps, 0 to let leaps follow leaps
+	#[arg(long, default_value = "3")]
+	recovery: usize,
+	/// How likely each degree of the scale is, from the base note up, like "4,1,2,1,3,1,0.5,resolve".
+	/// Also takes a preset: flat, tonal, tonic-dominant or pentatonic
+	#[arg(long, default_value = "flat")]
+	weights: DegreeWeights,
+	/// Read the degree weights from a file instead
+	#[arg(long)]
+	weights_file: Option<PathBuf>,
+	/// When notes start, in sixteenth-note steps: "steady", a note length like "2",
+	/// a Euclidean rhythm like "E(3,8)" or "E(3,8,2)" rotated by two steps, or per-step chances like "1,0,0.5,0.25"
+	#[arg(long, default_value = "steady")]
+	rhythm: RhythmSource,
+	/// Chance of turning each note of the rhythm into a rest, from 0 to 1
+	#[arg(long, default_value = "0", value_parser = chance)]
+	rests: f64,
+	/// Beats per minute, where a beat is four steps
+	#[arg(long, default_value = "60")]
+	tempo: f64,
+	#[command(flatten)]
+	sound: Sound,
+	/// How loud to play, from 0 to 1
+	#[arg(long, default_value = "1")]
+	volume: f64,
+	/// Stop after this many notes. Rendering stops after 600 seconds if they haven't all played by then
+	#[arg(long, group = "limit")]
+	notes: Option<u32>,
+	/// Stop after this many bars of four beats
+	#[arg(long, group = "limit")]
+	bars: Option<u32>,
+	/// Stop after this many seconds. Rendering stops after 30 unless told otherwise
+	#[arg(long, value_name = "SECONDS", group = "limit")]
+	duration: Option<f64>,
+	/// Seconds to fade out over before stopping
+	#[arg(long, value_name = "SECONDS", default_value = "1")]
+	fade: f64,
+	/// Develop a motif into phrases in this form, like AABA or ABAC, with its own rhythm
+	#[arg(short, long, group = "seed")]
+	form: Option<Form>,
+	/// Follow a chord progression in Roman numerals like "I vi IV V" or "ii7 V7 Imaj7", with chord tones on strong beats
+	#[arg(long, group = "seed")]
+	progression: Option<Progression>,
+	/// Beats each chord of the progression lasts
+	#[arg(
+		long,
+		value_name = "BEATS",
+		default_value = "4",
+		requires = "progression"
+	)]
+	beats_per_chord: u16,
+	/// Play the chords of the progression underneath the melody
+	#[arg(long, requires = "progression")]
+	harmony: bool,
+	/// Write counterpoint in the key against a cantus firmus of whole notes, like "D4 F4 E4 D4 G4 F4 A4 G4 F4 E4 D4"
+	#[arg(long, group = "seed")]
+	cantus: Option<String>,
+	/// Species of the counterpoint, from 1 for note against note to 5 for florid
+	#[arg(long, default_value = "1", requires = "cantus")]
+	species: Species,
+	/// Write the counterpoint "above" or "below" the cantus
+	#[arg(long, default_value = "above", requires = "cantus")]
+	placement: Placement,
+	/// Harmonize a soprano melody in the key for four voices, like "E4:4 D4:4 C4:8"
+	#[arg(long, value_name = "MELODY", group = "seed")]
+	harmonize: Option<String>,
+}
+
+/// How each note sounds, shared by generated music and the keyboard
+#[derive(clap::Args, Debug)]
+struct Sound {
+	/// The shape of the wave: sine, square, triangle or sawtooth
+	#[arg(long, default_value = "sine")]
+	waveform: Waveform,
+	/// Seconds each note takes to rise to full volume
+	#[arg(long, value_name = "SECONDS")]
+	attack: Option<f64>,
+	/// Seconds each note takes to fall from full volume to the sustain level
+	#[arg(long, value_name = "SECONDS")]
+	decay: Option<f64>,
+	/// Level each note holds at after its decay, from 0 to 1
+	#[arg(long, value_name = "LEVEL")]
+	sustain: Option<f64>,
+	/// Seconds each note takes to fall silent at its end
+	#[arg(long, value_name = "SECONDS")]
+	release: Option<f64>,
+	/// The frequency A4 sounds at, moving every other key with it
+	#[arg(long, value_name = "HZ", default_value = "440", value_parser = frequency)]
+	tuning: f64,
 }
 
+impl Sound {
+	/// Build the envelope from whichever parts were given, filling in the rest, if any were
+	fn envelope(&self) -> Option<Envelope> {
+		if [self.attack, self.decay, self.sustain, self.release]
+			.iter()
+			.all(Option::is_none)
+		{
+			return None;
+		}
+		let default = Envelope::default();
+		Some(Envelope {
+			attack: self.attack.map_or(default.attack, seconds),
+			decay: self.decay.map_or(default.decay, seconds),
+			sustain: self.sustain.unwrap_or(default.sustain),
+			release: self.release.map_or(default.release, seconds),
+		})
+	}
+
+	/// Give generated music this sound
+	fn music(&self, mut music: MusicMaker) -> MusicMaker {
+		music = music
+			.set_waveform(self.waveform)
+			.set_tuning(Hertz::from(self.tuning));
+		match self.envelope() {
+			Some(envelope) => music.set_envelope(envelope),
+			None => music,
+		}
+	}
+
+	/// Give chords this sound
+	fn chords(&self, mut chords: ChordPlayer) -> ChordPlayer {
+		chords = chords
+			.with_waveform(self.waveform)
+			.with_tuning(Hertz::from(self.tuning));
+		match self.envelope() {
+			Some(envelope) => chords.with_envelope(envelope),
+			None => chords,
+		}
+	}
+}
+
+impl Generate {
+	/// Find when the music should stop, if it should
+	fn limit(&self) -> Option<Limit> {
+		if self.notes.is_some() && self.rests >= 1.0 {
+			exit_with(&"Every note is a rest with --rests 1, so --notes would never be reached");
+		}
+		self.notes
+			.map(Limit::Notes)
+			.or(self.bars.map(Limit::Bars))
+			.or(self.duration.map(|d| Limit::Duration(seconds(d))))
+	}
+}
+
+/// Read a number of seconds from the command line
+fn seconds(seconds: f64) -> Duration {
+	Duration::try_from_secs_f64(seconds).unwrap_or_else(|e| exit_with(&e))
+}
+
+/// Read a chance from 0 to 1 from the command line
+fn chance(s: &str) -> Result<f64, String> {
+	s.parse::<f64>()
+		.ok()
+		.filter(|c| (0.0..=1.0).contains(c))
+		.ok_or_else(|| format!("{s} is not a chance, try a number from 0 to 1"))
+}
+
+/// Read a frequency in Hertz from the command line
+fn frequency(s: &str) -> Result<f64, String> {
+	s.parse::<f64>()
+		.ok()
+		.filter(|hz| hz.is_finite() && *hz > 0.0)
+		.ok_or_else(|| format!("{s} is not a frequency, try a number of Hertz above 0"))
+}
+
+/// Print an error and quit
+fn exit_with(error: &impl std::fmt::Display) -> ! {
+	eprintln!("{error}");
+	process::exit(1)
+}
+
+/// Print the motif of a piece and how each section develops it
+fn print_phrases(phrases: &Phrases) {
+	let motif = phrases
+		.motif()
+		.iter()
+		.map(ToString::to_string)
+		.collect::<Vec<_>>();
+	println!("Motif in scale steps and lengths: {}", motif.join(" "));
+	for (letter, developments) in phrases.sections() {
+		let developments = developments
+			.iter()
+			.map(ToString::to_string)
+			.collect::<Vec<_>>();
+		println!("{letter}: {}", developments.join(", "));
+	}
+}
+
+/// Set a counterpoint exercise against a cantus firmus of whole notes
+fn exercise(cantus: &str, species: Species, placement: Placement) -> Exercise {
+	let cantus = parse_melody(cantus).unwrap_or_else(|e| exit_with(&e));
+	let cantus = cantus.iter().map(|n| n.key).collect();
+	Exercise::new(cantus, species, placement).unwrap_or_else(|e| exit_with(&e))
+}
+
+/// Print a counterpoint under its cantus with any rules it breaks
+fn print_counterpoint(exercise: &Exercise, line: &[MelodyNote]) {
+	let show = |notes: &[MelodyNote]| {
+		notes
+			.iter()
+			.map(ToString::to_string)
+			.collect::<Vec<_>>()
+			.join(" ")
+	};
+	println!("Cantus firmus: {}", show(&exercise.cantus_melody()));
+	println!(
+		"Counterpoint in the {} {}: {}",
+		exercise.species(),
+		exercise.placement(),
+		show(line)
+	);
+	let violations = exercise.check(line);
+	if violations.is_empty() {
+		println!("No rules broken");
+	}
+	for violation in violations {
+		println!("{violation}");
+	}
+}
+
+/// Write a counterpoint in the key and print it with any broken rules.
+/// Returns the cantus and the counterpoint, ready to play together
+fn counterpoint(
+	cantus: &str,
+	species: Species,
+	placement: Placement,
+	key: Key,
+) -> (Sequence, Sequence) {
+	let exercise = exercise(cantus, species, placement);
+	let line = exercise
+		.generate(key, &mut rand::thread_rng())
+		.unwrap_or_else(|e| exit_with(&e));
+	print_counterpoint(&exercise, &line);
+	let cantus = Sequence::new(exercise.cantus_melody()).unwrap_or_else(|e| exit_with(&e));
+	let line = Sequence::new(line).unwrap_or_else(|e| exit_with(&e));
+	(cantus, line)
+}
+
+/// Harmonize a soprano melody in a key and print the chorale
+fn harmonize(melody: &str, key: Key) -> Chorale {
+	let melody = parse_melody(melody).unwrap_or_else(|e| exit_with(&e));
+	let chorale = Chorale::harmonize(&melody, key).unwrap_or_else(|e| exit_with(&e));
+	print!("{chorale}");
+	chorale
+}
+
+/// Harmonize a soprano melody and print the chorale.
+/// Returns the soprano and the lower voices mixed together, ready to play
+fn chorale(
+	melody: &str,
+	key: Key,
+	tempo: f64,
+	sound: &Sound,
+) -> (Sequence, Box<dyn Source<Item = f32> + Send>) {
+	let chorale = harmonize(melody, key);
+	let voice = |voice| {
+		let line = Sequence::new(chorale.voice(voice)).unwrap_or_else(|e| exit_with(&e));
+		sound.music(MusicMaker::default().set_composer(line).set_tempo(tempo))
+	};
+	let lower = voice(Voice::Alto)
+		.mix(voice(Voice::Tenor))
+		.mix(voice(Voice::Bass))
+		.amplify(0.3);
+	let soprano = Sequence::new(chorale.voice(Voice::Soprano)).unwrap_or_else(|e| exit_with(&e));
+	(soprano, Box::new(lower))
+}
+
+/// Build the music the options describe, printing what it is made of
+fn compose(opt: Generate) -> MusicMaker {
+	// Init procedural generator
+	let limit = opt.limit();
+	let weights = match &opt.weights_file {
+		Some(path) => DegreeWeights::load(path).unwrap_or_else(|e| exit_with(&e)),
+		None => opt.weights,
+	};
+	let mut music = MusicMaker::new(opt.base_note, opt.scale, opt.octaves)
+		.set_seed(Random::default().with_weights(weights.clone()))
+		.set_rhythm(
+			opt.rhythm
+				.build(opt.rests)
+				.unwrap_or_else(|e| exit_with(&e)),
+		)
+		.set_tempo(opt.tempo)
+		.set_fade(seconds(opt.fade))
+		.set_volume(opt.volume);
+	music = opt.sound.music(music);
+	if let Some(limit) = limit {
+		music = music.set_limit(limit);
+	}
+	println!("{music}");
+	let key = Key::new(opt.scale, opt.base_note, opt.octaves);
+	if let Some(source) = opt.row {
+		let serial = Serial::new(source.build()).with_forms(opt.forms);
+		println!("Twelve-tone row: {}", serial.row());
+		if opt.matrix {
+			print!("{}", serial.row().matrix());
+		}
+		for form in serial.forms() {
+			let keys = serial.voice(*form, key);
+			let keys = keys.iter().map(ToString::to_string).collect::<Vec<_>>();
+			println!("{form}: {}", keys.join(" "));
+		}
+		music = music.set_seed(serial);
+	} else if opt.model.is_some() || !opt.train.is_empty() {
+		let mut model = match &opt.model {
+			Some(path) => MarkovModel::load(path).unwrap_or_else(|e| exit_with(&e)),
+			None => MarkovModel::new(opt.order),
+		};
+		for path in &opt.train {
+			model.train_file(path).unwrap_or_else(|e| exit_with(&e));
+		}
+		if let Some(path) = &opt.save_model {
+			model.save(path).unwrap_or_else(|e| exit_with(&e));
+		}
+		println!("Markov model of order {}", model.order());
+		music = music.set_seed(Markov::new(model));
+	} else if let Some(form) = opt.form {
+		let phrases = Phrases::new(form, &mut rand::thread_rng());
+		print_phrases(&phrases);
+		music = music.set_composer(phrases);
+	} else if let Some(progression) = opt.progression {
+		let melody = ChordMelody::new(progression).with_beats_per_chord(opt.beats_per_chord);
+		let chords = melody.chords(key).unwrap_or_else(|e| exit_with(&e));
+		let chords = chords.iter().map(ToString::to_string).collect::<Vec<_>>();
+		println!("Progression {}: {}", melody.progression(), chords.join(" "));
+		if opt.harmony {
+			let harmony = melody.harmony(key).unwrap_or_else(|e| exit_with(&e));
+			for (_, reason) in &harmony.restarts {
+				println!("{reason}, so the voices start again in root position");
+			}
+			let chords = ChordPlayer::new(&harmony.voicings, opt.beats_per_chord, opt.tempo);
+			music = music.set_accompaniment(opt.sound.chords(chords));
+		}
+		music = music.set_composer(melody);
+	} else if let Some(cantus) = &opt.cantus {
+		let (cantus, counterpoint) = counterpoint(cantus, opt.species, opt.placement, key);
+		let cantus = MusicMaker::default()
+			.set_composer(cantus)
+			.set_tempo(opt.tempo);
+		music = music.set_accompaniment(opt.sound.music(cantus).amplify(0.5));
+		music = music.set_composer(counterpoint);
+	} else if let Some(melody) = &opt.harmonize {
+		let (soprano, lower) = chorale(melody, key, opt.tempo, &opt.sound);
+		music = music.set_accompaniment(lower);
+		music = music.set_composer(soprano);
+	} else if opt.walk {
+		let walk = Walk::default()
+			.with_steps(opt.steps)
+			.with_max_leap(opt.max_leap)
+			.with_gravity(opt.gravity, opt.center)
+			.with_leap_recovery((opt.recovery > 0).then_some(opt.recovery))
+			.with_weights(weights);
+		music = music.set_seed(walk);
+	}
+	music
+}
+
+/// Print the notes of a key, with its key signature if it has one
+fn print_scale(base_note: PianoKey, scale: Scale, octaves: u8) {
+	let key = Key::new(scale, base_note, octaves);
+	let keys = key.all_keys();
+	let keys = keys.iter().map(ToString::to_string).collect::<Vec<_>>();
+	println!("{} {}: {}", base_note.note, scale, keys.join(" "));
+	if let Scale::Diatonic(mode) = scale {
+		if let Ok(signature) = KeySignature::from_tonic(base_note.note, mode) {
+			println!("Key signature: {signature}");
+		}
+	}
+}
+
+/// Spell a chord from its symbol, or from a Roman numeral in a key
+fn print_chord(chord: &str, key: Key) {
+	let (name, chord) = if let Ok(symbol) = Chord::from_str(chord) {
+		(symbol.to_string(), symbol)
+	} else {
+		let numeral = RomanNumeral::from_str(chord).unwrap_or_else(|_| {
+			exit_with(&format!(
+				"{chord} is neither a chord symbol nor a Roman numeral"
+			))
+		});
+		let symbol = numeral.chord(key).unwrap_or_else(|e| exit_with(&e));
+		(
+			format!(
+				"{numeral} in the {} {} is {symbol}",
+				key.base_note.note, key.scale
+			),
+			symbol,
+		)
+	};
+	let notes = chord.notes();
+	let notes = notes.iter().map(ToString::to_string).collect::<Vec<_>>();
+	let intervals = chord.quality.intervals();
+	let intervals = intervals
+		.iter()
+		.map(ToString::to_string)
+		.collect::<Vec<_>>();
+	println!("{name}: {}", notes.join(" "));
+	println!("Intervals above the root: {}", intervals.join(" "));
+}
+
+/// Name the interval between two keys and its size
+fn print_interval(from: PianoKey, to: PianoKey) {
+	let semitones = (to.semitones_from_c0() - from.semitones_from_c0()).abs();
+	match from.interval_to(to) {
+		Some(interval) => println!("{from} to {to}: {interval}, {semitones} semitones"),
+		None => println!("{from} to {to}: {semitones} semitones"),
+	}
+}
+
+/// Print what there is to know about a single key
+fn print_info(note: PianoKey, tuning: Hertz) {
+	println!("{note}");
+	let frequency = Pitch::from(note).tuned(tuning);
+	println!("Frequency: {:.2} Hz", f64::from(frequency));
+	match note.midi() {
+		Some(number) => println!("MIDI number: {number}"),
+		None => println!("MIDI number: none"),
+	}
+	println!("Pitch class: {}", note.note.pitch_class());
+}
+
+/// How long rendering goes on for without a limit
+const RENDER_SECONDS: f64 = 30.0;
+
+/// The longest rendering waits for a number of notes, for rhythms that rarely or never play one
+const MAX_RENDER_SECONDS: f64 = 600.0;
+
 /// Displayed in the CLI each run
 const GREETING: &str = ".: Cool Tunes :.";
 
+/// Play a sound until it ends, which for generated music is never,
+/// printing any notes it reports as they reach the speakers, on a piano roll if there is one
+fn play(
+	source: impl Source<Item = f32> + Send + 'static,
+	events: Option<Receiver<NoteEvent>>,
+	mut roll: Option<PianoRoll>,
+) {
+	let (_stream, handle) = OutputStream::try_default().unwrap_or_else(|e| exit_with(&e));
+	let sink = Sink::try_new(&handle).unwrap_or_else(|e| exit_with(&e));
+	let start = Instant::now();
+	sink.append(source);
+	// The channel closes when the sink drops the finished music
+	for event in events.into_iter().flatten() {
+		if let Some(wait) = event.at.checked_sub(start.elapsed()) {
+			thread::sleep(wait);
+		}
+		match &mut roll {
+			Some(roll) => {
+				roll.push(event);
+				// Move to the top left and clear the screen before redrawing
+				print!("\x1b[H\x1b[2J{roll}");
+				io::stdout().flush().unwrap_or_else(|e| exit_with(&e));
+			},
+			None => println!("{event}"),
+		}
+	}
+	sink.sleep_until_end();
+}
+
+/// How long a chord or tone typed into the REPL sounds for
+const SOUND_SECONDS: f64 = 1.5;
+
+/// Run commands line by line, playing what they ask for until told to quit or the input runs out
+fn repl(mut session: Session) {
+	let (_stream, handle) = OutputStream::try_default().unwrap_or_else(|e| exit_with(&e));
+	let new_sink = || Sink::try_new(&handle).unwrap_or_else(|e| exit_with(&e));
+	let mut music: Option<Sink> = None;
+	println!("{session}\nType help to list the commands");
+	let mut lines = io::stdin().lines();
+	loop {
+		print!("> ");
+		io::stdout().flush().unwrap_or_else(|e| exit_with(&e));
+		let Some(line) = lines.next() else {
+			println!();
+			break;
+		};
+		let line = line.unwrap_or_else(|e| exit_with(&e));
+		if line.trim().is_empty() {
+			continue;
+		}
+		match session.run_line(&line) {
+			Ok(Action::Say(message)) => println!("{message}"),
+			Ok(Action::Play(maker)) => {
+				println!("{maker}");
+				let sink = new_sink();
+				sink.append(*maker);
+				if let Some(old) = music.replace(sink) {
+					old.stop();
+				}
+			},
+			Ok(Action::Stop) => {
+				if let Some(sink) = music.take() {
+					sink.stop();
+				}
+			},
+			Ok(Action::Sound(keys, volume)) => {
+				let names = keys.iter().map(ToString::to_string).collect::<Vec<_>>();
+				println!("{}", names.join(" "));
+				let sound = ChordPlayer::new(&[keys], 1, 60.0 / SOUND_SECONDS)
+					.with_amplitude(volume)
+					.take_duration(seconds(SOUND_SECONDS));
+				let sink = new_sink();
+				sink.append(sound);
+				// Let it ring over the music without holding on to it
+				sink.detach();
+			},
+			Ok(Action::Quit) => break,
+			Err(e) => println!("{e}"),
+		}
+	}
+}
+
+/// How long a key sounds after it was last pressed, for terminals that can't report releases.
+/// Holding a key down repeats it, which keeps it sounding
+const HOLD: Duration = Duration::from_millis(600);
+
+/// Play notes as keys are pressed and released until Esc, putting the terminal back afterwards
+fn keyboard(layout: Qwerty, sound: &Sound) {
+	let (_stream, handle) = OutputStream::try_default().unwrap_or_else(|e| exit_with(&e));
+	let sink = Sink::try_new(&handle).unwrap_or_else(|e| exit_with(&e));
+	let mut synth = Synth::default()
+		.with_waveform(sound.waveform)
+		.with_envelope(sound.envelope().unwrap_or_default())
+		.with_tuning(Hertz::from(sound.tuning));
+	let keys = synth.keys();
+	sink.append(synth);
+	println!("Z to / and Q to ] play the keys, Up and Down change octave, Esc quits");
+	terminal::enable_raw_mode().unwrap_or_else(|e| exit_with(&e));
+	let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+	if releases {
+		let flags = KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+		execute!(io::stdout(), PushKeyboardEnhancementFlags(flags))
+			.unwrap_or_else(|e| exit_with(&e));
+	}
+	let result = play_keys(layout, &keys, releases);
+	if releases {
+		let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+	}
+	let _ = terminal::disable_raw_mode();
+	println!();
+	result.unwrap_or_else(|e| exit_with(&e));
+}
+
+/// Read key presses and turn them into notes.
+/// Terminals that can't report releases let each note go a moment after its key was last pressed
+fn play_keys(mut layout: Qwerty, keys: &SynthKeys, releases: bool) -> Result<(), io::Error> {
+	let mut held = HashMap::new();
+	print!("Octave {}\r\n", layout.octave());
+	loop {
+		if event::poll(Duration::from_millis(10))? {
+			if let Event::Key(KeyEvent {
+				code,
+				modifiers,
+				kind,
+				..
+			}) = event::read()?
+			{
+				match (code, kind) {
+					(KeyCode::Esc, _) => break,
+					(KeyCode::Char('c'), _) if modifiers.contains(KeyModifiers::CONTROL) => break,
+					(KeyCode::Up | KeyCode::Down, KeyEventKind::Press) => {
+						if code == KeyCode::Up {
+							layout.octave_up();
+						} else {
+							layout.octave_down();
+						}
+						keys.all_off()?;
+						held.clear();
+						print!("\r\nOctave {}\r\n", layout.octave());
+					},
+					(KeyCode::Char(c), KeyEventKind::Release) => {
+						if let Some((key, _)) = held.remove(&c) {
+							keys.note_off(key)?;
+						}
+					},
+					(KeyCode::Char(c), _) => {
+						if let Some((_, pressed)) = held.get_mut(&c) {
+							*pressed = Instant::now();
+						} else if let Some(key) = layout.key_for(c) {
+							keys.note_on(key)?;
+							held.insert(c, (key, Instant::now()));
+							print!("{key} ");
+						}
+					},
+					_ => {},
+				}
+				io::stdout().flush()?;
+			}
+		}
+		if !releases {
+			let expired = held
+				.iter()
+				.filter(|(_, (_, pressed))| pressed.elapsed() >= HOLD)
+				.map(|(&c, &(key, _))| (c, key))
+				.collect::<Vec<_>>();
+			for (c, key) in expired {
+				held.remove(&c);
+				keys.note_off(key)?;
+			}
+		}
+	}
+	keys.all_off()
+}
+
+/// Find the value of a long option before the command line is parsed, given as `--name value` or `--name=value`
+fn option_value(args: &[OsString], name: &str) -> Option<OsString> {
+	let flag = format!("--{name}");
+	let prefix = format!("{flag}=");
+	args.iter().enumerate().find_map(|(i, arg)| {
+		let arg = arg.to_str()?;
+		if arg == flag {
+			args.get(i + 1).cloned()
+		} else {
+			arg.strip_prefix(&prefix).map(OsString::from)
+		}
+	})
+}
+
+/// Read the settings of the config file, and of a preset from it if one is named.
+/// Only a config file that was asked for has to exist
+fn load_settings(args: &[OsString]) -> Settings {
+	let preset = option_value(args, "preset").map(|p| p.to_string_lossy().into_owned());
+	let config = match option_value(args, "config") {
+		Some(path) => Config::load(&PathBuf::from(path)).unwrap_or_else(|e| exit_with(&e)),
+		None => match Config::default_path().filter(|path| path.exists()) {
+			Some(path) => Config::load(&path).unwrap_or_else(|e| exit_with(&e)),
+			None if preset.is_some() => exit_with(&"There is no config file to find presets in"),
+			None => Config::default(),
+		},
+	};
+	config
+		.settings(preset.as_deref())
+		.unwrap_or_else(|e| exit_with(&e))
+}
+
+/// Make each setting the default of every option with its name, so anything on the command line still wins
+fn with_settings(mut command: clap::Command, settings: Settings) -> clap::Command {
+	for (name, value) in settings {
+		let mut found = false;
+		for subcommand in command.get_subcommands_mut() {
+			let id = subcommand
+				.get_arguments()
+				.filter(|arg| !arg.is_global_set())
+				.find(|arg| match arg.get_long() {
+					Some(long) => long == name,
+					// Arguments without a long option, like the base note of `scale`, go by their id
+					None => arg.get_id().as_str().replace('_', "-") == name,
+				})
+				.map(|arg| arg.get_id().clone());
+			if let Some(id) = id {
+				found = true;
+				let value = value.clone();
+				*subcommand = subcommand
+					.clone()
+					.mut_arg(id, |arg| arg.default_value(value));
+			}
+		}
+		if !found {
+			exit_with(&format!("The config sets {name}, which is not an option"));
+		}
+	}
+	command
+}
+
 fn main() {
-	// Read arguments, greet user
-	let opt = Args::parse();
-	println!("{GREETING}");
-
-	// Set up audio playback
-	let (_stream, handle) = OutputStream::try_default().unwrap();
-	let sink = Sink::try_new(&handle).expect("Could not create sink");
-
-	// Define music source from Opt
-	if opt.pitch_mode {
-		let wave = SineWave::from(Pitch::from(opt.base_note));
-		println!("Playing single tone {}", opt.base_note);
-		// Play sine wave
-		sink.append(wave);
-	} else {
-		// Init procedural generator
-		let music = MusicMaker::new(opt.base_note, opt.scale, opt.octaves);
-		println!("{music}");
-		// Play random melody
-		sink.append(music);
+	let args = env::args_os().collect::<Vec<_>>();
+	let cli = with_settings(Cli::command(), load_settings(&args));
+	let parse = |args: &[OsString]| {
+		let matches = cli.clone().get_matches_from(args);
+		Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
 	};
-	// Sleep thread to allow music to play infinitely
-	sink.sleep_until_end();
+	let command = parse(&args).command.unwrap_or_else(|| {
+		// Playing is what happens without a subcommand, with the settings it would have
+		let args = [args.as_slice(), &["play".into()]].concat();
+		parse(&args).command.unwrap_or_else(|| unreachable!())
+	});
+	match command {
+		Command::Play {
+			quiet,
+			roll,
+			generate,
+		} => {
+			println!("{GREETING}");
+			let music = compose(generate);
+			let roll = (roll && io::stdout().is_terminal()).then(|| PianoRoll::new(music.key()));
+			if quiet {
+				play(music, None, None);
+			} else {
+				let (sender, receiver) = mpsc::channel();
+				play(music.set_events(sender), Some(receiver), roll);
+			}
+		},
+		Command::Tone { note, tuning } => {
+			println!("{GREETING}");
+			println!("Playing single tone {note}");
+			let pitch = Pitch::from(note).tuned(Hertz::from(tuning));
+			play(SineWave::from(pitch), None, None);
+		},
+		Command::Render {
+			output,
+			mut generate,
+		} => {
+			if generate.limit().is_none() {
+				generate.duration = Some(RENDER_SECONDS);
+			}
+			let music = compose(generate);
+			let duration = music.total_duration();
+			// Only a limit of notes leaves the length unknown, and a rhythm of rests may never reach it
+			let duration = duration.unwrap_or_else(|| seconds(MAX_RENDER_SECONDS));
+			write_wav(&output, music, duration).unwrap_or_else(|e| exit_with(&e));
+			println!("Wrote {}", output.display());
+		},
+		Command::Repl {
+			base_note,
+			scale,
+			octaves,
+		} => {
+			println!("{GREETING}");
+			repl(Session::new(Key::new(scale, base_note, octaves)));
+		},
+		Command::Keyboard {
+			base_note,
+			scale,
+			snap,
+			sound,
+		} => {
+			println!("{GREETING}");
+			let key = Key::new(scale, base_note, 1);
+			keyboard(Qwerty::new(key).with_snap(snap), &sound);
+		},
+		Command::Scale {
+			base_note,
+			scale,
+			octaves,
+		} => print_scale(base_note, scale, octaves),
+		Command::Chord {
+			chord,
+			base_note,
+			scale,
+		} => print_chord(&chord, Key::new(scale, base_note, 1)),
+		Command::Interval { from, to } => print_interval(from, to),
+		Command::Info { note, tuning } => print_info(note, Hertz::from(tuning)),
+		Command::Check {
+			cantus,
+			counterpoint,
+			species,
+			placement,
+		} => {
+			let exercise = exercise(&cantus, species, placement);
+			let line = parse_melody(&counterpoint).unwrap_or_else(|e| exit_with(&e));
+			print_counterpoint(&exercise, &line);
+		},
+		Command::Chorale {
+			melody,
+			base_note,
+			scale,
+			midi,
+			tempo,
+		} => {
+			let chorale = harmonize(&melody, Key::new(scale, base_note, 1));
+			if let Some(path) = midi {
+				music::midi::write_melodies(&path, &chorale.voices(), tempo)
+					.unwrap_or_else(|e| exit_with(&e));
+				println!("Wrote the chorale to {}", path.display());
+			}
+		},
+	}
 }
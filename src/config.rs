@@ -0,0 +1,131 @@
+//! A `Config` holds settings read from a TOML file, each named like the long command line option it stands in for.
+//! Named presets in `[presets.<name>]` tables override the settings above them:
+//!
+//! ```toml
+//! scale = "dorian"
+//! tempo = 90
+//! walk = true
+//!
+//! [presets.drone]
+//! base-note = "D2"
+//! waveform = "sawtooth"
+//! release = 0.5
+//! ```
+
+use std::{collections::BTreeMap, env, fs, io, path::Path, path::PathBuf, str::FromStr};
+
+/// Each setting and its value, written the way it would be typed on the command line
+pub type Settings = BTreeMap<String, String>;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Config {
+	settings: Settings,
+	presets: BTreeMap<String, Settings>,
+}
+
+impl Config {
+	/// Read a config file
+	pub fn load(path: &Path) -> Result<Self, io::Error> {
+		let text = fs::read_to_string(path).map_err(|e| {
+			io::Error::new(e.kind(), format!("Could not read {}: {e}", path.display()))
+		})?;
+		Self::from_str(&text)
+			.map_err(|e| io::Error::new(e.kind(), format!("In {}: {e}", path.display())))
+	}
+
+	/// Find where the config file lives when none is given: `music/config.toml` in `$XDG_CONFIG_HOME`,
+	/// or in `~/.config` without it
+	#[must_use]
+	pub fn default_path() -> Option<PathBuf> {
+		let base = env::var_os("XDG_CONFIG_HOME")
+			.filter(|dir| !dir.is_empty())
+			.map(PathBuf::from)
+			.or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+		Some(base.join("music").join("config.toml"))
+	}
+
+	/// List the names of the presets, in alphabetical order
+	#[must_use]
+	pub fn presets(&self) -> Vec<&str> {
+		self.presets.keys().map(String::as_str).collect()
+	}
+
+	/// Get the settings, with those of a preset laid over them if one is named
+	pub fn settings(&self, preset: Option<&str>) -> Result<Settings, io::Error> {
+		let mut ret = self.settings.clone();
+		if let Some(name) = preset {
+			let preset = self.presets.get(name).ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::NotFound,
+					format!(
+						"There is no preset called {name}, try one of: {}",
+						self.presets().join(", ")
+					),
+				)
+			})?;
+			ret.extend(preset.clone());
+		}
+		Ok(ret)
+	}
+}
+
+impl FromStr for Config {
+	type Err = io::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let table = s
+			.parse::<toml::Table>()
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+		let mut ret = Self::default();
+		for (name, value) in table {
+			if name == "presets" {
+				let toml::Value::Table(presets) = value else {
+					return Err(io::Error::new(
+						io::ErrorKind::InvalidData,
+						"presets should be a table of named presets, like [presets.drone]",
+					));
+				};
+				for (preset, settings) in presets {
+					let toml::Value::Table(settings) = settings else {
+						return Err(io::Error::new(
+							io::ErrorKind::InvalidData,
+							format!("Preset {preset} should be a table, like [presets.{preset}]"),
+						));
+					};
+					let settings = settings
+						.into_iter()
+						.map(|(name, value)| setting(&name, value))
+						.collect::<Result<_, _>>()?;
+					ret.presets.insert(preset, settings);
+				}
+			} else {
+				let (name, value) = setting(&name, value)?;
+				ret.settings.insert(name, value);
+			}
+		}
+		Ok(ret)
+	}
+}
+
+/// Write a setting the way it would be typed on the command line, with lists separated by commas.
+/// Names may use underscores in place of dashes
+fn setting(name: &str, value: toml::Value) -> Result<(String, String), io::Error> {
+	let value = match value {
+		toml::Value::String(s) => s,
+		toml::Value::Integer(i) => i.to_string(),
+		toml::Value::Float(f) => f.to_string(),
+		toml::Value::Boolean(b) => b.to_string(),
+		toml::Value::Array(values) => values
+			.into_iter()
+			.map(|value| setting(name, value).map(|(_, value)| value))
+			.collect::<Result<Vec<_>, _>>()?
+			.join(","),
+		toml::Value::Datetime(_) | toml::Value::Table(_) => {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("{name} should be a string, number, true or false, or a list of them"),
+			))
+		},
+	};
+	Ok((name.replace('_', "-"), value))
+}
@@ -0,0 +1,290 @@
+//! The `Error` type says what went wrong reading or building notes, keys, chords and melodies, so callers can match on the failure.
+//! It turns into an `io::Error` wherever one is expected, keeping its message
+
+use crate::theory::{
+	chord::Chord,
+	counterpoint::{Placement, Species},
+	interval::{Quality, MAX_NUMBER},
+	key_signature::MAX_ACCIDENTALS,
+	note::Note,
+	piano_key::PianoKey,
+	scale::{Mode, Scale},
+	tone_row::ROW_LENGTH,
+};
+use std::{error, fmt, io};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+	/// A note named by something other than the letters A to G
+	BadLetter(String),
+	/// An accidental other than b, bb, #, ## or x, or their symbols
+	BadAccidental(String),
+	/// Nothing where a note was expected
+	MissingNote,
+	/// A piano key without an octave number, like the 4 of C4
+	MissingOctave(String),
+	/// An octave above the top of the piano
+	OctaveOutOfRange(u8),
+	/// A scale this crate doesn't know, with the names that come closest
+	UnknownScale {
+		name: String,
+		suggestions: Vec<&'static str>,
+	},
+	/// A negative frequency, which has no whole number of cycles
+	NegativeHertz,
+	/// A frequency with a fraction of a cycle, where only whole ones will do
+	FractionalHertz,
+	/// An accidental moving a note further than a double sharp or flat
+	NoAccidental(i8),
+	/// An interval quality other than d, m, P, M or A
+	BadQuality(String),
+	/// An interval quality with no number after it, like the 3 of M3
+	MissingIntervalNumber(String),
+	/// An interval number that isn't a whole number
+	BadIntervalNumber(String),
+	/// An interval number below a unison or above the largest interval
+	IntervalNumberOutOfRange(u8),
+	/// A quality the interval can't have, like a major fifth
+	ImpossibleInterval { quality: Quality, number: u8 },
+	/// More sharps or flats than fit in a key signature
+	TooManyAccidentals(i8),
+	/// A key that needs double sharps or flats in its signature
+	TheoreticalKey { tonic: Note, mode: Mode },
+	/// A scale that isn't written with a key signature
+	NoKeySignature(Scale),
+	/// A scale without the diatonic chords that Roman numerals and chorales are built from
+	NotDiatonic(Scale),
+	/// A character that names no pitch class, which are 0 to 9 with T or A for 10 and E or B for 11
+	BadPitchClass(char),
+	/// A row form other than P, R, I or RI
+	BadRowForm(String),
+	/// A row form without a transposition from 0 to 11
+	BadTransposition(String),
+	/// A row with the wrong number of pitch classes
+	RowLength(usize),
+	/// A row missing one of the pitch classes
+	IncompleteRow(u8),
+	/// A chord quality this crate doesn't know
+	BadChordQuality(String),
+	/// A chord symbol that isn't a note followed by a quality
+	BadChordSymbol(String),
+	/// A Roman numeral that names no scale degree
+	BadNumeral(String),
+	/// A progression with no chords in it
+	EmptyProgression,
+	/// No voicing of the second chord follows the first within the voice leading's constraints
+	NoVoicing { from: Chord, to: Chord },
+	/// No root position voicing of the chord fits the ranges of the voices
+	NoRootPosition(Chord),
+	/// A species other than 1 to 5
+	BadSpecies(String),
+	/// A placement other than above or below
+	BadPlacement(String),
+	/// A cantus firmus shorter than three notes
+	ShortCantus(usize),
+	/// No counterpoint of the species fits the cantus
+	NoCounterpoint {
+		species: Species,
+		placement: Placement,
+	},
+	/// A melody without notes, or with a note of no length
+	EmptyMelody,
+	/// A length in steps that isn't a whole number
+	BadLength(String),
+	/// A melody note without a length, like the /2 of 4/2
+	MissingLength(String),
+	/// No chord in the key fits the melody note, counting notes from 1
+	NoHarmony {
+		note: usize,
+		key: PianoKey,
+		tonic: Note,
+		scale: Scale,
+	},
+	/// A key with no MIDI note number
+	NoMidiNumber(PianoKey),
+	/// A scale degree other than 1 to 7, with up to two sharps or flats
+	BadDegree(String),
+	/// A scale degree moved by more than two semitones
+	AlteredTooFar(String),
+	/// A weight that isn't a number from 0 up, or a preset, with the presets to try
+	BadWeight {
+		word: String,
+		presets: Vec<&'static str>,
+	},
+	/// A form with something other than letters
+	BadForm(String),
+	/// A center for a random walk other than tonic or middle
+	BadCenter(String),
+	/// A Euclidean rhythm with no steps, or more onsets than steps
+	BadEuclidean { onsets: u16, steps: u16 },
+	/// A probability pattern with no steps, or chances outside 0 to 1
+	BadChances,
+	/// A chance of resting outside 0 to 1, as written
+	BadChance(String),
+	/// A rhythm that is none of steady, a length, a Euclidean rhythm or chances
+	BadRhythm(String),
+}
+
+impl fmt::Display for Error {
+	#[allow(clippy::too_many_lines)]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Error::BadLetter(s) => write!(f, "{s} is not a valid note, notes are named A to G"),
+			Error::BadAccidental(s) => {
+				write!(f, "{s} is not a valid accidental, try b, bb, # or ##")
+			},
+			Error::MissingNote => write!(f, "Expected a note, like C or F#"),
+			Error::MissingOctave(s) => {
+				write!(f, "{s} is missing an octave, try something like {s}4")
+			},
+			Error::OctaveOutOfRange(octave) => {
+				write!(
+					f,
+					"{octave} is too high, the piano only goes up to octave 8"
+				)
+			},
+			Error::UnknownScale { name, suggestions } => match suggestions.as_slice() {
+				[] => write!(f, "Unknown scale {name}"),
+				[only] => write!(f, "Unknown scale {name}, did you mean {only}?"),
+				[rest @ .., last] => write!(
+					f,
+					"Unknown scale {name}, did you mean {} or {last}?",
+					rest.join(", ")
+				),
+			},
+			Error::NegativeHertz => write!(f, "Cannot convert negative Hertz to u32"),
+			Error::FractionalHertz => write!(f, "Cannot convert non-integer Hertz to u32"),
+			Error::NoAccidental(semitones) => {
+				write!(f, "No accidental moves a note {semitones} semitones")
+			},
+			Error::BadQuality(s) => write!(f, "{s} is not a valid interval quality"),
+			Error::MissingIntervalNumber(s) => write!(f, "{s} is missing an interval number"),
+			Error::BadIntervalNumber(s) => write!(f, "{s} is not a valid interval number"),
+			Error::IntervalNumberOutOfRange(0) => {
+				write!(f, "Interval numbers start at 1 for a unison")
+			},
+			Error::IntervalNumberOutOfRange(number) => write!(
+				f,
+				"Interval numbers only go up to {MAX_NUMBER}, got {number}"
+			),
+			Error::ImpossibleInterval { quality, number } => {
+				write!(f, "A {number} cannot have quality {quality:?}")
+			},
+			Error::TooManyAccidentals(fifths) => write!(
+				f,
+				"A key signature can hold at most {MAX_ACCIDENTALS} sharps or flats, not {}",
+				fifths.unsigned_abs()
+			),
+			Error::TheoreticalKey { tonic, mode } => write!(
+				f,
+				"{tonic} {mode:?} is a theoretical key with no standard signature"
+			),
+			Error::NoKeySignature(scale) => write!(f, "The {scale} has no key signature"),
+			Error::NotDiatonic(scale) => write!(
+				f,
+				"The {scale} has no diatonic chords, try a major or minor key"
+			),
+			Error::BadPitchClass(c) => write!(f, "{c} is not a valid pitch class"),
+			Error::BadRowForm(s) => write!(
+				f,
+				"{s} is not a row form, try P, R, I or RI followed by a number"
+			),
+			Error::BadTransposition(s) => write!(f, "{s} needs a transposition from 0 to 11"),
+			Error::RowLength(length) => write!(
+				f,
+				"A row needs exactly {ROW_LENGTH} pitch classes, found {length}"
+			),
+			Error::IncompleteRow(pc) => {
+				write!(f, "A row must use every pitch class once, {pc} is missing")
+			},
+			Error::BadChordQuality(s) => write!(f, "{s} is not a chord quality"),
+			Error::BadChordSymbol(s) => write!(
+				f,
+				"{s} is not a chord symbol, try something like C, F#m or Bb7"
+			),
+			Error::BadNumeral(s) => write!(
+				f,
+				"{s} is not a Roman numeral, try something like I, ii, V7 or viio"
+			),
+			Error::EmptyProgression => write!(f, "A progression needs at least one chord"),
+			Error::NoVoicing { from, to } => {
+				write!(
+					f,
+					"No voicing of {to} follows {from} within the constraints"
+				)
+			},
+			Error::NoRootPosition(chord) => write!(
+				f,
+				"No root position voicing of {chord} fits the ranges of the voices"
+			),
+			Error::BadSpecies(s) => write!(f, "{s} is not a species, try a number from 1 to 5"),
+			Error::BadPlacement(s) => write!(f, "{s} is not a placement, try above or below"),
+			Error::ShortCantus(length) => write!(
+				f,
+				"A cantus firmus needs at least three notes, found {length}"
+			),
+			Error::NoCounterpoint { species, placement } => write!(
+				f,
+				"Could not write {species} counterpoint {placement} this cantus, try another key or placement"
+			),
+			Error::EmptyMelody => write!(
+				f,
+				"A melody needs at least one note, and every note needs a length"
+			),
+			Error::BadLength(s) => {
+				write!(f, "{s} is not a length in steps, try a whole number like 4")
+			},
+			Error::MissingLength(s) => write!(f, "{s} needs a degree and a length, like 4/2"),
+			Error::NoHarmony {
+				note,
+				key,
+				tonic,
+				scale,
+			} => write!(
+				f,
+				"Could not harmonize note {note} ({key}) in the {tonic} {scale}"
+			),
+			Error::NoMidiNumber(key) => write!(f, "{key} has no MIDI note number"),
+			Error::BadDegree(s) => write!(f, "{s} is not a scale degree"),
+			Error::AlteredTooFar(s) => write!(f, "{s} is altered by more than two semitones"),
+			Error::BadWeight { word, presets } => write!(
+				f,
+				"{word} is not a weight, try numbers like 4,1,2 or one of: {}",
+				presets.join(", ")
+			),
+			Error::BadForm(s) => write!(f, "{s} is not a form, try letters like AABA"),
+			Error::BadCenter(s) => write!(f, "{s} is not a center, try tonic or middle"),
+			Error::BadEuclidean { onsets, steps } => write!(
+				f,
+				"E({onsets},{steps}) needs at least one step and no more onsets than steps"
+			),
+			Error::BadChances => write!(
+				f,
+				"A probability pattern needs at least one step, with chances from 0 to 1"
+			),
+			Error::BadChance(s) => write!(
+				f,
+				"{s} is not a chance of resting, try a number from 0 to 1"
+			),
+			Error::BadRhythm(s) => write!(
+				f,
+				"{s} is not a rhythm, try steady, a length in steps, E(3,8) or chances like 1,0,0.5,0"
+			),
+		}
+	}
+}
+
+impl error::Error for Error {}
+
+impl From<Error> for io::Error {
+	fn from(e: Error) -> Self {
+		let kind = match e {
+			Error::NoVoicing { .. }
+			| Error::NoRootPosition(_)
+			| Error::NoCounterpoint { .. }
+			| Error::NoHarmony { .. } => io::ErrorKind::NotFound,
+			_ => io::ErrorKind::InvalidInput,
+		};
+		io::Error::new(kind, e)
+	}
+}
@@ -1,13 +1,34 @@
 #![warn(clippy::pedantic)]
 #![allow(clippy::missing_errors_doc)]
 
+//! Music theory, with generators that play it.
+//! The `theory` feature builds the theory alone, without any audio libraries.
+//! `synth` adds the generators and synths that turn it into samples, and `playback`
+//! plays them on a sound device through rodio. `cli`, on by default, adds the config file
+//! the command line program reads
+
+#[cfg(feature = "cli")]
+pub mod config;
+#[cfg(feature = "theory")]
+pub mod error;
+#[cfg(feature = "theory")]
+pub mod midi;
+#[cfg(feature = "synth")]
 pub mod output;
+#[cfg(feature = "theory")]
+pub mod qwerty;
+#[cfg(feature = "synth")]
+pub mod repl;
+#[cfg(all(feature = "serde", feature = "theory"))]
+mod serde_impls;
+#[cfg(feature = "theory")]
 pub mod theory;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "theory"))]
 mod test;
 
 /// Helper function to produce an f32 from an f64, isolating the possible truncation.
+#[cfg(feature = "synth")]
 fn f64_to_f32(f: f64) -> f32 {
 	#![allow(clippy::cast_possible_truncation)]
 	f as f32
@@ -0,0 +1,155 @@
+//! The midi module reads Standard MIDI Files into melodies, and writes melodies back out
+
+use crate::{
+	error::Error,
+	theory::{
+		melody::{MelodyNote, STEPS_PER_BEAT},
+		piano_key::PianoKey,
+	},
+};
+use midly::{Format, Header, MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind};
+use std::{fs, io, path::Path};
+
+/// General MIDI reserves this channel for percussion, counting from zero
+const DRUM_CHANNEL: u8 = 9;
+
+/// The resolution of written files, which divides evenly into steps
+const TICKS_PER_BEAT: u16 = 96;
+
+/// Read a MIDI file, producing one melody per track that plays any notes
+pub fn read_melodies(path: impl AsRef<Path>) -> Result<Vec<Vec<MelodyNote>>, io::Error> {
+	parse_melodies(&fs::read(path)?)
+}
+
+/// Parse the bytes of a MIDI file, producing one melody per track that plays any notes.
+/// Where a track plays several notes at once only the highest is kept,
+/// and each note lasts until the next one starts. Drum tracks are skipped.
+pub fn parse_melodies(bytes: &[u8]) -> Result<Vec<Vec<MelodyNote>>, io::Error> {
+	let smf = Smf::parse(bytes).map_err(|e| {
+		io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("Could not read MIDI file: {e}"),
+		)
+	})?;
+	let ticks_per_beat = match smf.header.timing {
+		Timing::Metrical(ticks) => u64::from(ticks.as_int()),
+		// Without a beat to go on, assume 120 beats per minute
+		Timing::Timecode(fps, subframes) => u64::from(fps.as_int()) * u64::from(subframes) / 2,
+	}
+	.max(1);
+	let mut ret = Vec::new();
+	for track in &smf.tracks {
+		let mut tick = 0u64;
+		// The start of each note and the key it plays
+		let mut onsets: Vec<(u64, u8)> = Vec::new();
+		let mut end = None;
+		for event in track {
+			tick += u64::from(event.delta.as_int());
+			let TrackEventKind::Midi { channel, message } = event.kind else {
+				continue;
+			};
+			if channel.as_int() == DRUM_CHANNEL {
+				continue;
+			}
+			match message {
+				MidiMessage::NoteOn { key, vel } if vel.as_int() > 0 => {
+					let key = key.as_int();
+					match onsets.last_mut() {
+						Some((start, highest)) if *start == tick => *highest = (*highest).max(key),
+						_ => onsets.push((tick, key)),
+					}
+				},
+				MidiMessage::NoteOn { key, .. } | MidiMessage::NoteOff { key, .. }
+					if onsets.last().map(|(_, k)| *k) == Some(key.as_int()) =>
+				{
+					end = Some(tick);
+				},
+				_ => {},
+			}
+		}
+		let melody = onsets
+			.iter()
+			.enumerate()
+			.filter_map(|(i, &(start, key))| {
+				let finish = onsets
+					.get(i + 1)
+					.map(|(next, _)| *next)
+					.or(end)
+					.filter(|finish| *finish > start)
+					.unwrap_or(start + ticks_per_beat);
+				let steps = ((finish - start) * u64::from(STEPS_PER_BEAT) + ticks_per_beat / 2)
+					/ ticks_per_beat;
+				Some(MelodyNote::new(
+					PianoKey::from_midi(key)?,
+					u16::try_from(steps.max(1)).unwrap_or(u16::MAX),
+				))
+			})
+			.collect::<Vec<_>>();
+		if !melody.is_empty() {
+			ret.push(melody);
+		}
+	}
+	Ok(ret)
+}
+
+/// Write melodies to a MIDI file, one track each, all starting together
+pub fn write_melodies(
+	path: impl AsRef<Path>,
+	melodies: &[Vec<MelodyNote>],
+	tempo: f64,
+) -> Result<(), io::Error> {
+	fs::write(path, encode_melodies(melodies, tempo)?)
+}
+
+/// Encode melodies as the bytes of a MIDI file, one track each at the given beats per minute
+pub fn encode_melodies(melodies: &[Vec<MelodyNote>], tempo: f64) -> Result<Vec<u8>, io::Error> {
+	let ticks_per_step = u32::from(TICKS_PER_BEAT / STEPS_PER_BEAT);
+	#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+	let micros_per_beat = (60_000_000.0 / tempo.max(1.0)).round() as u32;
+	let mut tracks = Vec::new();
+	for (i, melody) in melodies.iter().enumerate() {
+		let mut track = Vec::new();
+		if i == 0 {
+			track.push(TrackEvent {
+				delta: 0.into(),
+				kind: TrackEventKind::Meta(MetaMessage::Tempo(
+					micros_per_beat.min(0xFF_FFFF).into(),
+				)),
+			});
+		}
+		// Each melody gets its own channel, stepping around the drums
+		let channel = u8::try_from(i % 15).unwrap_or_default();
+		let channel = if channel >= DRUM_CHANNEL {
+			channel + 1
+		} else {
+			channel
+		};
+		for note in melody {
+			let key = note.key.midi().ok_or(Error::NoMidiNumber(note.key))?;
+			let event = |delta: u32, vel: u8| TrackEvent {
+				delta: delta.into(),
+				kind: TrackEventKind::Midi {
+					channel: channel.into(),
+					message: MidiMessage::NoteOn {
+						key: key.into(),
+						vel: vel.into(),
+					},
+				},
+			};
+			track.push(event(0, 64));
+			track.push(event(u32::from(note.steps) * ticks_per_step, 0));
+		}
+		track.push(TrackEvent {
+			delta: 0.into(),
+			kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+		});
+		tracks.push(track);
+	}
+	let smf = Smf {
+		header: Header::new(Format::Parallel, Timing::Metrical(TICKS_PER_BEAT.into())),
+		tracks,
+	};
+	let mut ret = Vec::new();
+	smf.write_std(&mut ret)?;
+	Ok(ret)
+}
@@ -0,0 +1,125 @@
+use super::{envelope::Envelope, signal::Signal, waveform::Waveform};
+use crate::{
+	f64_to_f32,
+	theory::{
+		hertz::{Hertz, SAMPLE_RATE, STANDARD_PITCH},
+		piano_key::PianoKey,
+		pitch::Pitch,
+	},
+};
+use std::time::Duration;
+
+/// Plays a cycle of chords quietly, in sine waves unless told otherwise, to sit underneath a melody
+pub struct ChordPlayer {
+	chords: Vec<Vec<PianoKey>>,
+	/// The frequencies of each chord's notes
+	frequencies: Vec<Vec<f64>>,
+	current_chord: usize,
+	current_sample: u32,
+	sample_rate: u32,
+	/// Samples each chord is held for
+	chord_samples: f64,
+	amplitude: f64,
+	waveform: Waveform,
+	/// Shapes each chord, which otherwise starts and stops at full volume
+	envelope: Option<Envelope>,
+}
+
+impl ChordPlayer {
+	/// Hold each chord for a number of beats at a tempo in beats per minute
+	///
+	/// # Panics
+	///
+	/// This function would panic if the sample rate did not fit in a `u32`, but it is a small constant.
+	#[must_use]
+	pub fn new(chords: &[Vec<PianoKey>], beats_per_chord: u16, tempo: f64) -> Self {
+		let sample_rate = u32::try_from(SAMPLE_RATE).unwrap();
+		Self {
+			chords: chords.to_vec(),
+			frequencies: Vec::new(),
+			current_chord: 0,
+			current_sample: 0,
+			sample_rate,
+			chord_samples: f64::from(beats_per_chord.max(1)) * 60.0 / tempo.max(1.0)
+				* f64::from(sample_rate),
+			amplitude: 0.3,
+			waveform: Waveform::default(),
+			envelope: None,
+		}
+		.with_tuning(STANDARD_PITCH)
+	}
+
+	/// Set how loud the chords are, where 1 is as loud as the melody
+	#[must_use]
+	pub fn with_amplitude(mut self, amplitude: f64) -> Self {
+		self.amplitude = amplitude.max(0.0);
+		self
+	}
+
+	/// Tune A4 to another frequency, moving every other key with it
+	#[must_use]
+	pub fn with_tuning(mut self, a4: Hertz) -> Self {
+		self.frequencies = self
+			.chords
+			.iter()
+			.map(|chord| {
+				chord
+					.iter()
+					.map(|&k| Pitch::from(k).tuned(a4).into())
+					.collect()
+			})
+			.collect();
+		self
+	}
+
+	/// Play the chords in another waveform
+	#[must_use]
+	pub fn with_waveform(mut self, waveform: Waveform) -> Self {
+		self.waveform = waveform;
+		self
+	}
+
+	/// Shape each chord with an envelope, releasing it before the next chord starts
+	#[must_use]
+	pub fn with_envelope(mut self, envelope: Envelope) -> Self {
+		self.envelope = Some(envelope);
+		self
+	}
+}
+
+impl Iterator for ChordPlayer {
+	type Item = f32;
+	fn next(&mut self) -> Option<Self::Item> {
+		let Some(chord) = self.frequencies.get(self.current_chord) else {
+			return Some(0.0);
+		};
+		let time = f64::from(self.current_sample) / f64::from(self.sample_rate);
+		#[allow(clippy::cast_precision_loss)]
+		let mut value = chord
+			.iter()
+			.map(|frequency| self.waveform.sample(frequency * time))
+			.sum::<f64>()
+			* self.amplitude
+			/ chord.len().max(1) as f64;
+		if let Some(envelope) = &self.envelope {
+			let length = self.chord_samples / f64::from(self.sample_rate);
+			value *= envelope.level(
+				Duration::from_secs_f64(time),
+				Duration::from_secs_f64(length),
+			);
+		}
+		self.current_sample += 1;
+		if f64::from(self.current_sample) >= self.chord_samples {
+			self.current_sample = 0;
+			self.current_chord = (self.current_chord + 1) % self.frequencies.len();
+		}
+		Some(f64_to_f32(value))
+	}
+}
+
+impl Signal for ChordPlayer {
+	#[inline]
+	fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+}
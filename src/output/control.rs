@@ -0,0 +1,81 @@
+//! A `Controller` changes a `MusicMaker` from another thread while it plays.
+//! Changes queue up and take effect at the start of the next note or rest
+
+use super::waveform::Waveform;
+use crate::theory::{piano_key::PianoKey, scale::Scale};
+use std::{
+	io,
+	sync::mpsc::{Receiver, Sender},
+};
+
+/// One change to apply at the next note boundary
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Change {
+	BaseNote(PianoKey),
+	Scale(Scale),
+	Octaves(u8),
+	Tempo(f64),
+	Waveform(Waveform),
+	Volume(f64),
+}
+
+/// A handle on playing music that any thread can use. Cloning it gives another handle on the same music
+#[derive(Debug, Clone)]
+pub struct Controller(Sender<Change>);
+
+impl Controller {
+	pub(crate) fn new(sender: Sender<Change>) -> Self {
+		Self(sender)
+	}
+
+	/// Queue a change, failing once the music has finished and nothing is listening
+	fn send(&self, change: Change) -> Result<(), io::Error> {
+		self.0.send(change).map_err(|_| {
+			io::Error::new(io::ErrorKind::BrokenPipe, "The music has already finished")
+		})
+	}
+
+	/// Move the key to a new base note, keeping its scale and range
+	pub fn set_base_note(&self, base_note: PianoKey) -> Result<(), io::Error> {
+		self.send(Change::BaseNote(base_note))
+	}
+
+	pub fn set_scale(&self, scale: Scale) -> Result<(), io::Error> {
+		self.send(Change::Scale(scale))
+	}
+
+	pub fn set_octaves(&self, octaves: u8) -> Result<(), io::Error> {
+		self.send(Change::Octaves(octaves))
+	}
+
+	/// Set the speed in beats per minute
+	pub fn set_tempo(&self, tempo: f64) -> Result<(), io::Error> {
+		self.send(Change::Tempo(finite(tempo, "tempo")?))
+	}
+
+	pub fn set_waveform(&self, waveform: Waveform) -> Result<(), io::Error> {
+		self.send(Change::Waveform(waveform))
+	}
+
+	/// Set how loud the music is, from 0 for silent to 1 for full volume
+	pub fn set_volume(&self, volume: f64) -> Result<(), io::Error> {
+		self.send(Change::Volume(finite(volume, "volume")?))
+	}
+}
+
+/// Turn away infinities and NaN, which would stay in the music and spoil every sample after them
+fn finite(value: f64, name: &str) -> Result<f64, io::Error> {
+	if value.is_finite() {
+		Ok(value)
+	} else {
+		Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			format!("{value} is not a {name}"),
+		))
+	}
+}
+
+/// Take every change queued so far without waiting for more
+pub(crate) fn pending(changes: &Receiver<Change>) -> Vec<Change> {
+	changes.try_iter().collect()
+}
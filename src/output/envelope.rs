@@ -0,0 +1,55 @@
+//! An `Envelope` shapes how loud a note is over its life, from the moment it starts to after it is let go
+
+use std::time::Duration;
+
+/// How a note swells when it starts and dies away when it ends
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Envelope {
+	/// Time to rise to full volume
+	pub attack: Duration,
+	/// Time to fall from full volume to the sustain level
+	pub decay: Duration,
+	/// Level held while the note lasts, from 0 to 1
+	pub sustain: f64,
+	/// Time to fall silent once the note is let go
+	pub release: Duration,
+}
+
+impl Default for Envelope {
+	/// A quick start that settles a little, and a short tail
+	fn default() -> Self {
+		Self {
+			attack: Duration::from_millis(10),
+			decay: Duration::from_millis(150),
+			sustain: 0.7,
+			release: Duration::from_millis(250),
+		}
+	}
+}
+
+impl Envelope {
+	/// Find how loud a note of known length is some time after it started, from 0 to 1.
+	/// The release happens within the note, so it is silent by the time the next one starts
+	#[must_use]
+	pub fn level(&self, at: Duration, length: Duration) -> f64 {
+		let sustain = self.sustain.clamp(0.0, 1.0);
+		let at = at.as_secs_f64();
+		let attack = self.attack.as_secs_f64();
+		let decay = self.decay.as_secs_f64();
+		let level = if at < attack {
+			at / attack
+		} else if at < attack + decay {
+			1.0 - (1.0 - sustain) * (at - attack) / decay
+		} else {
+			sustain
+		};
+		let left = (length.as_secs_f64() - at).max(0.0);
+		let release = self.release.as_secs_f64();
+		if left < release {
+			level * left / release
+		} else {
+			level
+		}
+	}
+}
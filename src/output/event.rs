@@ -0,0 +1,51 @@
+//! Note events report each note the generator plays, so another thread can show what is sounding
+
+use crate::theory::{hertz::Hertz, key::Key, piano_key::PianoKey, pitch::Pitch};
+use std::{fmt, time::Duration};
+
+/// A note starting to sound
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NoteEvent {
+	pub key: PianoKey,
+	/// The frequency in Hertz, in standard tuning unless the event says otherwise
+	pub frequency: f64,
+	/// The scale degree counting from 1 for the base note, if the note is in the scale
+	pub degree: Option<usize>,
+	/// When the note starts, measured in audio time from the first sample
+	pub at: Duration,
+}
+
+impl NoteEvent {
+	#[must_use]
+	pub fn new(key: PianoKey, scale: Key, at: Duration) -> Self {
+		let pitch_class = key.note.pitch_class();
+		Self {
+			key,
+			frequency: Pitch::from(key).into(),
+			degree: scale
+				.get_notes()
+				.iter()
+				.position(|n| n.pitch_class() == pitch_class)
+				.map(|i| i + 1),
+			at,
+		}
+	}
+
+	/// Give the frequency the key sounds at when A4 is tuned to another frequency
+	#[must_use]
+	pub fn with_tuning(mut self, a4: Hertz) -> Self {
+		self.frequency = Pitch::from(self.key).tuned(a4).into();
+		self
+	}
+}
+
+impl fmt::Display for NoteEvent {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:<5}{:>8.2} Hz", self.key.to_string(), self.frequency)?;
+		match self.degree {
+			Some(degree) => write!(f, "  degree {degree}"),
+			None => write!(f, "  outside the scale"),
+		}
+	}
+}
@@ -1,7 +1,30 @@
 //! The output module is concerned with generating soundwaves from notes
-//! It also defines the generator seed strategies
+//! It also defines the generator seed strategies, the rhythms that time them,
+//! and a chord player to accompany them. Anything it plays can also be rendered to a WAV file,
+//! and the notes reported as they start and drawn on a piano roll.
+//! A synth plays keys live as they are pressed and released.
+//! All of it needs the `synth` feature, and playing on a device through rodio needs `playback`
 
+mod chord_player;
+mod control;
+mod envelope;
+mod event;
 mod music_maker;
-mod seed;
+mod piano_roll;
+pub mod rhythm;
+pub mod seed;
+mod signal;
+mod synth;
+mod wav;
+mod waveform;
 
-pub use music_maker::MusicMaker;
+pub use chord_player::ChordPlayer;
+pub use control::Controller;
+pub use envelope::Envelope;
+pub use event::NoteEvent;
+pub use music_maker::{Limit, MusicMaker};
+pub use piano_roll::PianoRoll;
+pub use signal::Signal;
+pub use synth::{Synth, SynthKeys};
+pub use wav::{encode_wav, write_wav};
+pub use waveform::Waveform;
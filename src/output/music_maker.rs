@@ -1,18 +1,78 @@
-use super::seed::{Random, Seed};
+use super::{
+	control::{self, Change, Controller},
+	envelope::Envelope,
+	event::NoteEvent,
+	rhythm::{Pulse, Rhythm, Steady},
+	seed::{Random, Seed},
+	signal::Signal,
+	waveform::Waveform,
+};
 use crate::{
 	f64_to_f32,
-	theory::{hertz::SAMPLE_RATE, key::Key, piano_key::PianoKey, pitch::Pitch, scale::Scale},
+	theory::{
+		hertz::{Hertz, SAMPLE_RATE, STANDARD_PITCH},
+		key::Key,
+		melody::STEPS_PER_BEAT,
+		piano_key::PianoKey,
+		pitch::Pitch,
+		scale::Scale,
+	},
+};
+use std::{
+	fmt,
+	str::FromStr,
+	sync::{
+		mpsc::{self, Receiver, Sender},
+		Arc, Mutex,
+	},
+	time::Duration,
 };
-use rodio::source::Source;
-use std::{f64::consts::PI, fmt, str::FromStr, time::Duration};
+
+/// When generated music stops, rather than playing forever
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+	/// After this many notes, not counting rests
+	Notes(u32),
+	/// After this many bars of four beats
+	Bars(u32),
+	/// After this much time
+	Duration(Duration),
+}
 
 pub struct MusicMaker {
 	key: Key,
 	seed: Box<dyn Seed>,
+	rhythm: Box<dyn Rhythm>,
 	current_note: PianoKey,
+	current_pulse: Pulse,
 	current_sample: u32,
 	sample_rate: u32,
+	/// Beats per minute
+	tempo: f64,
+	waveform: Waveform,
+	/// Shapes each note, which otherwise starts and stops at full volume
+	envelope: Option<Envelope>,
+	/// The frequency A4 sounds at
+	tuning: Hertz,
+	/// From 0 for silent to 1 for full volume
 	volume: f64,
+	/// Plays underneath the melody, sharing its sample rate
+	accompaniment: Option<Box<dyn Iterator<Item = Sample> + Send>>,
+	limit: Option<Limit>,
+	/// How long the music takes to fade out before it stops
+	fade: Duration,
+	/// Samples played so far
+	elapsed: u64,
+	/// The sample the tempo last changed on while playing, and the beats played before it,
+	/// so a limit of bars counts earlier beats at the tempo they were played at
+	tempo_changed: (u64, f64),
+	notes_played: u32,
+	/// The samples the last note starts and ends on, once it has started
+	last_note: Option<(u64, u64)>,
+	/// Where to report each note as it starts
+	events: Option<Sender<NoteEvent>>,
+	/// Changes from controllers on other threads, waiting for the next note
+	changes: Option<Receiver<Change>>,
 }
 
 impl Default for MusicMaker {
@@ -20,10 +80,26 @@ impl Default for MusicMaker {
 		Self {
 			key: Key::default(),
 			seed: Box::<Random>::default(),
+			rhythm: Box::<Steady>::default(),
 			current_note: PianoKey::from_str("C4").unwrap(),
+			// An empty pulse makes the very first sample ask the seed for the opening note
+			current_pulse: Pulse::Rest(0),
 			current_sample: u32::default(),
 			sample_rate: u32::try_from(SAMPLE_RATE).unwrap(),
-			volume: 2.0,
+			tempo: 60.0,
+			waveform: Waveform::default(),
+			envelope: None,
+			tuning: STANDARD_PITCH,
+			volume: 1.0,
+			accompaniment: None,
+			limit: None,
+			fade: Duration::from_secs(1),
+			elapsed: 0,
+			tempo_changed: (0, 0.0),
+			notes_played: 0,
+			last_note: None,
+			events: None,
+			changes: None,
 		}
 	}
 }
@@ -36,14 +112,199 @@ impl MusicMaker {
 		Self::default().set_key(base_note, scale, octaves)
 	}
 	fn get_frequency(&mut self) -> Sample {
-		let pitch = Pitch::from(self.current_note);
+		let pitch = Pitch::from(self.current_note).tuned(self.tuning);
 		f64_to_f32(pitch.into())
 	}
 	fn new_note(&mut self) {
+		self.apply_changes();
+		self.current_pulse = self.rhythm.next_pulse();
+		if self.current_pulse.is_rest() {
+			return;
+		}
+		self.notes_played = self.notes_played.saturating_add(1);
+		if self.limit == Some(Limit::Notes(self.notes_played)) {
+			#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+			let samples = self.pulse_samples().ceil() as u64;
+			self.last_note = Some((self.elapsed, self.elapsed + samples));
+		}
 		let new_note = self.seed.get_note(self.key);
-		//print!("{} ", new_note); TODO doesn't work b/c sleep until end, i think?
+		if let Some(events) = &self.events {
+			let at = self.duration_of(self.elapsed);
+			// Nobody listening is no reason to stop playing
+			let event = NoteEvent::new(new_note, self.key, at).with_tuning(self.tuning);
+			let _ = events.send(event);
+		}
 		self.current_note = new_note;
 	}
+	/// Count the samples the current note or rest lasts at the current tempo
+	fn pulse_samples(&self) -> f64 {
+		let beats = f64::from(self.current_pulse.steps()) / f64::from(STEPS_PER_BEAT);
+		beats * 60.0 / self.tempo * f64::from(self.sample_rate)
+	}
+	/// Apply whatever the controllers have changed since the last note
+	fn apply_changes(&mut self) {
+		let Some(changes) = &self.changes else {
+			return;
+		};
+		for change in control::pending(changes) {
+			let Key {
+				base_note,
+				scale,
+				octaves,
+				..
+			} = self.key;
+			match change {
+				Change::BaseNote(base_note) => self.key = Key::new(scale, base_note, octaves),
+				Change::Scale(scale) => self.key = Key::new(scale, base_note, octaves),
+				Change::Octaves(octaves) => self.key = Key::new(scale, base_note, octaves),
+				Change::Tempo(tempo) => {
+					self.tempo_changed = (self.elapsed, self.beats_played());
+					self.tempo = tempo.max(1.0);
+				},
+				Change::Waveform(waveform) => self.waveform = waveform,
+				Change::Volume(volume) => self.volume = volume.clamp(0.0, 1.0),
+			}
+		}
+	}
+	/// Convert a count of samples to the time they take to play
+	#[allow(clippy::cast_precision_loss)]
+	fn duration_of(&self, samples: u64) -> Duration {
+		Duration::from_secs_f64(samples as f64 / f64::from(self.sample_rate))
+	}
+	/// Count the beats played so far, each at the tempo it was played at
+	#[allow(clippy::cast_precision_loss)]
+	fn beats_played(&self) -> f64 {
+		let (at, beats) = self.tempo_changed;
+		let seconds = (self.elapsed - at) as f64 / f64::from(self.sample_rate);
+		beats + seconds * self.tempo / 60.0
+	}
+	/// Find the sample the music stops on, if it is known yet
+	fn end_sample(&self) -> Option<u64> {
+		let seconds = match self.limit? {
+			Limit::Notes(0) => return Some(0),
+			Limit::Notes(_) => return self.last_note.map(|(_, end)| end),
+			// Only the beats left are played at the current tempo
+			Limit::Bars(bars) => {
+				let (at, beats) = self.tempo_changed;
+				let beats_left = (f64::from(bars) * 4.0 - beats).max(0.0);
+				#[allow(clippy::cast_precision_loss)]
+				let played = at as f64 / f64::from(self.sample_rate);
+				played + beats_left * 60.0 / self.tempo
+			},
+			Limit::Duration(duration) => duration.as_secs_f64(),
+		};
+		#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+		Some((seconds * f64::from(self.sample_rate)).round() as u64)
+	}
+	/// Scale the volume down over the fade before the end.
+	/// A limit of notes only knows its end once the last note starts, so the fade lasts that note at most
+	fn fade_gain(&self, end: Option<u64>) -> f64 {
+		let mut fade = self.fade.as_secs_f64() * f64::from(self.sample_rate);
+		if let Some((start, end)) = self.last_note {
+			#[allow(clippy::cast_precision_loss)]
+			let length = (end - start) as f64;
+			fade = fade.min(length);
+		}
+		match end {
+			#[allow(clippy::cast_precision_loss)]
+			Some(end) if fade >= 1.0 => (end.saturating_sub(self.elapsed) as f64 / fade).min(1.0),
+			_ => 1.0,
+		}
+	}
+	/// Stop after a number of notes, bars or a length of time, instead of playing forever
+	#[must_use]
+	pub fn set_limit(mut self, limit: Limit) -> Self {
+		self.limit = Some(limit);
+		self
+	}
+	/// Set how long the music fades out for before it stops, if it is limited
+	#[must_use]
+	pub fn set_fade(mut self, fade: Duration) -> Self {
+		self.fade = fade;
+		self
+	}
+	/// Play another source underneath the melody, which drops in volume to make room.
+	/// The accompaniment should share the melody's sample rate, and stops and fades with it
+	#[must_use]
+	pub fn set_accompaniment(
+		mut self,
+		source: impl Iterator<Item = Sample> + Send + 'static,
+	) -> Self {
+		self.accompaniment = Some(Box::new(source));
+		self
+	}
+	/// Report each note on a channel as it starts, timed from the first sample
+	#[must_use]
+	pub fn set_events(mut self, events: Sender<NoteEvent>) -> Self {
+		self.events = Some(events);
+		self
+	}
+	/// Swap in a different strategy for timing notes
+	#[must_use]
+	pub fn set_rhythm(mut self, rhythm: impl Rhythm + 'static) -> Self {
+		self.rhythm = Box::new(rhythm);
+		self
+	}
+	/// Use one strategy for both the notes and their timing, for seeds that plan their own rhythm
+	#[must_use]
+	pub fn set_composer(mut self, composer: impl Seed + Rhythm + 'static) -> Self {
+		let shared = Shared(Arc::new(Mutex::new(composer)));
+		self.seed = Box::new(shared.clone());
+		self.rhythm = Box::new(shared);
+		self
+	}
+	/// Set the speed in beats per minute, where a beat is four steps of the rhythm.
+	/// A tempo that isn't a finite number leaves the speed as it was
+	#[must_use]
+	pub fn set_tempo(mut self, tempo: f64) -> Self {
+		if tempo.is_finite() {
+			self.tempo = tempo.max(1.0);
+		}
+		self
+	}
+	#[must_use]
+	pub fn set_waveform(mut self, waveform: Waveform) -> Self {
+		self.waveform = waveform;
+		self
+	}
+	/// Shape each note with an envelope, releasing it before the next note starts
+	#[must_use]
+	pub fn set_envelope(mut self, envelope: Envelope) -> Self {
+		self.envelope = Some(envelope);
+		self
+	}
+	/// Tune A4 to another frequency, moving every other key with it
+	#[must_use]
+	pub fn set_tuning(mut self, a4: Hertz) -> Self {
+		self.tuning = a4;
+		self
+	}
+	/// Set how loud the music is, from 0 for silent to 1 for full volume.
+	/// A volume that isn't a finite number leaves it as it was
+	#[must_use]
+	pub fn set_volume(mut self, volume: f64) -> Self {
+		if volume.is_finite() {
+			self.volume = volume.clamp(0.0, 1.0);
+		}
+		self
+	}
+	/// Get a handle to change the key, tempo, waveform and volume from another thread while the music plays.
+	/// Changes take effect at the start of the next note or rest
+	pub fn controller(&mut self) -> Controller {
+		let (sender, receiver) = mpsc::channel();
+		self.changes = Some(receiver);
+		Controller::new(sender)
+	}
+	/// Swap in a different strategy for picking notes
+	#[must_use]
+	pub fn set_seed(mut self, seed: impl Seed + 'static) -> Self {
+		self.seed = Box::new(seed);
+		self
+	}
+	#[must_use]
+	pub fn key(&self) -> Key {
+		self.key
+	}
 	#[must_use]
 	pub fn set_key(mut self, base_note: PianoKey, scale: Scale, octaves: u8) -> Self {
 		self.key = Key::new(scale, base_note, octaves);
@@ -51,43 +312,78 @@ impl MusicMaker {
 	}
 }
 
+/// Lets a single composer stand in as both the seed and the rhythm
+struct Shared<T>(Arc<Mutex<T>>);
+
+impl<T> Clone for Shared<T> {
+	fn clone(&self) -> Self {
+		Self(Arc::clone(&self.0))
+	}
+}
+
+impl<T: Seed> Seed for Shared<T> {
+	fn get_note(&mut self, key: Key) -> PianoKey {
+		self.0
+			.lock()
+			.expect("A composer never panics while held")
+			.get_note(key)
+	}
+}
+
+impl<T: Rhythm> Rhythm for Shared<T> {
+	fn next_pulse(&mut self) -> Pulse {
+		self.0
+			.lock()
+			.expect("A composer never panics while held")
+			.next_pulse()
+	}
+}
+
 impl Iterator for MusicMaker {
 	type Item = Sample; // Sampled amplitude
 	fn next(&mut self) -> Option<Self::Item> {
+		let end = self.end_sample();
+		if end.is_some_and(|end| self.elapsed >= end) {
+			return None;
+		}
 		self.current_sample = self.current_sample.wrapping_add(1); // will cycle
 
-		let value =
-			self.volume * PI * f64::from(self.get_frequency()) * f64::from(self.current_sample)
-				/ f64::from(self.sample_rate);
+		// How many cycles of the wave have passed since the note started
+		let phase = f64::from(self.get_frequency()) * f64::from(self.current_sample)
+			/ f64::from(self.sample_rate);
+		let mut value = self.waveform.sample(phase);
+		if let Some(envelope) = &self.envelope {
+			#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+			let length = self.duration_of(self.pulse_samples().ceil() as u64);
+			value *= envelope.level(self.duration_of(self.current_sample.into()), length);
+		}
+		let resting = self.current_pulse.is_rest();
 		// when to switch notes?
-		if f64::from(self.current_sample) >= f64::from(self.sample_rate) {
+		if f64::from(self.current_sample) >= self.pulse_samples() {
 			self.current_sample = 0;
 			self.new_note();
 		}
-		let result = f64_to_f32(value.sin());
-		Some(result)
+		let mut result = if resting { 0.0 } else { value };
+		if let Some(accompaniment) = &mut self.accompaniment {
+			result = result * 0.7 + f64::from(accompaniment.next().unwrap_or_default());
+		}
+		// The end of the last note only becomes known once it starts
+		let gain = self.fade_gain(end.or_else(|| self.end_sample()));
+		self.elapsed += 1;
+		Some(f64_to_f32(result * gain * self.volume))
 	}
 }
 
-impl Source for MusicMaker {
-	#[inline]
-	fn current_frame_len(&self) -> Option<usize> {
-		None
-	}
-
-	#[inline]
-	fn channels(&self) -> u16 {
-		1
-	}
-
+impl Signal for MusicMaker {
 	#[inline]
 	fn sample_rate(&self) -> u32 {
 		self.sample_rate
 	}
 
+	/// Known from the start for limits of bars or time, and for a number of notes once the last one starts
 	#[inline]
 	fn total_duration(&self) -> Option<Duration> {
-		None
+		self.end_sample().map(|end| self.duration_of(end))
 	}
 }
 
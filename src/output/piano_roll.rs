@@ -0,0 +1,168 @@
+//! A `PianoRoll` draws recent notes in text, each on its own row above an ASCII piano keyboard.
+//! The keyboard marks the notes of the key, and the note sounding now
+
+use super::event::NoteEvent;
+use crate::theory::{key::Key, piano_key::PianoKey};
+use std::{collections::VecDeque, fmt};
+
+/// Each white key is this many characters wide, counting its left edge
+const WHITE_WIDTH: usize = 4;
+
+pub struct PianoRoll {
+	key: Key,
+	/// The most recent notes, oldest first
+	history: VecDeque<NoteEvent>,
+	rows: usize,
+}
+
+impl PianoRoll {
+	/// Draw the keyboard for a key, keeping the last dozen notes
+	#[must_use]
+	pub fn new(key: Key) -> Self {
+		Self {
+			key,
+			history: VecDeque::new(),
+			rows: 12,
+		}
+	}
+
+	/// Keep this many notes in the roll, scrolling away the oldest if there are more
+	#[must_use]
+	pub fn with_rows(mut self, rows: usize) -> Self {
+		self.rows = rows.max(1);
+		let extra = self.history.len().saturating_sub(self.rows);
+		self.history.drain(..extra);
+		self
+	}
+
+	/// Add a note that just started, scrolling the oldest away
+	pub fn push(&mut self, event: NoteEvent) {
+		if self.history.len() == self.rows {
+			self.history.pop_front();
+		}
+		self.history.push_back(event);
+	}
+
+	/// Get the note sounding now
+	#[must_use]
+	pub fn current(&self) -> Option<PianoKey> {
+		self.history.back().map(|e| e.key)
+	}
+
+	/// Find the range of the keyboard in semitones above C0: whole octaves from C,
+	/// wide enough for the key and every note in the roll
+	fn range(&self) -> (i16, i16) {
+		let keys = self.key.all_keys();
+		let semitones = keys
+			.iter()
+			.copied()
+			.chain(self.history.iter().map(|e| e.key))
+			.map(PianoKey::semitones_from_c0);
+		let (low, high) = semitones.fold((i16::MAX, i16::MIN), |(low, high), s| {
+			(low.min(s), high.max(s))
+		});
+		let low = low.div_euclid(12) * 12;
+		// End on a C when the highest note is one, otherwise on the B above it
+		let high = if high.rem_euclid(12) == 0 {
+			high
+		} else {
+			high.div_euclid(12) * 12 + 11
+		};
+		(low, high.max(low))
+	}
+
+	/// Find the column a key is drawn in, relative to the lowest C of the keyboard
+	fn column(low: i16, semitones: i16) -> usize {
+		let offset = usize::try_from(semitones - low).unwrap_or_default();
+		let whites = offset / 12 * 7 + WHITES_BEFORE[offset % 12];
+		if is_black(offset) {
+			whites * WHITE_WIDTH
+		} else {
+			whites * WHITE_WIDTH + WHITE_WIDTH / 2
+		}
+	}
+
+	/// Draw the keyboard alone, with the notes of the key and the current note marked
+	#[must_use]
+	pub fn keyboard(&self) -> String {
+		let (low, high) = self.range();
+		let notes = self.key.get_notes();
+		let current = self.current().map(PianoKey::semitones_from_c0);
+		let whites =
+			(low..=high).filter(|&s| !is_black(usize::try_from(s - low).unwrap_or_default()));
+		let width = whites.count() * WHITE_WIDTH + 1;
+		let mut black = vec![' '; width];
+		let mut white = vec![' '; width];
+		let mut labels = vec![' '; width];
+		for s in low..=high {
+			let offset = usize::try_from(s - low).unwrap_or_default();
+			let column = Self::column(low, s);
+			let mark = if Some(s) == current {
+				'*'
+			} else if notes
+				.iter()
+				.any(|n| i16::from(n.pitch_class()) == s.rem_euclid(12))
+			{
+				'o'
+			} else {
+				' '
+			};
+			if is_black(offset) {
+				black[column - 1] = '[';
+				black[column] = if mark == ' ' { '#' } else { mark };
+				black[column + 1] = ']';
+			} else {
+				// Black keys cover the edge between white keys on the top row
+				let edge = column - WHITE_WIDTH / 2;
+				if black[edge] == ' ' {
+					black[edge] = '|';
+				}
+				white[edge] = '|';
+				white[column] = mark;
+				if s.rem_euclid(12) == 0 {
+					let label = format!("C{}", s.div_euclid(12));
+					for (i, c) in label.chars().enumerate() {
+						labels[column + i] = c;
+					}
+				} else {
+					labels[column] = letter(offset);
+				}
+			}
+		}
+		black[width - 1] = '|';
+		white[width - 1] = '|';
+		let row = |chars: Vec<char>| chars.into_iter().collect::<String>().trim_end().to_string();
+		format!("{}\n{}\n{}\n", row(black), row(white), row(labels))
+	}
+}
+
+impl fmt::Display for PianoRoll {
+	/// Draw the roll, newest note last, then the keyboard beneath it
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let (low, high) = self.range();
+		let width = Self::column(low, high) + 2;
+		for _ in self.history.len()..self.rows {
+			writeln!(f)?;
+		}
+		for event in &self.history {
+			let column = Self::column(low, event.key.semitones_from_c0());
+			let mut row = vec![' '; width];
+			row[column] = '█';
+			writeln!(f, "{}  {event}", row.into_iter().collect::<String>())?;
+		}
+		write!(f, "{}", self.keyboard())
+	}
+}
+
+/// White keys below each semitone within an octave
+const WHITES_BEFORE: [usize; 12] = [0, 1, 1, 2, 2, 3, 4, 4, 5, 5, 6, 6];
+
+/// Whether a semitone within an octave from C falls on a black key
+fn is_black(offset: usize) -> bool {
+	matches!(offset % 12, 1 | 3 | 6 | 8 | 10)
+}
+
+/// Name the white key a semitone from C falls on
+fn letter(offset: usize) -> char {
+	['C', 'D', 'E', 'F', 'G', 'A', 'B'][WHITES_BEFORE[offset % 12]]
+}
@@ -0,0 +1,319 @@
+//! Rhythms decide when notes start and how long they last, leaving the choice of notes to a `Seed`.
+//! Time is counted in sixteenth-note steps, and patterns repeat once per cycle
+
+use crate::{error::Error, theory::melody::STEPS_PER_BEAT};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use std::{collections::VecDeque, fmt, str::FromStr};
+
+/// A Pulse is either a note or a silence, lasting some number of steps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pulse {
+	Note(u16),
+	Rest(u16),
+}
+
+impl Pulse {
+	/// Get the length in steps
+	#[must_use]
+	pub fn steps(self) -> u16 {
+		match self {
+			Pulse::Note(steps) | Pulse::Rest(steps) => steps,
+		}
+	}
+
+	#[must_use]
+	pub fn is_rest(self) -> bool {
+		matches!(self, Pulse::Rest(_))
+	}
+}
+
+impl fmt::Display for Pulse {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Pulse::Note(steps) => write!(f, "x{steps}"),
+			Pulse::Rest(steps) => write!(f, ".{steps}"),
+		}
+	}
+}
+
+/// To be a rhythm, types simply must implement this single method
+pub trait Rhythm: Send {
+	fn next_pulse(&mut self) -> Pulse;
+}
+
+/// Every note lasts the same number of steps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Steady(pub u16);
+
+impl Default for Steady {
+	/// One note per beat
+	fn default() -> Self {
+		Self(STEPS_PER_BEAT)
+	}
+}
+
+impl Rhythm for Steady {
+	fn next_pulse(&mut self) -> Pulse {
+		Pulse::Note(self.0.max(1))
+	}
+}
+
+/// E(k,n) spreads k onsets as evenly as possible over n steps, like E(3,8) for the tresillo "x..x..x."
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Euclidean {
+	pattern: Vec<bool>,
+	queue: VecDeque<Pulse>,
+}
+
+impl Euclidean {
+	/// Build the pattern of k onsets over n steps
+	pub fn new(onsets: u16, steps: u16) -> Result<Self, Error> {
+		if steps == 0 || onsets > steps {
+			return Err(Error::BadEuclidean { onsets, steps });
+		}
+		Ok(Self {
+			pattern: bjorklund(usize::from(onsets), usize::from(steps)),
+			queue: VecDeque::new(),
+		})
+	}
+
+	/// Start the pattern this many steps later, wrapping the skipped steps round to the end
+	#[must_use]
+	pub fn with_rotation(mut self, rotation: usize) -> Self {
+		let len = self.pattern.len();
+		self.pattern.rotate_left(rotation % len);
+		self.queue.clear();
+		self
+	}
+
+	/// Get the onsets of one cycle
+	#[must_use]
+	pub fn pattern(&self) -> &[bool] {
+		&self.pattern
+	}
+}
+
+impl Rhythm for Euclidean {
+	fn next_pulse(&mut self) -> Pulse {
+		if self.queue.is_empty() {
+			self.queue.extend(pulses(&self.pattern));
+		}
+		self.queue
+			.pop_front()
+			.expect("Every cycle has at least one step")
+	}
+}
+
+/// Each step has its own chance of starting a note, rolled again every cycle
+#[derive(Debug, Clone)]
+pub struct Probabilistic {
+	chances: Vec<f64>,
+	queue: VecDeque<Pulse>,
+	rng: SmallRng,
+}
+
+impl Probabilistic {
+	/// Give the chance of an onset on each step of the cycle, from 0 to 1
+	pub fn new(chances: Vec<f64>) -> Result<Self, Error> {
+		if chances.is_empty() || chances.iter().any(|c| !(0.0..=1.0).contains(c)) {
+			return Err(Error::BadChances);
+		}
+		Ok(Self {
+			chances,
+			queue: VecDeque::new(),
+			rng: SmallRng::from_entropy(),
+		})
+	}
+}
+
+impl Rhythm for Probabilistic {
+	fn next_pulse(&mut self) -> Pulse {
+		if self.queue.is_empty() {
+			let pattern = self
+				.chances
+				.iter()
+				.map(|&c| self.rng.gen_bool(c))
+				.collect::<Vec<_>>();
+			self.queue.extend(pulses(&pattern));
+		}
+		self.queue
+			.pop_front()
+			.expect("Every cycle has at least one step")
+	}
+}
+
+/// Wrap another rhythm, silencing some of its notes at random
+pub struct WithRests<R: Rhythm> {
+	rhythm: R,
+	chance: f64,
+	rng: SmallRng,
+}
+
+impl<R: Rhythm> WithRests<R> {
+	/// Turn each note into a rest with the given chance, from 0 to 1
+	pub fn new(rhythm: R, chance: f64) -> Result<Self, Error> {
+		if !chance.is_finite() {
+			return Err(Error::BadChance(chance.to_string()));
+		}
+		Ok(Self {
+			rhythm,
+			chance: chance.clamp(0.0, 1.0),
+			rng: SmallRng::from_entropy(),
+		})
+	}
+}
+
+impl<R: Rhythm> Rhythm for WithRests<R> {
+	fn next_pulse(&mut self) -> Pulse {
+		match self.rhythm.next_pulse() {
+			Pulse::Note(steps) if self.rng.gen_bool(self.chance) => Pulse::Rest(steps),
+			pulse => pulse,
+		}
+	}
+}
+
+impl Rhythm for Box<dyn Rhythm> {
+	fn next_pulse(&mut self) -> Pulse {
+		self.as_mut().next_pulse()
+	}
+}
+
+/// Bjorklund's algorithm: pair off onsets with silences, then keep pairing the groups with the leftovers
+/// until at most one leftover remains
+fn bjorklund(onsets: usize, steps: usize) -> Vec<bool> {
+	if onsets == 0 {
+		return vec![false; steps];
+	}
+	let mut groups = vec![vec![true]; onsets];
+	let mut remainder = vec![vec![false]; steps - onsets];
+	while remainder.len() > 1 {
+		let pairs = groups.len().min(remainder.len());
+		let leftover = if groups.len() > pairs {
+			groups.split_off(pairs)
+		} else {
+			remainder.split_off(pairs)
+		};
+		for (group, tail) in groups.iter_mut().zip(&remainder) {
+			group.extend(tail);
+		}
+		remainder = leftover;
+	}
+	groups.into_iter().chain(remainder).flatten().collect()
+}
+
+/// Turn a cycle of onsets into pulses. Each note lasts until the next onset or the end of the cycle,
+/// and any steps before the first onset are a rest
+fn pulses(pattern: &[bool]) -> Vec<Pulse> {
+	let mut ret = Vec::new();
+	let mut start = 0;
+	for (i, &onset) in pattern.iter().enumerate().skip(1) {
+		if onset {
+			ret.push((start, i));
+			start = i;
+		}
+	}
+	ret.push((start, pattern.len()));
+	ret.into_iter()
+		.map(|(start, end)| {
+			let steps = u16::try_from(end - start).unwrap_or(u16::MAX);
+			if pattern[start] {
+				Pulse::Note(steps)
+			} else {
+				Pulse::Rest(steps)
+			}
+		})
+		.collect()
+}
+
+/// A `RhythmSource` describes a rhythm to build, so one can be picked on the command line
+#[derive(Debug, Clone, PartialEq)]
+pub enum RhythmSource {
+	Steady(u16),
+	Euclidean {
+		onsets: u16,
+		steps: u16,
+		rotation: usize,
+	},
+	Probabilistic(Vec<f64>),
+}
+
+impl Default for RhythmSource {
+	fn default() -> Self {
+		RhythmSource::Steady(STEPS_PER_BEAT)
+	}
+}
+
+impl RhythmSource {
+	/// Produce the rhythm, silencing notes with the given chance
+	pub fn build(&self, rests: f64) -> Result<Box<dyn Rhythm>, Error> {
+		let rhythm: Box<dyn Rhythm> = match self {
+			RhythmSource::Steady(steps) => Box::new(Steady(*steps)),
+			RhythmSource::Euclidean {
+				onsets,
+				steps,
+				rotation,
+			} => Box::new(Euclidean::new(*onsets, *steps)?.with_rotation(*rotation)),
+			RhythmSource::Probabilistic(chances) => Box::new(Probabilistic::new(chances.clone())?),
+		};
+		Ok(if rests == 0.0 {
+			rhythm
+		} else {
+			Box::new(WithRests::new(rhythm, rests)?)
+		})
+	}
+}
+
+impl FromStr for RhythmSource {
+	type Err = Error;
+
+	/// Accepts "steady", a note length in steps like "2", a Euclidean rhythm like "E(3,8)" or "E(3,8,2)" rotated by two steps,
+	/// or per-step chances like "1,0,0.5,0.25"
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+		let invalid = || Error::BadRhythm(s.to_string());
+		if s.eq_ignore_ascii_case("steady") {
+			return Ok(RhythmSource::default());
+		}
+		if let Some(args) = s
+			.strip_prefix(['E', 'e'])
+			.and_then(|a| a.strip_prefix('('))
+			.and_then(|a| a.strip_suffix(')'))
+		{
+			let numbers = args
+				.split(',')
+				.map(|n| n.trim().parse::<u16>().map_err(|_| invalid()))
+				.collect::<Result<Vec<_>, _>>()?;
+			return match numbers[..] {
+				[onsets, steps] => Ok(RhythmSource::Euclidean {
+					onsets,
+					steps,
+					rotation: 0,
+				}),
+				[onsets, steps, rotation] => Ok(RhythmSource::Euclidean {
+					onsets,
+					steps,
+					rotation: usize::from(rotation),
+				}),
+				_ => Err(invalid()),
+			};
+		}
+		if let Ok(steps) = s.parse::<u16>() {
+			return if steps > 0 {
+				Ok(RhythmSource::Steady(steps))
+			} else {
+				Err(invalid())
+			};
+		}
+		let chances = s
+			.split(',')
+			.map(|c| {
+				c.trim()
+					.parse::<f64>()
+					.ok()
+					.filter(|c| c.is_finite())
+					.ok_or_else(invalid)
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(RhythmSource::Probabilistic(chances))
+	}
+}
@@ -0,0 +1,207 @@
+//! The `ChordMelody` seed follows a chord progression written in Roman numerals.
+//! Strong beats land on tones of the current chord, and the weak beats between them
+//! fill in with passing and neighbour tones from the scale
+
+use super::Seed;
+use crate::{
+	error::Error,
+	output::rhythm::{Pulse, Rhythm},
+	theory::{
+		chord::{Chord, Progression},
+		key::Key,
+		melody::STEPS_PER_BEAT,
+		piano_key::PianoKey,
+		scale::Scale,
+		voice_leading::VoiceLeading,
+	},
+};
+use rand::{rngs::SmallRng, seq::SliceRandom, Rng, SeedableRng};
+use std::collections::VecDeque;
+
+/// The four-part voicings of a progression, one for each chord
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Harmony {
+	pub voicings: Vec<Vec<PianoKey>>,
+	/// The chords, counting from 0, the voices could not move to smoothly, each with the reason why.
+	/// These start again in root position, the one place the voices may leap further than the voice leading allows
+	pub restarts: Vec<(usize, Error)>,
+}
+
+pub struct ChordMelody {
+	progression: Progression,
+	beats_per_chord: u16,
+	leading: VoiceLeading,
+	/// The notes left to play in the current pass through the progression
+	plan: VecDeque<PianoKey>,
+	previous: Option<PianoKey>,
+	rng: SmallRng,
+}
+
+impl ChordMelody {
+	/// Follow a progression, changing chord every four beats
+	#[must_use]
+	pub fn new(progression: Progression) -> Self {
+		Self {
+			progression,
+			beats_per_chord: 4,
+			leading: VoiceLeading::default(),
+			plan: VecDeque::new(),
+			previous: None,
+			rng: SmallRng::from_entropy(),
+		}
+	}
+
+	/// Hold each chord for this many beats
+	#[must_use]
+	pub fn with_beats_per_chord(mut self, beats: u16) -> Self {
+		self.beats_per_chord = beats.max(1);
+		self.plan.clear();
+		self
+	}
+
+	/// Voice the harmony under these constraints
+	#[must_use]
+	pub fn with_voice_leading(mut self, leading: VoiceLeading) -> Self {
+		self.leading = leading;
+		self
+	}
+
+	#[must_use]
+	pub fn progression(&self) -> &Progression {
+		&self.progression
+	}
+
+	#[must_use]
+	pub fn beats_per_chord(&self) -> u16 {
+		self.beats_per_chord
+	}
+
+	/// Spell the progression in a key. Keys that are not diatonic borrow the major scale on their base note
+	pub fn chords(&self, key: Key) -> Result<Vec<Chord>, Error> {
+		self.progression.chords(diatonic(key))
+	}
+
+	/// Voice the chords of the progression in four parts, starting in root position an octave below the key
+	/// and moving each voice as little as it can from there. Where the voices cannot move smoothly to a chord
+	/// it starts again in root position, and the voices lead on from it
+	pub fn harmony(&self, key: Key) -> Result<Harmony, Error> {
+		let octave = key.base_note.octave.saturating_sub(1);
+		let chords = self.chords(key)?;
+		let leading = &self.leading;
+		let mut voicings = vec![leading.root_position(chords[0], octave)?];
+		let mut restarts = Vec::new();
+		for (i, pair) in chords.windows(2).enumerate() {
+			let next = match leading.lead(pair[0], &voicings[i], pair[1]) {
+				Ok(next) => next,
+				Err(e) => {
+					restarts.push((i + 1, e));
+					leading.root_position(pair[1], octave)?
+				},
+			};
+			voicings.push(next);
+		}
+		Ok(Harmony { voicings, restarts })
+	}
+
+	/// Write the melody for one pass through the progression
+	fn plan(&mut self, key: Key) {
+		let chords = self
+			.chords(key)
+			.expect("Every key can borrow a diatonic scale");
+		let scale = key.all_keys();
+		let beats = usize::from(self.beats_per_chord);
+		let mut notes: Vec<Option<PianoKey>> = vec![None; chords.len() * beats];
+		let mut previous = self.previous.unwrap_or(key.base_note);
+		// Strong beats first, each a chord tone close to the last one
+		for (i, note) in notes.iter_mut().enumerate() {
+			if i % beats % 2 == 0 {
+				let mut tones = chord_tones(chords[i / beats], key);
+				tones.sort_by_key(|t| (t.semitones_from_c0() - previous.semitones_from_c0()).abs());
+				tones.truncate(3);
+				previous = tones.choose(&mut self.rng).copied().unwrap_or(previous);
+				*note = Some(previous);
+			}
+		}
+		// Then the weak beats, which always sit between two strong ones or at the end
+		for i in 0..notes.len() {
+			if notes[i].is_none() {
+				let before = notes[i - 1].expect("The first beat is always strong");
+				let after = notes.get(i + 1).copied().flatten();
+				notes[i] = Some(embellish(before, after, &scale, &mut self.rng));
+			}
+		}
+		self.plan.extend(notes.into_iter().flatten());
+	}
+}
+
+impl Seed for ChordMelody {
+	fn get_note(&mut self, key: Key) -> PianoKey {
+		if self.plan.is_empty() {
+			self.plan(key);
+		}
+		let ret = self
+			.plan
+			.pop_front()
+			.expect("Every progression has at least one chord");
+		self.previous = Some(ret);
+		ret
+	}
+}
+
+impl Rhythm for ChordMelody {
+	/// One note per beat keeps the melody in step with the chords
+	fn next_pulse(&mut self) -> Pulse {
+		Pulse::Note(STEPS_PER_BEAT)
+	}
+}
+
+/// Roman numerals only make sense in a diatonic key, so other scales fall back to major
+fn diatonic(key: Key) -> Key {
+	match key.scale {
+		Scale::Diatonic(_) => key,
+		_ => Key::new(Scale::default(), key.base_note, key.octaves),
+	}
+}
+
+/// Find every tone of a chord within the range of a key, spelled as the chord spells it
+fn chord_tones(chord: Chord, key: Key) -> Vec<PianoKey> {
+	let low = key.base_note.semitones_from_c0();
+	let high = low + 12 * i16::from(key.octaves);
+	let notes = chord.notes();
+	(low..=high)
+		.filter_map(|semitones| {
+			notes
+				.iter()
+				.find(|n| i16::from(n.pitch_class()) == semitones.rem_euclid(12))
+				.map(|&n| PianoKey::with_note(n, semitones))
+		})
+		.collect()
+}
+
+/// Pick a weak beat note from the scale: a passing tone between notes a third apart,
+/// a neighbour tone between repeated notes, or otherwise a step on the way to the next note
+fn embellish(
+	before: PianoKey,
+	after: Option<PianoKey>,
+	scale: &[PianoKey],
+	rng: &mut impl Rng,
+) -> PianoKey {
+	let nearest = |key: PianoKey| {
+		(0..scale.len())
+			.min_by_key(|&i| (scale[i].semitones_from_c0() - key.semitones_from_c0()).abs())
+			.unwrap_or_default()
+	};
+	let from = nearest(before);
+	let direction = if rng.gen_bool(0.5) { 1 } else { -1 };
+	let neighbour = || {
+		from.checked_add_signed(direction)
+			.filter(|&i| i < scale.len())
+			.or_else(|| from.checked_add_signed(-direction))
+	};
+	let step = match after.map(|a| nearest(a).cast_signed() - from.cast_signed()) {
+		None | Some(0) => neighbour(),
+		Some(distance) if distance.abs() == 2 => from.checked_add_signed(distance / 2),
+		Some(distance) => from.checked_add_signed(distance.signum()),
+	};
+	step.and_then(|i| scale.get(i).copied()).unwrap_or(before)
+}
@@ -0,0 +1,185 @@
+//! Degrees are the key-free language of the seeds that plan melodies ahead.
+//! A plan written in degrees can be played in any key
+
+use crate::{
+	error::Error,
+	theory::{
+		interval::{Interval, Quality},
+		key::Key,
+		piano_key::PianoKey,
+	},
+};
+use std::{fmt, str::FromStr};
+
+/// A Degree places a note against a key without naming it, so a melody learned in one key can be played in another.
+/// The step counts scale steps from the base note, continuing up or down through the octaves,
+/// and chromatic notes are recorded as a scale note raised or lowered by some semitones.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Degree {
+	pub step: i16,
+	pub alteration: i8,
+}
+
+impl Degree {
+	/// Find the degree of a piano key in a key.
+	/// Notes outside the scale count from the scale note with the same letter, or failing that the closest one
+	///
+	/// # Panics
+	///
+	/// This function would panic if a scale had no notes or more than an octave's worth.
+	#[must_use]
+	pub fn of(piano_key: PianoKey, key: Key) -> Self {
+		let offsets = scale_offsets(key);
+		let notes = key.get_notes();
+		let len = i16::try_from(offsets.len()).expect("Scales are at most twelve notes");
+		let distance = piano_key.semitones_from_c0() - key.base_note.semitones_from_c0();
+		let within = distance.rem_euclid(12);
+		let index = offsets
+			.iter()
+			.position(|&o| o == within)
+			.or_else(|| {
+				notes[..offsets.len()]
+					.iter()
+					.position(|n| n.letter == piano_key.note.letter)
+			})
+			.unwrap_or_else(|| {
+				(0..offsets.len())
+					.min_by_key(|&i| (within - offsets[i]).abs())
+					.expect("Scales have at least one note")
+			});
+		let index16 = i16::try_from(index).expect("Scales are at most twelve notes");
+		let difference = distance - offsets[index];
+		// Round to the nearest octave, leaving the rest as the alteration
+		let octave = (difference + 6).div_euclid(12);
+		Self {
+			step: octave * len + index16,
+			alteration: i8::try_from(difference - octave * 12)
+				.expect("Alterations are within an octave"),
+		}
+	}
+
+	/// Find the piano key for this degree in a key
+	///
+	/// # Panics
+	///
+	/// This function would panic if the augmented unison were not a valid interval.
+	#[must_use]
+	pub fn place(self, key: Key) -> PianoKey {
+		let intervals = key.scale.intervals_from(key.base_note.note);
+		let len = i16::try_from(intervals.len()).expect("Scales are at most twelve notes");
+		let index = usize::try_from(self.step.rem_euclid(len)).expect("Remainders are positive");
+		let mut ret = key.base_note;
+		for interval in &intervals[..index] {
+			ret = ret + *interval;
+		}
+		let octaves = self.step.div_euclid(len);
+		for _ in 0..octaves.abs() {
+			ret = if octaves > 0 {
+				ret + Interval::OCTAVE
+			} else {
+				ret - Interval::OCTAVE
+			};
+		}
+		let semitone =
+			Interval::new(Quality::Augmented, 1).expect("The augmented unison is an interval");
+		for _ in 0..self.alteration.abs() {
+			ret = if self.alteration > 0 {
+				ret + semitone
+			} else {
+				ret - semitone
+			};
+		}
+		ret
+	}
+}
+
+impl fmt::Display for Degree {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let symbol = if self.alteration > 0 { "#" } else { "b" };
+		write!(
+			f,
+			"{}{}",
+			self.step,
+			symbol.repeat(usize::from(self.alteration.unsigned_abs()))
+		)
+	}
+}
+
+impl FromStr for Degree {
+	type Err = Error;
+
+	/// Accepts a step number followed by up to two sharps or flats, like "4", "-2" or "3b"
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let split = s.find(['#', 'b']).unwrap_or(s.len());
+		let step = s[..split]
+			.parse::<i16>()
+			.map_err(|_| Error::BadDegree(s.to_string()))?;
+		let mut alteration = 0i8;
+		for c in s[split..].chars() {
+			alteration += match c {
+				'#' => 1,
+				'b' => -1,
+				_ => return Err(Error::BadDegree(s.to_string())),
+			};
+			// Like notes, degrees are at most doubly sharp or flat
+			if alteration.abs() > 2 {
+				return Err(Error::AlteredTooFar(s.to_string()));
+			}
+		}
+		Ok(Self { step, alteration })
+	}
+}
+
+/// A `DegreeNote` is a scale degree held for a number of steps
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DegreeNote {
+	pub degree: Degree,
+	pub steps: u16,
+}
+
+impl fmt::Display for DegreeNote {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}/{}", self.degree, self.steps)
+	}
+}
+
+impl FromStr for DegreeNote {
+	type Err = Error;
+
+	/// Accepts a degree and a length in steps, like "3b/4"
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (degree, steps) = s
+			.split_once('/')
+			.ok_or_else(|| Error::MissingLength(s.to_string()))?;
+		Ok(Self {
+			degree: Degree::from_str(degree)?,
+			steps: steps
+				.parse::<u16>()
+				.map_err(|_| Error::BadLength(steps.to_string()))?,
+		})
+	}
+}
+
+/// Move a key by octaves until it sits within the range of a key
+pub(super) fn fit(mut piano_key: PianoKey, key: Key) -> PianoKey {
+	let low = key.base_note.semitones_from_c0();
+	let high = low + 12 * i16::from(key.octaves.max(1));
+	while piano_key.semitones_from_c0() < low && piano_key.octave < PianoKey::max_octave() {
+		piano_key.octave += 1;
+	}
+	while piano_key.semitones_from_c0() > high && piano_key.octave > 0 {
+		piano_key.octave -= 1;
+	}
+	piano_key
+}
+
+/// Semitones from the base note to each note of the scale within the first octave
+fn scale_offsets(key: Key) -> Vec<i16> {
+	let intervals = key.scale.get_intervals();
+	let mut ret = vec![0];
+	for interval in &intervals[..intervals.len() - 1] {
+		let last = ret[ret.len() - 1];
+		ret.push(last + i16::from(i8::from(*interval)));
+	}
+	ret
+}
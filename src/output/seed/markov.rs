@@ -0,0 +1,239 @@
+//! The `Markov` seed imitates a style by learning which scale degree and length tends to follow which.
+//! Its model looks back over the last few notes and falls back to shorter histories it has never seen
+
+use super::{
+	degree::{fit, Degree, DegreeNote},
+	Seed,
+};
+use crate::{
+	midi,
+	theory::{
+		analysis::estimate_key,
+		key::Key,
+		melody::{self, MelodyNote},
+		piano_key::PianoKey,
+	},
+};
+use rand::{distributions::WeightedIndex, prelude::Distribution, rngs::SmallRng, Rng, SeedableRng};
+use std::{
+	collections::{BTreeMap, VecDeque},
+	fmt, fs, io,
+	path::Path,
+	str::FromStr,
+};
+
+/// The first line of a saved model
+const HEADER: &str = "markov";
+
+/// The name a `DegreeNote` first had, when only the `Markov` seed used it
+pub type MarkovNote = DegreeNote;
+
+/// A `MarkovModel` counts what followed every history of up to `order` notes in its training melodies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkovModel {
+	order: usize,
+	transitions: BTreeMap<Vec<DegreeNote>, BTreeMap<DegreeNote, u32>>,
+}
+
+impl Default for MarkovModel {
+	fn default() -> Self {
+		Self::new(2)
+	}
+}
+
+impl MarkovModel {
+	/// Build an empty model looking back over the given number of notes
+	#[must_use]
+	pub fn new(order: usize) -> Self {
+		Self {
+			order,
+			transitions: BTreeMap::new(),
+		}
+	}
+
+	#[must_use]
+	pub fn order(&self) -> usize {
+		self.order
+	}
+
+	/// Check whether the model has learned anything yet
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.transitions.is_empty()
+	}
+
+	/// Learn from a melody, estimating its key first
+	pub fn train(&mut self, melody: &[MelodyNote]) {
+		let Some(estimate) = estimate_key(melody.iter().map(|n| (n.key, f64::from(n.steps))))
+			.first()
+			.copied()
+		else {
+			return;
+		};
+		self.train_in_key(melody, estimate.key);
+	}
+
+	/// Learn from a melody in a known key
+	pub fn train_in_key(&mut self, melody: &[MelodyNote], key: Key) {
+		let states = melody
+			.iter()
+			.map(|n| DegreeNote {
+				degree: Degree::of(n.key, key),
+				steps: n.steps,
+			})
+			.collect::<Vec<_>>();
+		for (i, next) in states.iter().enumerate() {
+			for length in 0..=self.order.min(i) {
+				*self
+					.transitions
+					.entry(states[i - length..i].to_vec())
+					.or_default()
+					.entry(*next)
+					.or_default() += 1;
+			}
+		}
+	}
+
+	/// Learn from every melody in a file - MIDI files by their extension, anything else as one melody per line of text
+	pub fn train_file(&mut self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+		let path = path.as_ref();
+		let is_midi = path
+			.extension()
+			.and_then(|e| e.to_str())
+			.is_some_and(|e| matches!(e.to_lowercase().as_str(), "mid" | "midi"));
+		let melodies = if is_midi {
+			midi::read_melodies(path)?
+		} else {
+			melody::parse_melodies(&fs::read_to_string(path)?)?
+		};
+		for melody in &melodies {
+			self.train(melody);
+		}
+		Ok(())
+	}
+
+	/// Pick what comes next after the given history, using the longest part of it the model has seen
+	pub fn sample(&self, history: &[DegreeNote], rng: &mut impl Rng) -> Option<DegreeNote> {
+		(0..=self.order.min(history.len()))
+			.rev()
+			.find_map(|length| {
+				let choices = self.transitions.get(&history[history.len() - length..])?;
+				let index = WeightedIndex::new(choices.values()).ok()?;
+				choices.keys().nth(index.sample(rng)).copied()
+			})
+	}
+
+	/// Write the model to a file that `load` can read back
+	pub fn save(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+		fs::write(path, self.to_string())
+	}
+
+	/// Read a model written by `save`
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+		Self::from_str(&fs::read_to_string(path)?)
+	}
+}
+
+impl fmt::Display for MarkovModel {
+	/// Write a header line with the order, then one line per history listing each next note with its count:
+	/// "0/4 2/4 -> 4/4:3 5/2:1"
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "{HEADER} {}", self.order)?;
+		for (history, nexts) in &self.transitions {
+			let history = history.iter().map(ToString::to_string).collect::<Vec<_>>();
+			let nexts = nexts
+				.iter()
+				.map(|(next, count)| format!("{next}:{count}"))
+				.collect::<Vec<_>>();
+			writeln!(f, "{} -> {}", history.join(" "), nexts.join(" "))?;
+		}
+		Ok(())
+	}
+}
+
+impl FromStr for MarkovModel {
+	type Err = io::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+		let mut lines = s.lines().map(str::trim).filter(|l| !l.is_empty());
+		let order = lines
+			.next()
+			.and_then(|l| l.strip_prefix(HEADER))
+			.and_then(|o| o.trim().parse::<usize>().ok())
+			.ok_or_else(|| {
+				invalid(format!(
+					"A model must start with \"{HEADER}\" and its order"
+				))
+			})?;
+		let mut ret = Self::new(order);
+		for line in lines {
+			let (history, nexts) = line
+				.split_once("->")
+				.ok_or_else(|| invalid(format!("{line} is missing \"->\"")))?;
+			let history = history
+				.split_whitespace()
+				.map(DegreeNote::from_str)
+				.collect::<Result<Vec<_>, _>>()?;
+			if history.len() > order {
+				return Err(invalid(format!(
+					"{line} looks back further than the model's order of {order}"
+				)));
+			}
+			let counts = ret.transitions.entry(history).or_default();
+			for next in nexts.split_whitespace() {
+				let (note, count) = next
+					.rsplit_once(':')
+					.ok_or_else(|| invalid(format!("{next} needs a count, like 4/2:3")))?;
+				let count = count
+					.parse::<u32>()
+					.map_err(|_| invalid(format!("{count} is not a count")))?;
+				*counts.entry(DegreeNote::from_str(note)?).or_default() += count;
+			}
+		}
+		Ok(ret)
+	}
+}
+
+/// The `Markov` seed plays melodies generated from a trained model
+pub struct Markov {
+	model: MarkovModel,
+	history: VecDeque<DegreeNote>,
+	rng: SmallRng,
+}
+
+impl Markov {
+	#[must_use]
+	pub fn new(model: MarkovModel) -> Self {
+		Self {
+			model,
+			history: VecDeque::new(),
+			rng: SmallRng::from_entropy(),
+		}
+	}
+
+	#[must_use]
+	pub fn model(&self) -> &MarkovModel {
+		&self.model
+	}
+
+	/// Generate the next note with its length, kept within the key's range.
+	/// An untrained model just plays the base note
+	pub fn next_note(&mut self, key: Key) -> MelodyNote {
+		let history = self.history.make_contiguous();
+		let Some(next) = self.model.sample(history, &mut self.rng) else {
+			return MelodyNote::new(key.base_note, melody::STEPS_PER_BEAT);
+		};
+		self.history.push_back(next);
+		while self.history.len() > self.model.order() {
+			self.history.pop_front();
+		}
+		MelodyNote::new(fit(next.degree.place(key), key), next.steps)
+	}
+}
+
+impl Seed for Markov {
+	fn get_note(&mut self, key: Key) -> PianoKey {
+		self.next_note(key).key
+	}
+}
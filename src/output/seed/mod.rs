@@ -2,11 +2,27 @@
 
 use crate::theory::{key::Key, piano_key::PianoKey};
 
+mod chord_melody;
+mod degree;
 mod linear;
+mod markov;
+mod phrase;
 mod random;
+mod sequence;
+mod serial;
+mod walk;
+mod weights;
 
+pub use chord_melody::{ChordMelody, Harmony};
+pub use degree::{Degree, DegreeNote};
 pub use linear::Linear;
+pub use markov::{Markov, MarkovModel, MarkovNote};
+pub use phrase::{Cadence, Development, Form, Phrases};
 pub use random::Random;
+pub use sequence::Sequence;
+pub use serial::{RowSource, Serial};
+pub use walk::{Center, Walk};
+pub use weights::DegreeWeights;
 
 /// To be a seed, types simply must implement this single method
 pub trait Seed: Send {
@@ -0,0 +1,342 @@
+//! The `Phrases` seed writes a short motif and builds a whole piece from it.
+//! Each letter of a form like AABA is a four-bar phrase developing the motif, closed by a cadence.
+//! It plans its own rhythm as well, so it can time the notes it picks
+
+use super::{
+	degree::{fit, Degree, DegreeNote},
+	Seed,
+};
+use crate::{
+	error::Error,
+	output::rhythm::{Pulse, Rhythm},
+	theory::{key::Key, melody::STEPS_PER_BEAT, piano_key::PianoKey},
+};
+use rand::{rngs::SmallRng, seq::SliceRandom, Rng, SeedableRng};
+use std::{fmt, str::FromStr};
+
+/// The number of steps in a bar of four beats
+const BAR: u16 = 4 * STEPS_PER_BEAT;
+
+/// The number of bars in a phrase, the last of which holds the cadence
+const PHRASE_BARS: u16 = 4;
+
+/// A Development is one way of reworking a motif
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Development {
+	/// Play it again unchanged
+	Repeat,
+	/// Move every note by a number of scale steps
+	Transpose(i16),
+	/// Mirror it around its first note, so rising steps fall
+	Invert,
+	/// Play it backwards
+	Retrograde,
+	/// Double every length
+	Augment,
+	/// Halve every length
+	Diminish,
+	/// Keep only the first few notes
+	Fragment(usize),
+}
+
+impl Development {
+	/// Rework a motif
+	#[must_use]
+	pub fn apply(self, motif: &[DegreeNote]) -> Vec<DegreeNote> {
+		let first = motif.first().map_or(0, |n| n.degree.step);
+		let mut ret = motif.to_vec();
+		match self {
+			Development::Repeat => {},
+			Development::Transpose(steps) => {
+				for note in &mut ret {
+					note.degree.step += steps;
+				}
+			},
+			Development::Invert => {
+				for note in &mut ret {
+					note.degree = Degree {
+						step: 2 * first - note.degree.step,
+						alteration: -note.degree.alteration,
+					};
+				}
+			},
+			Development::Retrograde => ret.reverse(),
+			Development::Augment => {
+				for note in &mut ret {
+					note.steps = note.steps.saturating_mul(2);
+				}
+			},
+			Development::Diminish => {
+				for note in &mut ret {
+					note.steps = (note.steps / 2).max(1);
+				}
+			},
+			Development::Fragment(len) => ret.truncate(len.max(1)),
+		}
+		ret
+	}
+}
+
+impl fmt::Display for Development {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Development::Repeat => write!(f, "repeat"),
+			Development::Transpose(steps) => write!(f, "transpose {steps:+}"),
+			Development::Invert => write!(f, "invert"),
+			Development::Retrograde => write!(f, "retrograde"),
+			Development::Augment => write!(f, "augment"),
+			Development::Diminish => write!(f, "diminish"),
+			Development::Fragment(len) => write!(f, "fragment of {len}"),
+		}
+	}
+}
+
+/// A Cadence closes a phrase
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cadence {
+	/// Come to rest on the tonic, from the note above
+	Authentic,
+	/// Pause on the dominant, leaving the music open
+	Half,
+}
+
+impl Cadence {
+	/// Get the bar of two half notes that makes up the cadence
+	#[must_use]
+	pub fn notes(self) -> Vec<DegreeNote> {
+		let steps = match self {
+			Cadence::Authentic => [1, 0],
+			Cadence::Half => [5, 4],
+		};
+		steps
+			.iter()
+			.map(|&step| DegreeNote {
+				degree: Degree {
+					step,
+					alteration: 0,
+				},
+				steps: BAR / 2,
+			})
+			.collect()
+	}
+}
+
+/// A Form lists the phrases of a piece by letter, with repeated letters repeating a phrase
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Form(Vec<char>);
+
+impl Default for Form {
+	fn default() -> Self {
+		Self(vec!['A', 'A', 'B', 'A'])
+	}
+}
+
+impl Form {
+	#[must_use]
+	pub fn letters(&self) -> &[char] {
+		&self.0
+	}
+}
+
+impl fmt::Display for Form {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.0.iter().collect::<String>())
+	}
+}
+
+impl FromStr for Form {
+	type Err = Error;
+
+	/// Accepts letters naming each phrase, like "AABA" or "ABAC"
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let letters = s.trim().to_uppercase().chars().collect::<Vec<_>>();
+		if letters.is_empty() || !letters.iter().all(char::is_ascii_uppercase) {
+			return Err(Error::BadForm(s.to_string()));
+		}
+		Ok(Self(letters))
+	}
+}
+
+pub struct Phrases {
+	form: Form,
+	motif: Vec<DegreeNote>,
+	/// How each distinct letter of the form develops the motif
+	sections: Vec<(char, Vec<Development>)>,
+	plan: Vec<DegreeNote>,
+	position: usize,
+	/// A note chosen by the rhythm that has not been played yet
+	pending: Option<DegreeNote>,
+}
+
+impl Default for Phrases {
+	fn default() -> Self {
+		Self::new(Form::default(), &mut SmallRng::from_entropy())
+	}
+}
+
+impl Phrases {
+	/// Write a new motif and develop it into the given form
+	///
+	/// # Panics
+	///
+	/// This function would panic if a random motif came out empty, but every motif fills a bar.
+	pub fn new(form: Form, rng: &mut impl Rng) -> Self {
+		let motif = random_motif(rng);
+		Self::with_motif(form, motif, rng).expect("Random motifs are never empty")
+	}
+
+	/// Develop a given motif into the given form
+	pub fn with_motif(
+		form: Form,
+		motif: Vec<DegreeNote>,
+		rng: &mut impl Rng,
+	) -> Result<Self, Error> {
+		if motif.is_empty() || motif.iter().any(|n| n.steps == 0) {
+			return Err(Error::EmptyMelody);
+		}
+		let mut letters: Vec<(char, Vec<Development>)> = Vec::new();
+		let mut plan = Vec::new();
+		for (i, &letter) in form.letters().iter().enumerate() {
+			let developments =
+				if let Some((_, developments)) = letters.iter().find(|(l, _)| *l == letter) {
+					developments.clone()
+				} else {
+					let developments = if letters.is_empty() {
+						opening_developments(motif.len(), rng)
+					} else {
+						contrasting_developments(motif.len(), rng)
+					};
+					letters.push((letter, developments.clone()));
+					developments
+				};
+			let cadence = if i + 1 == form.letters().len() {
+				Cadence::Authentic
+			} else {
+				Cadence::Half
+			};
+			plan.extend(phrase(&motif, &developments, cadence));
+		}
+		Ok(Self {
+			form,
+			motif,
+			sections: letters,
+			plan,
+			position: 0,
+			pending: None,
+		})
+	}
+
+	#[must_use]
+	pub fn form(&self) -> &Form {
+		&self.form
+	}
+
+	#[must_use]
+	pub fn motif(&self) -> &[DegreeNote] {
+		&self.motif
+	}
+
+	/// Get the developments behind each distinct letter of the form, in order of appearance
+	#[must_use]
+	pub fn sections(&self) -> &[(char, Vec<Development>)] {
+		&self.sections
+	}
+
+	/// Get every note of the piece, which repeats once finished
+	#[must_use]
+	pub fn plan(&self) -> &[DegreeNote] {
+		&self.plan
+	}
+
+	/// Move on to the next note of the plan
+	fn advance(&mut self) -> DegreeNote {
+		let ret = self.plan[self.position];
+		self.position = (self.position + 1) % self.plan.len();
+		ret
+	}
+}
+
+impl Seed for Phrases {
+	fn get_note(&mut self, key: Key) -> PianoKey {
+		let note = self.pending.take().unwrap_or_else(|| self.advance());
+		fit(note.degree.place(key), key)
+	}
+}
+
+impl Rhythm for Phrases {
+	fn next_pulse(&mut self) -> Pulse {
+		let note = self.advance();
+		self.pending = Some(note);
+		Pulse::Note(note.steps)
+	}
+}
+
+/// Build one phrase: three bars developing the motif, then the cadence
+fn phrase(motif: &[DegreeNote], developments: &[Development], cadence: Cadence) -> Vec<DegreeNote> {
+	let body = BAR * (PHRASE_BARS - 1);
+	let mut ret = Vec::new();
+	let mut total = 0;
+	// Going round the developments again moves up a step, like a sequence
+	for (round, development) in (0i16..).flat_map(|r| developments.iter().map(move |d| (r, d))) {
+		for note in Development::Transpose(round).apply(&development.apply(motif)) {
+			let steps = note.steps.min(body - total);
+			ret.push(DegreeNote { steps, ..note });
+			total += steps;
+			if total == body {
+				ret.extend(cadence.notes());
+				return ret;
+			}
+		}
+	}
+	unreachable!("The developments repeat until the phrase is full")
+}
+
+/// Write a motif of one bar, moving mostly by step from the tonic, third or fifth
+fn random_motif(rng: &mut impl Rng) -> Vec<DegreeNote> {
+	let mut step = *[0, 2, 4].choose(rng).expect("There are starting notes");
+	let mut ret = Vec::new();
+	let mut total = 0;
+	while total < BAR {
+		let steps = [2, 4, 4, 4, 8]
+			.choose(rng)
+			.copied()
+			.expect("There are lengths")
+			.min(BAR - total);
+		ret.push(DegreeNote {
+			degree: Degree {
+				step,
+				alteration: 0,
+			},
+			steps,
+		});
+		total += steps;
+		step += [-2, -1, -1, 1, 1, 2, 3]
+			.choose(rng)
+			.expect("There are moves");
+	}
+	ret
+}
+
+/// The first phrase states the motif, then moves it somewhere new
+fn opening_developments(len: usize, rng: &mut impl Rng) -> Vec<Development> {
+	vec![
+		Development::Repeat,
+		Development::Transpose(*[-1, 1, 2].choose(rng).expect("There are transpositions")),
+		Development::Fragment((len / 2).max(1)),
+	]
+}
+
+/// Later phrases contrast with the first, leaning on the bigger transformations
+fn contrasting_developments(len: usize, rng: &mut impl Rng) -> Vec<Development> {
+	let mut choices = vec![
+		Development::Invert,
+		Development::Retrograde,
+		Development::Augment,
+		Development::Diminish,
+		Development::Transpose(*[-2, 3, 4].choose(rng).expect("There are transpositions")),
+		Development::Fragment((len / 2).max(1)),
+	];
+	choices.shuffle(rng);
+	choices.truncate(3);
+	choices
+}
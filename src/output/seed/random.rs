@@ -1,20 +1,47 @@
 //! The `RandomSeed` will produce notes randomly.
 
-use super::Seed;
+use super::{DegreeWeights, Seed};
 use crate::theory::{key::Key, piano_key::PianoKey};
-use rand::{rngs::SmallRng, seq::SliceRandom, SeedableRng};
+use rand::{
+	distributions::WeightedIndex, prelude::Distribution, rngs::SmallRng, seq::SliceRandom,
+	SeedableRng,
+};
 
-pub struct Random(SmallRng);
+pub struct Random {
+	rng: SmallRng,
+	weights: DegreeWeights,
+	previous: Option<PianoKey>,
+}
 
 impl Default for Random {
 	fn default() -> Self {
-		Self(SmallRng::from_entropy())
+		Self {
+			rng: SmallRng::from_entropy(),
+			weights: DegreeWeights::default(),
+			previous: None,
+		}
+	}
+}
+
+impl Random {
+	/// Favour some degrees of the scale over others
+	#[must_use]
+	pub fn with_weights(mut self, weights: DegreeWeights) -> Self {
+		self.weights = weights;
+		self
 	}
 }
 
 impl Seed for Random {
 	fn get_note(&mut self, key: Key) -> PianoKey {
 		let keys = key.all_keys();
-		*keys.choose(&mut self.0).unwrap()
+		let weights = self.weights.weigh(&keys, key, self.previous);
+		// Weights that rule out every key fall back to an even choice
+		let ret = match WeightedIndex::new(&weights) {
+			Ok(index) => keys[index.sample(&mut self.rng)],
+			Err(_) => *keys.choose(&mut self.rng).unwrap(),
+		};
+		self.previous = Some(ret);
+		ret
 	}
 }
@@ -0,0 +1,56 @@
+//! The `Sequence` seed plays a melody written out in advance, over and over.
+//! It keeps the melody's own keys and lengths, whatever the key
+
+use super::Seed;
+use crate::{
+	error::Error,
+	output::rhythm::{Pulse, Rhythm},
+	theory::{key::Key, melody::MelodyNote, piano_key::PianoKey},
+};
+
+pub struct Sequence {
+	notes: Vec<MelodyNote>,
+	position: usize,
+	/// A note chosen by the rhythm that has not been played yet
+	pending: Option<MelodyNote>,
+}
+
+impl Sequence {
+	/// Play a melody of at least one note
+	pub fn new(notes: Vec<MelodyNote>) -> Result<Self, Error> {
+		if notes.is_empty() || notes.iter().any(|n| n.steps == 0) {
+			return Err(Error::EmptyMelody);
+		}
+		Ok(Self {
+			notes,
+			position: 0,
+			pending: None,
+		})
+	}
+
+	#[must_use]
+	pub fn notes(&self) -> &[MelodyNote] {
+		&self.notes
+	}
+
+	/// Move on to the next note, starting over after the last
+	fn advance(&mut self) -> MelodyNote {
+		let ret = self.notes[self.position];
+		self.position = (self.position + 1) % self.notes.len();
+		ret
+	}
+}
+
+impl Seed for Sequence {
+	fn get_note(&mut self, _key: Key) -> PianoKey {
+		self.pending.take().unwrap_or_else(|| self.advance()).key
+	}
+}
+
+impl Rhythm for Sequence {
+	fn next_pulse(&mut self) -> Pulse {
+		let note = self.advance();
+		self.pending = Some(note);
+		Pulse::Note(note.steps)
+	}
+}
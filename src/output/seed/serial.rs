@@ -0,0 +1,147 @@
+//! The `Serial` seed plays the forms of a twelve-tone row in turn.
+
+use super::Seed;
+use crate::theory::{
+	key::Key,
+	note::Note,
+	piano_key::PianoKey,
+	scale::Scale,
+	semitone::Semitones,
+	tone_row::{RowForm, ToneRow},
+};
+use rand::{rngs::SmallRng, SeedableRng};
+use std::{io, str::FromStr};
+
+pub struct Serial {
+	row: ToneRow,
+	forms: Vec<RowForm>,
+	form: usize,
+	position: usize,
+	previous: Option<PianoKey>,
+}
+
+impl Default for Serial {
+	fn default() -> Self {
+		Self::new(ToneRow::random(&mut SmallRng::from_entropy()))
+	}
+}
+
+impl Serial {
+	/// Play the row as given, over and over
+	#[must_use]
+	pub fn new(row: ToneRow) -> Self {
+		Self {
+			row,
+			forms: vec![RowForm::Prime(0)],
+			form: 0,
+			position: 0,
+			previous: None,
+		}
+	}
+
+	/// Cycle through these forms instead, returning to the first after the last.
+	/// An empty list keeps the current forms.
+	#[must_use]
+	pub fn with_forms(mut self, forms: Vec<RowForm>) -> Self {
+		if !forms.is_empty() {
+			self.forms = forms;
+			self.form = 0;
+			self.position = 0;
+		}
+		self
+	}
+
+	#[must_use]
+	pub fn row(&self) -> ToneRow {
+		self.row
+	}
+
+	#[must_use]
+	pub fn forms(&self) -> &[RowForm] {
+		&self.forms
+	}
+
+	/// Lay a whole row form out over the range of a key, moving as little as possible between notes
+	#[must_use]
+	pub fn voice(&self, form: RowForm, key: Key) -> Vec<PianoKey> {
+		let mut previous = None;
+		self.row
+			.form(form)
+			.iter()
+			.map(|&pc| {
+				let ret = place(pc, key, previous);
+				previous = Some(ret);
+				ret
+			})
+			.collect()
+	}
+}
+
+impl Seed for Serial {
+	fn get_note(&mut self, key: Key) -> PianoKey {
+		let pc = self.row.form(self.forms[self.form])[self.position];
+		self.position += 1;
+		if self.position == self.row.pitch_classes().len() {
+			self.position = 0;
+			self.form = (self.form + 1) % self.forms.len();
+		}
+		let ret = place(pc, key, self.previous);
+		self.previous = Some(ret);
+		ret
+	}
+}
+
+/// Find the key for a pitch class within the key's range, as close as possible to the previous note.
+/// Rows are chromatic, so only the range of the key matters, not its scale.
+fn place(pitch_class: u8, key: Key, previous: Option<PianoKey>) -> PianoKey {
+	let candidates = Key::new(Scale::Chromatic, key.base_note, key.octaves)
+		.all_keys()
+		.into_iter()
+		.filter(|k| k.note.pitch_class() == pitch_class);
+	let closest = match previous {
+		Some(previous) => {
+			candidates.min_by_key(|k| (k.semitones_from_c0() - previous.semitones_from_c0()).abs())
+		},
+		None => candidates.min_by_key(|k| k.semitones_from_c0()),
+	};
+	closest.unwrap_or_else(|| PianoKey {
+		note: Note::from(Semitones::from(
+			i8::try_from(pitch_class).unwrap_or_default(),
+		)),
+		octave: key.base_note.octave,
+	})
+}
+
+/// A `RowSource` says where a `Serial` seed's row comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowSource {
+	Random,
+	AllInterval,
+	Given(ToneRow),
+}
+
+impl RowSource {
+	/// Produce the row, generating one if needed
+	#[must_use]
+	pub fn build(self) -> ToneRow {
+		let mut rng = SmallRng::from_entropy();
+		match self {
+			RowSource::Random => ToneRow::random(&mut rng),
+			RowSource::AllInterval => ToneRow::all_interval(&mut rng),
+			RowSource::Given(row) => row,
+		}
+	}
+}
+
+impl FromStr for RowSource {
+	type Err = io::Error;
+
+	/// Accepts "random", "all-interval", or a row in any form `ToneRow` can parse
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"random" => Ok(RowSource::Random),
+			"all-interval" | "all_interval" | "allinterval" => Ok(RowSource::AllInterval),
+			_ => Ok(RowSource::Given(ToneRow::from_str(s)?)),
+		}
+	}
+}
@@ -0,0 +1,178 @@
+//! The `Walk` seed wanders up and down the key a few steps at a time, the way a singer would.
+//! Steps are counted in notes of the scale, so a step of 2 is a third in a diatonic key
+
+use super::{DegreeWeights, Seed};
+use crate::{
+	error::Error,
+	theory::{key::Key, piano_key::PianoKey},
+};
+use rand::{distributions::WeightedIndex, prelude::Distribution, rngs::SmallRng, SeedableRng};
+use std::str::FromStr;
+
+/// How likely each step size is by default, from repeating a note up to an octave leap
+const DEFAULT_STEPS: [f64; 8] = [0.5, 6.0, 3.0, 1.5, 1.0, 0.5, 0.25, 0.5];
+
+/// Where gravity pulls the melody back to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Center {
+	/// The nearest base note of the key, in any octave
+	#[default]
+	Tonic,
+	/// The middle of the key's range
+	Middle,
+}
+
+impl FromStr for Center {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"tonic" => Ok(Center::Tonic),
+			"middle" | "center" | "centre" => Ok(Center::Middle),
+			_ => Err(Error::BadCenter(s.to_string())),
+		}
+	}
+}
+
+pub struct Walk {
+	steps: Vec<f64>,
+	max_leap: usize,
+	gravity: f64,
+	center: Center,
+	recovery: Option<usize>,
+	weights: DegreeWeights,
+	previous: Option<PianoKey>,
+	/// After a large leap, the direction of the step that has to follow
+	recovering: Option<isize>,
+	rng: SmallRng,
+}
+
+impl Default for Walk {
+	fn default() -> Self {
+		Self {
+			steps: DEFAULT_STEPS.to_vec(),
+			max_leap: 7,
+			gravity: 0.5,
+			center: Center::default(),
+			recovery: Some(3),
+			weights: DegreeWeights::default(),
+			previous: None,
+			recovering: None,
+			rng: SmallRng::from_entropy(),
+		}
+	}
+}
+
+impl Walk {
+	/// Weigh each step size, starting with repeating the same note.
+	/// Sizes past the end of the list are never taken. An empty list keeps the current weights
+	#[must_use]
+	pub fn with_steps(mut self, steps: Vec<f64>) -> Self {
+		if !steps.is_empty() {
+			self.steps = steps;
+		}
+		self
+	}
+
+	/// Never leap further than this many scale steps
+	#[must_use]
+	pub fn with_max_leap(mut self, max_leap: usize) -> Self {
+		self.max_leap = max_leap;
+		self
+	}
+
+	/// Pull the melody back toward a center. At 0 there is no pull,
+	/// and each step of gravity makes moving one note closer about 2.7 times as likely as moving away
+	#[must_use]
+	pub fn with_gravity(mut self, gravity: f64, center: Center) -> Self {
+		self.gravity = gravity.max(0.0);
+		self.center = center;
+		self
+	}
+
+	/// After a leap of more than this many scale steps, step back the other way.
+	/// `None` lets leaps follow each other freely
+	#[must_use]
+	pub fn with_leap_recovery(mut self, recovery: Option<usize>) -> Self {
+		self.recovery = recovery;
+		self
+	}
+
+	/// Favour landing on some degrees of the scale over others
+	#[must_use]
+	pub fn with_weights(mut self, weights: DegreeWeights) -> Self {
+		self.weights = weights;
+		self
+	}
+
+	/// Measure how far a position in the key is from the center of gravity, in scale steps
+	fn distance_from_center(&self, index: usize, key: Key, len: usize) -> usize {
+		match self.center {
+			Center::Tonic => {
+				let notes_per_octave = key.scale.get_intervals().len();
+				let below = index % notes_per_octave;
+				let above = notes_per_octave - below;
+				// The top tonic is only in range if the walk can reach it
+				if index + above < len {
+					below.min(above)
+				} else {
+					below
+				}
+			},
+			Center::Middle => index.abs_diff(len / 2),
+		}
+	}
+
+	/// Find the start of the walk, the center itself
+	fn start(&self, len: usize) -> usize {
+		match self.center {
+			Center::Tonic => 0,
+			Center::Middle => len / 2,
+		}
+	}
+}
+
+impl Seed for Walk {
+	fn get_note(&mut self, key: Key) -> PianoKey {
+		let keys = key.all_keys();
+		let Some(previous) = self.previous else {
+			let ret = keys[self.start(keys.len())];
+			self.previous = Some(ret);
+			return ret;
+		};
+		let current = (0..keys.len())
+			.min_by_key(|&i| (keys[i].semitones_from_c0() - previous.semitones_from_c0()).abs())
+			.unwrap_or_default();
+		// Recover from a leap with a single step back, unless that would leave the key
+		let recovery = self
+			.recovering
+			.take()
+			.and_then(|direction| current.checked_add_signed(direction))
+			.filter(|&i| i < keys.len());
+		let next = recovery.unwrap_or_else(|| {
+			let from = self.distance_from_center(current, key, keys.len());
+			let degree_weights = self.weights.weigh(&keys, key, Some(previous));
+			let (candidates, weights): (Vec<usize>, Vec<f64>) = (0..keys.len())
+				.filter(|i| i.abs_diff(current) <= self.max_leap)
+				.map(|i| {
+					let weight = self.steps.get(i.abs_diff(current)).copied().unwrap_or(0.0);
+					let to = self.distance_from_center(i, key, keys.len());
+					#[allow(clippy::cast_precision_loss)]
+					let pull = (-self.gravity * (to as f64 - from as f64)).exp();
+					(i, weight.max(0.0) * pull * degree_weights[i])
+				})
+				.unzip();
+			WeightedIndex::new(&weights)
+				.map_or(current, |index| candidates[index.sample(&mut self.rng)])
+		});
+		if self
+			.recovery
+			.is_some_and(|limit| next.abs_diff(current) > limit)
+		{
+			self.recovering = Some(if next > current { -1 } else { 1 });
+		}
+		let ret = keys[next];
+		self.previous = Some(ret);
+		ret
+	}
+}
@@ -0,0 +1,162 @@
+//! `DegreeWeights` make some notes of a scale more likely than others, giving a melody a tonal center.
+//! Weights are written as numbers starting from the base note, like "4,1,2,1,3,1,0.5",
+//! optionally followed by "resolve" to send the leading tone straight to the tonic
+
+use super::Degree;
+use crate::{
+	error::Error,
+	theory::{key::Key, piano_key::PianoKey},
+};
+use std::{fmt, fs, io, path::Path, str::FromStr};
+
+/// The named weightings, with the weights they stand for
+const PRESETS: [(&str, &str); 4] = [
+	("flat", "1"),
+	("tonal", "4,1,2.5,1.5,3,1.5,0.5,resolve"),
+	("tonic-dominant", "4,0.5,1,0.5,3,0.5,0.5,resolve"),
+	("pentatonic", "1,1,1,0,1,1,0"),
+];
+
+/// The word that turns on leading tone resolution
+const RESOLVE: &str = "resolve";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DegreeWeights {
+	weights: Vec<f64>,
+	resolve_leading_tone: bool,
+}
+
+impl Default for DegreeWeights {
+	/// Every degree is equally likely
+	fn default() -> Self {
+		Self::new(Vec::new())
+	}
+}
+
+impl DegreeWeights {
+	/// Weigh each degree of the scale, starting from the base note.
+	/// Degrees past the end of the list weigh 1
+	#[must_use]
+	pub fn new(weights: Vec<f64>) -> Self {
+		Self {
+			weights: weights.into_iter().map(|w| w.max(0.0)).collect(),
+			resolve_leading_tone: false,
+		}
+	}
+
+	/// Follow the leading tone, a semitone below the base note, with the base note above it
+	#[must_use]
+	pub fn with_leading_tone_resolution(mut self, resolve: bool) -> Self {
+		self.resolve_leading_tone = resolve;
+		self
+	}
+
+	/// Look up one of the named weightings
+	#[must_use]
+	pub fn preset(name: &str) -> Option<Self> {
+		PRESETS
+			.iter()
+			.find(|(n, _)| n.eq_ignore_ascii_case(name))
+			.and_then(|(_, weights)| weights.parse().ok())
+	}
+
+	/// List the names of the presets
+	#[must_use]
+	pub fn presets() -> Vec<&'static str> {
+		PRESETS.iter().map(|(name, _)| *name).collect()
+	}
+
+	/// Read weights from a file, which may spread them over several lines and use # comments
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+		let contents = fs::read_to_string(path)?;
+		let weights = contents
+			.lines()
+			.map(|l| l.split('#').next().unwrap_or_default())
+			.collect::<Vec<_>>()
+			.join(" ");
+		Ok(Self::from_str(&weights)?)
+	}
+
+	/// Get the weight of a degree in a key. Notes outside the scale weigh nothing
+	#[must_use]
+	pub fn weight(&self, piano_key: PianoKey, key: Key) -> f64 {
+		let degree = Degree::of(piano_key, key);
+		if degree.alteration != 0 {
+			return 0.0;
+		}
+		let len = i16::try_from(key.scale.get_intervals().len()).unwrap_or(i16::MAX);
+		usize::try_from(degree.step.rem_euclid(len))
+			.ok()
+			.and_then(|i| self.weights.get(i).copied())
+			.unwrap_or(1.0)
+	}
+
+	/// Weigh each of a list of keys as the note to follow the previous one
+	#[must_use]
+	pub fn weigh(&self, keys: &[PianoKey], key: Key, previous: Option<PianoKey>) -> Vec<f64> {
+		let base = key.base_note.semitones_from_c0();
+		let resolution = previous
+			.filter(|p| {
+				self.resolve_leading_tone && (p.semitones_from_c0() - base).rem_euclid(12) == 11
+			})
+			.and_then(|p| {
+				keys.iter()
+					.position(|k| k.semitones_from_c0() == p.semitones_from_c0() + 1)
+			});
+		if let Some(tonic) = resolution {
+			return (0..keys.len())
+				.map(|i| if i == tonic { 1.0 } else { 0.0 })
+				.collect();
+		}
+		keys.iter().map(|&k| self.weight(k, key)).collect()
+	}
+}
+
+impl fmt::Display for DegreeWeights {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut words = self
+			.weights
+			.iter()
+			.map(ToString::to_string)
+			.collect::<Vec<_>>();
+		if self.resolve_leading_tone {
+			words.push(RESOLVE.into());
+		}
+		write!(f, "{}", words.join(","))
+	}
+}
+
+impl FromStr for DegreeWeights {
+	type Err = Error;
+
+	/// Accepts a preset name, or weights separated by commas or spaces with an optional "resolve"
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Some((_, weights)) = PRESETS
+			.iter()
+			.find(|(n, _)| n.eq_ignore_ascii_case(s.trim()))
+		{
+			return Self::from_str(weights);
+		}
+		let mut weights = Vec::new();
+		let mut resolve = false;
+		for word in s
+			.split(|c: char| c == ',' || c.is_whitespace())
+			.filter(|w| !w.is_empty())
+		{
+			if word.eq_ignore_ascii_case(RESOLVE) {
+				resolve = true;
+			} else {
+				weights.push(
+					word.parse::<f64>()
+						.ok()
+						.filter(|w| *w >= 0.0)
+						.ok_or_else(|| Error::BadWeight {
+							word: word.to_string(),
+							presets: Self::presets(),
+						})?,
+				);
+			}
+		}
+		Ok(Self::new(weights).with_leading_tone_resolution(resolve))
+	}
+}
@@ -0,0 +1,52 @@
+//! A `Signal` is a stream of samples at a known rate, which is all it takes to render sound to a file.
+//! With the `playback` feature every signal here is also a rodio `Source`, ready to play on a device
+
+use std::time::Duration;
+
+pub trait Signal: Iterator<Item = f32> {
+	/// Count the channels the samples are interleaved across
+	fn channels(&self) -> u16 {
+		1
+	}
+
+	/// Count the samples in each second of each channel
+	fn sample_rate(&self) -> u32;
+
+	/// Say how long the signal lasts, if it ends and that is known yet
+	fn total_duration(&self) -> Option<Duration> {
+		None
+	}
+}
+
+/// Play signals through rodio, which asks the same of them
+#[cfg(feature = "playback")]
+macro_rules! playable {
+	($($signal:ty),*) => {
+		$(
+			impl rodio::Source for $signal {
+				#[inline]
+				fn current_frame_len(&self) -> Option<usize> {
+					None
+				}
+
+				#[inline]
+				fn channels(&self) -> u16 {
+					Signal::channels(self)
+				}
+
+				#[inline]
+				fn sample_rate(&self) -> u32 {
+					Signal::sample_rate(self)
+				}
+
+				#[inline]
+				fn total_duration(&self) -> Option<Duration> {
+					Signal::total_duration(self)
+				}
+			}
+		)*
+	};
+}
+
+#[cfg(feature = "playback")]
+playable!(super::ChordPlayer, super::MusicMaker, super::Synth);
@@ -0,0 +1,230 @@
+//! A `Synth` plays keys as they are pressed and released, from any thread, shaping each with an envelope.
+//! It sounds forever, silent while no key is held
+
+use super::{envelope::Envelope, signal::Signal, waveform::Waveform};
+use crate::{
+	f64_to_f32,
+	theory::{
+		hertz::{Hertz, SAMPLE_RATE, STANDARD_PITCH},
+		piano_key::PianoKey,
+		pitch::Pitch,
+	},
+};
+use std::{
+	io,
+	sync::mpsc::{self, Receiver, Sender},
+	time::Duration,
+};
+
+/// How loud a single held key is, leaving room for a few more before the sound clips
+const VOICE_AMPLITUDE: f64 = 0.4;
+
+/// Where a note is in its envelope
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+	Attack,
+	Decay,
+	Sustain,
+	/// Falling by this much each sample
+	Release(f64),
+}
+
+/// A key that is sounding
+struct Voice {
+	key: PianoKey,
+	frequency: f64,
+	/// Samples since the note started, to keep its wave in phase
+	sample: u64,
+	stage: Stage,
+	level: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Message {
+	NoteOn(PianoKey),
+	NoteOff(PianoKey),
+	AllOff,
+}
+
+pub struct Synth {
+	waveform: Waveform,
+	envelope: Envelope,
+	/// The frequency A4 sounds at
+	tuning: Hertz,
+	voices: Vec<Voice>,
+	sample_rate: u32,
+	messages: Option<Receiver<Message>>,
+}
+
+impl Default for Synth {
+	fn default() -> Self {
+		Self {
+			waveform: Waveform::default(),
+			envelope: Envelope::default(),
+			tuning: STANDARD_PITCH,
+			voices: Vec::new(),
+			sample_rate: u32::try_from(SAMPLE_RATE).unwrap(),
+			messages: None,
+		}
+	}
+}
+
+impl Synth {
+	#[must_use]
+	pub fn with_waveform(mut self, waveform: Waveform) -> Self {
+		self.waveform = waveform;
+		self
+	}
+
+	#[must_use]
+	pub fn with_envelope(mut self, envelope: Envelope) -> Self {
+		self.envelope = envelope;
+		self
+	}
+
+	/// Tune A4 to another frequency, moving every other key with it
+	#[must_use]
+	pub fn with_tuning(mut self, a4: Hertz) -> Self {
+		self.tuning = a4;
+		self
+	}
+
+	/// Get a handle to press and release keys with from another thread.
+	/// Only the latest handle is listened to
+	pub fn keys(&mut self) -> SynthKeys {
+		let (sender, receiver) = mpsc::channel();
+		self.messages = Some(receiver);
+		SynthKeys(sender)
+	}
+
+	/// Press a key, starting its note again if it is already sounding
+	pub fn note_on(&mut self, key: PianoKey) {
+		if let Some(voice) = self.voices.iter_mut().find(|v| v.key == key) {
+			// Rise again from wherever the note is, so it doesn't click
+			voice.stage = Stage::Attack;
+			return;
+		}
+		self.voices.push(Voice {
+			key,
+			frequency: Pitch::from(key).tuned(self.tuning).into(),
+			sample: 0,
+			stage: Stage::Attack,
+			level: 0.0,
+		});
+	}
+
+	/// Release a key, letting its note die away
+	pub fn note_off(&mut self, key: PianoKey) {
+		let release = self.samples(self.envelope.release);
+		for voice in self.voices.iter_mut().filter(|v| v.key == key) {
+			voice.stage = Stage::Release(voice.level / release);
+		}
+	}
+
+	/// Release every key
+	pub fn all_off(&mut self) {
+		let keys = self.voices.iter().map(|v| v.key).collect::<Vec<_>>();
+		for key in keys {
+			self.note_off(key);
+		}
+	}
+
+	/// List the keys sounding, including those dying away
+	#[must_use]
+	pub fn sounding(&self) -> Vec<PianoKey> {
+		self.voices.iter().map(|v| v.key).collect()
+	}
+
+	/// Count the samples a stretch of time takes, at least one
+	fn samples(&self, duration: Duration) -> f64 {
+		(duration.as_secs_f64() * f64::from(self.sample_rate)).max(1.0)
+	}
+
+	/// Move a voice one sample along its envelope, returning whether it still sounds
+	fn advance(&self, voice: &mut Voice) -> bool {
+		let Envelope {
+			attack,
+			decay,
+			sustain,
+			..
+		} = self.envelope;
+		let sustain = sustain.clamp(0.0, 1.0);
+		match voice.stage {
+			Stage::Attack => {
+				voice.level += 1.0 / self.samples(attack);
+				if voice.level >= 1.0 {
+					voice.level = 1.0;
+					voice.stage = Stage::Decay;
+				}
+			},
+			Stage::Decay => {
+				voice.level -= (1.0 - sustain) / self.samples(decay);
+				if voice.level <= sustain {
+					voice.level = sustain;
+					voice.stage = Stage::Sustain;
+				}
+			},
+			Stage::Sustain => {},
+			Stage::Release(step) => voice.level -= step,
+		}
+		voice.sample += 1;
+		!matches!(voice.stage, Stage::Release(_)) || voice.level > 0.0
+	}
+}
+
+impl Iterator for Synth {
+	type Item = f32;
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(messages) = &self.messages {
+			for message in messages.try_iter().collect::<Vec<_>>() {
+				match message {
+					Message::NoteOn(key) => self.note_on(key),
+					Message::NoteOff(key) => self.note_off(key),
+					Message::AllOff => self.all_off(),
+				}
+			}
+		}
+		let rate = f64::from(self.sample_rate);
+		let mut value = 0.0;
+		let mut voices = std::mem::take(&mut self.voices);
+		#[allow(clippy::cast_precision_loss)]
+		voices.retain_mut(|voice| {
+			let phase = voice.frequency * voice.sample as f64 / rate;
+			value += self.waveform.sample(phase) * voice.level * VOICE_AMPLITUDE;
+			self.advance(voice)
+		});
+		self.voices = voices;
+		Some(f64_to_f32(value.clamp(-1.0, 1.0)))
+	}
+}
+
+impl Signal for Synth {
+	#[inline]
+	fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+}
+
+/// A handle for pressing and releasing the keys of a playing `Synth`
+#[derive(Debug, Clone)]
+pub struct SynthKeys(Sender<Message>);
+
+impl SynthKeys {
+	fn send(&self, message: Message) -> Result<(), io::Error> {
+		self.0
+			.send(message)
+			.map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "The synth has stopped playing"))
+	}
+
+	pub fn note_on(&self, key: PianoKey) -> Result<(), io::Error> {
+		self.send(Message::NoteOn(key))
+	}
+
+	pub fn note_off(&self, key: PianoKey) -> Result<(), io::Error> {
+		self.send(Message::NoteOff(key))
+	}
+
+	pub fn all_off(&self) -> Result<(), io::Error> {
+		self.send(Message::AllOff)
+	}
+}
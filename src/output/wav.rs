@@ -0,0 +1,61 @@
+//! Renders any sound source to a WAV file of 16-bit samples, for when there is nothing to play it live
+
+use super::signal::Signal;
+use std::{
+	fs::File,
+	io::{self, BufWriter, Write},
+	path::Path,
+	time::Duration,
+};
+
+/// Write the first stretch of a source to a WAV file
+pub fn write_wav(
+	path: impl AsRef<Path>,
+	source: impl Signal,
+	duration: Duration,
+) -> Result<(), io::Error> {
+	let mut writer = BufWriter::new(File::create(path)?);
+	encode_wav(&mut writer, source, duration)?;
+	writer.flush()
+}
+
+/// Encode the first stretch of a source as WAV, stopping early if the source runs out
+pub fn encode_wav(
+	writer: &mut impl Write,
+	source: impl Signal,
+	duration: Duration,
+) -> Result<(), io::Error> {
+	let channels = source.channels().max(1);
+	let sample_rate = source.sample_rate();
+	// Whole frames only, so every channel gets the same number of samples
+	#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+	let frames = (duration.as_secs_f64() * f64::from(sample_rate)).round() as usize;
+	let samples = source
+		.take(frames.saturating_mul(usize::from(channels)))
+		.map(|s| {
+			#[allow(clippy::cast_possible_truncation)]
+			let sample = (s.clamp(-1.0, 1.0) * f32::from(i16::MAX)).round() as i16;
+			sample
+		})
+		.collect::<Vec<_>>();
+	let too_long = || io::Error::new(io::ErrorKind::InvalidInput, "Too long for a WAV file");
+	let data_len = u32::try_from(samples.len() * 2).map_err(|_| too_long())?;
+	let block_align = channels * 2;
+	writer.write_all(b"RIFF")?;
+	writer.write_all(&(36 + data_len).to_le_bytes())?;
+	writer.write_all(b"WAVEfmt ")?;
+	writer.write_all(&16u32.to_le_bytes())?;
+	// Uncompressed PCM
+	writer.write_all(&1u16.to_le_bytes())?;
+	writer.write_all(&channels.to_le_bytes())?;
+	writer.write_all(&sample_rate.to_le_bytes())?;
+	writer.write_all(&(sample_rate * u32::from(block_align)).to_le_bytes())?;
+	writer.write_all(&block_align.to_le_bytes())?;
+	writer.write_all(&16u16.to_le_bytes())?;
+	writer.write_all(b"data")?;
+	writer.write_all(&data_len.to_le_bytes())?;
+	for sample in samples {
+		writer.write_all(&sample.to_le_bytes())?;
+	}
+	Ok(())
+}
@@ -0,0 +1,75 @@
+//! The shape of the wave each note is played with
+
+use std::{f64::consts::PI, fmt, io, str::FromStr};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Waveform {
+	/// A pure tone
+	#[default]
+	Sine,
+	/// Hollow and reedy, with only odd harmonics
+	Square,
+	/// Softer than a square, still with only odd harmonics
+	Triangle,
+	/// Bright and buzzy, with every harmonic
+	Sawtooth,
+}
+
+impl Waveform {
+	#[must_use]
+	pub fn all() -> [Self; 4] {
+		[
+			Waveform::Sine,
+			Waveform::Square,
+			Waveform::Triangle,
+			Waveform::Sawtooth,
+		]
+	}
+
+	/// Get the height of the wave, from -1 to 1, at a point from 0 to 1 through its cycle, starting at zero and rising
+	#[must_use]
+	pub fn sample(self, phase: f64) -> f64 {
+		let phase = phase.rem_euclid(1.0);
+		match self {
+			Waveform::Sine => (2.0 * PI * phase).sin(),
+			Waveform::Square => {
+				if phase < 0.5 {
+					1.0
+				} else {
+					-1.0
+				}
+			},
+			Waveform::Triangle => 1.0 - 4.0 * ((phase + 0.25).rem_euclid(1.0) - 0.5).abs(),
+			Waveform::Sawtooth => 2.0 * (phase + 0.5).rem_euclid(1.0) - 1.0,
+		}
+	}
+}
+
+impl fmt::Display for Waveform {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let name = match self {
+			Waveform::Sine => "sine",
+			Waveform::Square => "square",
+			Waveform::Triangle => "triangle",
+			Waveform::Sawtooth => "sawtooth",
+		};
+		write!(f, "{name}")
+	}
+}
+
+impl FromStr for Waveform {
+	type Err = io::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"sine" | "sin" => Ok(Waveform::Sine),
+			"square" | "sq" => Ok(Waveform::Square),
+			"triangle" | "tri" => Ok(Waveform::Triangle),
+			"sawtooth" | "saw" => Ok(Waveform::Sawtooth),
+			_ => Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!("{s} is not a waveform, try sine, square, triangle or sawtooth"),
+			)),
+		}
+	}
+}
@@ -0,0 +1,123 @@
+//! A `Qwerty` layout turns a computer keyboard into two octaves of piano keys, like a tracker does.
+//! The bottom row plays from C in the current octave, with the row above it as the black keys,
+//! and the top row plays on from C an octave higher, with the number row as its black keys
+
+use crate::theory::{key::Key, note::Note, piano_key::PianoKey, semitone::Semitones};
+
+/// Each typed character and how many semitones above the C of the current octave it plays
+const LAYOUT: [(char, i16); 37] = [
+	('z', 0),
+	('s', 1),
+	('x', 2),
+	('d', 3),
+	('c', 4),
+	('v', 5),
+	('g', 6),
+	('b', 7),
+	('h', 8),
+	('n', 9),
+	('j', 10),
+	('m', 11),
+	(',', 12),
+	('l', 13),
+	('.', 14),
+	(';', 15),
+	('/', 16),
+	('q', 12),
+	('2', 13),
+	('w', 14),
+	('3', 15),
+	('e', 16),
+	('r', 17),
+	('5', 18),
+	('t', 19),
+	('6', 20),
+	('y', 21),
+	('7', 22),
+	('u', 23),
+	('i', 24),
+	('9', 25),
+	('o', 26),
+	('0', 27),
+	('p', 28),
+	('[', 29),
+	('=', 30),
+	(']', 31),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Qwerty {
+	key: Key,
+	octave: u8,
+	snap: bool,
+}
+
+impl Qwerty {
+	/// Start from the octave of the key's base note, spelling notes the way the key does
+	#[must_use]
+	pub fn new(key: Key) -> Self {
+		Self {
+			key,
+			octave: key.base_note.octave.min(Self::max_octave()),
+			snap: false,
+		}
+	}
+
+	/// Move notes outside the key to the nearest note in it, so there are no wrong notes
+	#[must_use]
+	pub fn with_snap(mut self, snap: bool) -> Self {
+		self.snap = snap;
+		self
+	}
+
+	/// Get the octave the bottom row starts in
+	#[must_use]
+	pub fn octave(&self) -> u8 {
+		self.octave
+	}
+
+	/// Shift both rows up an octave, as long as the top row still starts on the piano
+	pub fn octave_up(&mut self) {
+		self.octave = (self.octave + 1).min(Self::max_octave());
+	}
+
+	/// Shift both rows down an octave
+	pub fn octave_down(&mut self) {
+		self.octave = self.octave.saturating_sub(1);
+	}
+
+	/// The highest octave the bottom row can start in, leaving one above it for the top row
+	fn max_octave() -> u8 {
+		PianoKey::max_octave() - 1
+	}
+
+	/// Find the piano key a typed character plays, if it plays one at all
+	#[must_use]
+	pub fn key_for(&self, c: char) -> Option<PianoKey> {
+		let c = c.to_ascii_lowercase();
+		let (_, offset) = LAYOUT.iter().find(|(k, _)| *k == c)?;
+		let semitones = i16::from(self.octave) * 12 + offset;
+		let notes = self.key.get_notes();
+		let spelled = |s: i16| {
+			notes
+				.iter()
+				.find(|n| i16::from(n.pitch_class()) == s.rem_euclid(12))
+				.copied()
+		};
+		let (note, semitones) = if self.snap {
+			// Look the same distance down before up, so ties go down
+			(0..12)
+				.flat_map(|d| [semitones - d, semitones + d])
+				.find_map(|s| spelled(s).map(|n| (n, s)))?
+		} else {
+			let note = spelled(semitones).unwrap_or_else(|| {
+				#[allow(clippy::cast_possible_truncation)]
+				Note::from(Semitones::from(semitones.rem_euclid(12) as i8))
+			});
+			(note, semitones)
+		};
+		let key = PianoKey::with_note(note, semitones);
+		(key.semitones_from_c0() == semitones && key.octave <= PianoKey::max_octave())
+			.then_some(key)
+	}
+}
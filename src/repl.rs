@@ -0,0 +1,314 @@
+//! A `Session` keeps the settings of an interactive session and runs its commands, one per line
+//! like `key D dorian` or `tempo 90`. It leaves the sound to its caller, handing back what to play
+
+use crate::{
+	output::{Controller, MusicMaker, Waveform},
+	theory::{
+		chord::{Chord, RomanNumeral},
+		key::Key,
+		note::Note,
+		piano_key::PianoKey,
+		scale::Scale,
+	},
+};
+use std::{fmt, io, str::FromStr};
+
+/// What the commands of a session look like
+pub const HELP: &str = "\
+key <note> [scale]   Move to a new key, like \"key D dorian\" or \"key Eb4\"
+scale <scale>        Keep the base note but change the scale, like \"scale minor\"
+octaves <count>      Range over this many octaves
+tempo <bpm>          Set the speed in beats per minute
+wave <shape>         Play with a sine, square, triangle or sawtooth wave
+volume <level>       Set how loud to play, from 0 to 1
+play                 Start generating music in the current key
+stop                 Stop the music
+chord <chord>        Sound a chord symbol like Am7, or a Roman numeral like V7 in the key
+tone <key>           Sound a single key, like A4
+show                 Print the current settings
+help                 Print this list
+quit                 Leave the session";
+
+/// A single line typed into a session
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+	/// Move to a new base note, keeping the octave unless one is given, and the scale unless one is given
+	Key {
+		note: Note,
+		octave: Option<u8>,
+		scale: Option<Scale>,
+	},
+	Scale(Scale),
+	Octaves(u8),
+	Tempo(f64),
+	Waveform(Waveform),
+	Volume(f64),
+	Play,
+	Stop,
+	/// A chord symbol or a Roman numeral, left to the session to spell in its key
+	Chord(String),
+	Tone(PianoKey),
+	Show,
+	Help,
+	Quit,
+}
+
+impl FromStr for Command {
+	type Err = io::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut words = s.split_whitespace();
+		let Some(name) = words.next() else {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"Type a command, or help to list them",
+			));
+		};
+		let name = name.to_lowercase();
+		let arguments = words.collect::<Vec<_>>();
+		let expect = |count: usize| {
+			if arguments.len() == count {
+				Ok(())
+			} else {
+				Err(io::Error::new(
+					io::ErrorKind::InvalidInput,
+					format!("{name} takes {count} argument(s), got {}", arguments.len()),
+				))
+			}
+		};
+		let number = |s: &str| {
+			s.parse::<f64>()
+				.ok()
+				.filter(|n| n.is_finite())
+				.ok_or_else(|| {
+					io::Error::new(io::ErrorKind::InvalidInput, format!("{s} is not a number"))
+				})
+		};
+		match name.as_str() {
+			"key" => {
+				let (word, scale) = match arguments[..] {
+					[word] => (word, None),
+					[word, scale] => (word, Some(Scale::from_str(scale)?)),
+					_ => {
+						return Err(io::Error::new(
+							io::ErrorKind::InvalidInput,
+							"key takes a note and maybe a scale, like \"key D dorian\"",
+						))
+					},
+				};
+				let (note, octave) = match PianoKey::from_str(word) {
+					Ok(key) => (key.note, Some(key.octave)),
+					Err(_) => (Note::from_str(word)?, None),
+				};
+				Ok(Self::Key {
+					note,
+					octave,
+					scale,
+				})
+			},
+			"scale" => expect(1).and_then(|()| Ok(Self::Scale(Scale::from_str(arguments[0])?))),
+			"octaves" => expect(1).and_then(|()| {
+				let octaves = arguments[0].parse().map_err(|_| {
+					io::Error::new(
+						io::ErrorKind::InvalidInput,
+						format!("{} is not a number of octaves", arguments[0]),
+					)
+				})?;
+				Ok(Self::Octaves(octaves))
+			}),
+			"tempo" => expect(1).and_then(|()| Ok(Self::Tempo(number(arguments[0])?))),
+			"wave" | "waveform" => {
+				expect(1).and_then(|()| Ok(Self::Waveform(Waveform::from_str(arguments[0])?)))
+			},
+			"volume" => expect(1).and_then(|()| Ok(Self::Volume(number(arguments[0])?))),
+			"play" => expect(0).map(|()| Self::Play),
+			"stop" => expect(0).map(|()| Self::Stop),
+			"chord" => expect(1).map(|()| Self::Chord(arguments[0].to_string())),
+			"tone" => expect(1).and_then(|()| Ok(Self::Tone(PianoKey::from_str(arguments[0])?))),
+			"show" => expect(0).map(|()| Self::Show),
+			"help" | "?" => Ok(Self::Help),
+			"quit" | "exit" => Ok(Self::Quit),
+			_ => Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!("Unknown command {name}, type help to list them"),
+			)),
+		}
+	}
+}
+
+/// What the caller should do after a command
+pub enum Action {
+	/// Print a message
+	Say(String),
+	/// Stop whatever music is playing and play this instead
+	Play(Box<MusicMaker>),
+	/// Stop the music
+	Stop,
+	/// Sound these keys together for a moment at a volume from 0 to 1, over any music
+	Sound(Vec<PianoKey>, f64),
+	/// End the session
+	Quit,
+}
+
+/// The settings an interactive session plays with, and a handle on its music while it plays
+#[derive(Debug)]
+pub struct Session {
+	key: Key,
+	tempo: f64,
+	waveform: Waveform,
+	volume: f64,
+	playing: Option<Controller>,
+}
+
+impl Default for Session {
+	/// Start in C major over one octave at 60 beats per minute
+	fn default() -> Self {
+		Self {
+			key: Key::new(Scale::default(), PianoKey::from_str("C4").unwrap(), 1),
+			tempo: 60.0,
+			waveform: Waveform::default(),
+			volume: 1.0,
+			playing: None,
+		}
+	}
+}
+
+impl Session {
+	#[must_use]
+	pub fn new(key: Key) -> Self {
+		Self {
+			key,
+			..Self::default()
+		}
+	}
+
+	#[must_use]
+	pub fn key(&self) -> Key {
+		self.key
+	}
+
+	/// Check whether music is playing
+	#[must_use]
+	pub fn is_playing(&self) -> bool {
+		self.playing.is_some()
+	}
+
+	/// Parse a line and run it
+	pub fn run_line(&mut self, line: &str) -> Result<Action, io::Error> {
+		self.run(Command::from_str(line)?)
+	}
+
+	/// Run a command, passing any change on to the music that is playing
+	pub fn run(&mut self, command: Command) -> Result<Action, io::Error> {
+		let Key {
+			base_note,
+			scale,
+			octaves,
+		} = self.key;
+		match command {
+			Command::Key {
+				note,
+				octave,
+				scale: new_scale,
+			} => {
+				let base_note = PianoKey {
+					note,
+					octave: octave.unwrap_or(base_note.octave),
+				};
+				Ok(self.set_key(Key::new(new_scale.unwrap_or(scale), base_note, octaves)))
+			},
+			Command::Scale(scale) => Ok(self.set_key(Key::new(scale, base_note, octaves))),
+			Command::Octaves(octaves) => Ok(self.set_key(Key::new(scale, base_note, octaves))),
+			Command::Tempo(tempo) => {
+				self.tempo = tempo.max(1.0);
+				self.control(|c| c.set_tempo(tempo));
+				Ok(Action::Say(format!(
+					"Tempo {} beats per minute",
+					self.tempo
+				)))
+			},
+			Command::Waveform(waveform) => {
+				self.waveform = waveform;
+				self.control(|c| c.set_waveform(waveform));
+				Ok(Action::Say(format!("Playing a {waveform} wave")))
+			},
+			Command::Volume(volume) => {
+				self.volume = volume.clamp(0.0, 1.0);
+				self.control(|c| c.set_volume(volume));
+				Ok(Action::Say(format!("Volume {}", self.volume)))
+			},
+			Command::Play => {
+				let mut music = MusicMaker::new(base_note, scale, octaves)
+					.set_tempo(self.tempo)
+					.set_waveform(self.waveform)
+					.set_volume(self.volume);
+				self.playing = Some(music.controller());
+				Ok(Action::Play(Box::new(music)))
+			},
+			Command::Stop => {
+				self.playing = None;
+				Ok(Action::Stop)
+			},
+			Command::Chord(chord) => Ok(Action::Sound(self.chord_keys(&chord)?, self.volume)),
+			Command::Tone(key) => Ok(Action::Sound(vec![key], self.volume)),
+			Command::Show => Ok(Action::Say(self.to_string())),
+			Command::Help => Ok(Action::Say(HELP.to_string())),
+			Command::Quit => Ok(Action::Quit),
+		}
+	}
+
+	/// Spell a chord symbol, or a Roman numeral in the key, as keys from its root up, rooted in the octave of the base note
+	pub fn chord_keys(&self, chord: &str) -> Result<Vec<PianoKey>, io::Error> {
+		let chord = match Chord::from_str(chord) {
+			Ok(chord) => chord,
+			Err(_) => RomanNumeral::from_str(chord)
+				.map_err(|_| {
+					io::Error::new(
+						io::ErrorKind::InvalidInput,
+						format!("{chord} is neither a chord symbol nor a Roman numeral"),
+					)
+				})?
+				.chord(self.key)?,
+		};
+		let root = PianoKey {
+			note: chord.root,
+			octave: self.key.base_note.octave,
+		};
+		let mut ret = vec![root];
+		ret.extend(chord.quality.intervals().into_iter().map(|i| root + i));
+		Ok(ret)
+	}
+
+	/// Move to a new key, passing it on to the music
+	fn set_key(&mut self, key: Key) -> Action {
+		self.key = key;
+		self.control(|c| c.set_base_note(key.base_note));
+		self.control(|c| c.set_scale(key.scale));
+		self.control(|c| c.set_octaves(key.octaves));
+		Action::Say(self.to_string())
+	}
+
+	/// Pass a change on to the music, forgetting it once it has finished
+	fn control(&mut self, change: impl Fn(&Controller) -> Result<(), io::Error>) {
+		if self.playing.as_ref().is_some_and(|c| change(c).is_err()) {
+			self.playing = None;
+		}
+	}
+}
+
+impl fmt::Display for Session {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let key = self.key;
+		write!(
+			f,
+			"{} {} over {} octave(s) from {}: {key}\nTempo {}, {} wave, volume {}",
+			key.base_note.note,
+			key.scale,
+			key.octaves,
+			key.base_note,
+			self.tempo,
+			self.waveform,
+			self.volume
+		)
+	}
+}
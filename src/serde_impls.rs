@@ -0,0 +1,81 @@
+//! With the `serde` feature, types that can be written as text and read back with `FromStr` serialize as
+//! those strings, so a `PianoKey` is "E♭4" rather than a nest of letters and accidentals
+
+#[cfg(feature = "synth")]
+use crate::output::Waveform;
+use crate::theory::{
+	chord::{Chord, Progression, RomanNumeral},
+	interval::Interval,
+	key::Key,
+	melody::MelodyNote,
+	note::Note,
+	piano_key::PianoKey,
+	scale::Scale,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::str::FromStr;
+
+/// Serialize each type as its `Display` string and deserialize it with `FromStr`
+macro_rules! via_string {
+	($($t:ty),*) => {
+		$(
+			impl Serialize for $t {
+				fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+					serializer.collect_str(self)
+				}
+			}
+
+			impl<'de> Deserialize<'de> for $t {
+				fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+					let s = String::deserialize(deserializer)?;
+					Self::from_str(&s).map_err(de::Error::custom)
+				}
+			}
+		)*
+	};
+}
+
+via_string!(
+	Note,
+	PianoKey,
+	Interval,
+	MelodyNote,
+	Chord,
+	RomanNumeral,
+	Progression
+);
+
+#[cfg(feature = "synth")]
+via_string!(Waveform);
+
+/// Scales display as prose like "major scale", so they go by the name `FromStr` knows them by instead
+impl Serialize for Scale {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let name = match self {
+			Scale::Diatonic(mode) => format!("{mode:?}"),
+			_ => format!("{self:?}"),
+		};
+		serializer.serialize_str(&name)
+	}
+}
+
+impl<'de> Deserialize<'de> for Scale {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let s = String::deserialize(deserializer)?;
+		Self::from_str(&s).map_err(de::Error::custom)
+	}
+}
+
+/// A key as it is written, read through `Key::new` so its octaves are kept on the piano
+#[derive(Deserialize)]
+pub(crate) struct KeyFields {
+	base_note: PianoKey,
+	octaves: u8,
+	scale: Scale,
+}
+
+impl From<KeyFields> for Key {
+	fn from(fields: KeyFields) -> Self {
+		Key::new(fields.scale, fields.base_note, fields.octaves)
+	}
+}
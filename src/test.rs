@@ -1,8 +1,20 @@
+#[cfg(feature = "cli")]
+use super::config::Config;
+use super::error::Error;
+#[cfg(feature = "synth")]
+use super::output::{
+	rhythm::*, seed::*, ChordPlayer, Envelope, Limit, MusicMaker, NoteEvent, PianoRoll, Signal,
+	Synth, Waveform,
+};
+use super::qwerty::Qwerty;
+#[cfg(feature = "synth")]
+use super::repl::{Action, Command, Session};
 use super::theory::{
-	cent::Cents, hertz::*, interval::*, key::Key, note::*, piano_key::PianoKey, pitch::Pitch,
-	scale::*, semitone::Semitones,
+	analysis::*, cent::Cents, chorale::*, chord::*, counterpoint::*, hertz::*, interval::*,
+	key::Key, key_signature::*, melody::*, note::*, piano_key::PianoKey, pitch::Pitch,
+	pitch_class_set::*, scale::*, semitone::Semitones, tone_row::*, voice_leading::*,
 };
-use pretty_assertions::assert_eq;
+use pretty_assertions::{assert_eq, assert_ne};
 use std::str::FromStr;
 
 #[test]
@@ -74,91 +86,212 @@ fn test_new_piano_key() {
 
 #[test]
 fn test_add_interval() {
-	use Interval::*;
-	assert_eq!(Unison + Unison, Unison);
-	assert_eq!(Unison + Maj3, Maj3);
-	assert_eq!(Maj2 + Min3, Perfect4);
-	assert_eq!(Octave + Octave, Unison);
-	assert_eq!(Tritone + Tritone, Unison);
-	assert_eq!(Maj7 + Min3, Maj2);
+	use Interval as I;
+	assert_eq!(I::UNISON + I::UNISON, I::UNISON);
+	assert_eq!(I::UNISON + I::MAJ3, I::MAJ3);
+	assert_eq!(I::MAJ2 + I::MIN3, I::PERFECT4);
+	assert_eq!(I::OCTAVE + I::OCTAVE, I::from_str("P15").unwrap());
+	assert_eq!(I::TRITONE + I::TRITONE, I::from_str("A7").unwrap());
+	assert_eq!(I::MAJ7 + I::MIN3, I::from_str("M9").unwrap());
+	// Sums too altered to name fall back to the usual spelling instead of panicking
+	let aa4 = I::from_str("AA4").unwrap();
+	assert_eq!(aa4.checked_add(aa4), None);
+	assert_eq!(aa4 + aa4, I::from_str("M9").unwrap());
+	let widest = I::from_str("P74").unwrap();
+	assert_eq!(widest.checked_add(I::OCTAVE), None);
+	assert_eq!(widest + widest, I::from_str("AA74").unwrap());
+	assert_eq!(I::MAJ2.checked_add(I::MIN3), Some(I::PERFECT4));
 }
 
 #[test]
 fn test_sub_interval() {
-	use Interval::*;
-	assert_eq!(Unison - Unison, Unison);
-	assert_eq!(Unison - Maj3, Min6);
-	assert_eq!(Maj2 - Min3, Maj7);
-	assert_eq!(Octave - Octave, Unison);
-	assert_eq!(Tritone - Tritone, Unison);
-	assert_eq!(Maj7 - Min3, Min6);
+	use Interval as I;
+	assert_eq!(I::UNISON - I::UNISON, I::UNISON);
+	assert_eq!(I::UNISON - I::MAJ3, I::MIN6);
+	assert_eq!(I::MAJ2 - I::MIN3, I::MAJ7);
+	assert_eq!(I::OCTAVE - I::OCTAVE, I::UNISON);
+	assert_eq!(I::TRITONE - I::TRITONE, I::UNISON);
+	assert_eq!(I::MAJ7 - I::MIN3, I::from_str("A5").unwrap());
+	let aa4 = I::from_str("AA4").unwrap();
+	let dd4 = I::from_str("dd4").unwrap();
+	assert_eq!(aa4.checked_sub(dd4), None);
+	assert_eq!(aa4 - dd4, I::from_str("M3").unwrap());
+	assert_eq!(I::MAJ2.checked_sub(I::MIN3), Some(I::MAJ7));
+}
+
+#[test]
+fn test_interval_names() {
+	use Interval as I;
+	assert_eq!(I::MAJ3.to_string(), "M3");
+	assert_eq!(I::TRITONE.to_string(), "A4");
+	assert_eq!(I::from_str("d5").unwrap().to_string(), "d5");
+	assert_eq!(I::from_str("P11").unwrap().number(), 11);
+	assert_eq!(
+		I::from_str("AA4").unwrap().quality(),
+		Quality::DoublyAugmented
+	);
+	assert_eq!(I::from_str("dim7").unwrap(), I::from_str("d7").unwrap());
+	assert!(I::from_str("P3").is_err());
+	assert!(I::from_str("M5").is_err());
+	assert!(I::from_str("M0").is_err());
+	assert!(I::from_str("M").is_err());
+	assert!(I::from_str("P99").is_err());
+	assert!(I::new(Quality::Perfect, 75).is_err());
+	assert_ne!(I::TRITONE, I::from_str("d5").unwrap());
+}
+
+#[test]
+fn test_interval_semitones() {
+	use Interval as I;
+	assert_eq!(i8::from(I::TRITONE), 6);
+	assert_eq!(i8::from(I::from_str("d5").unwrap()), 6);
+	assert_eq!(i8::from(I::from_str("dd7").unwrap()), 8);
+	assert_eq!(i8::from(I::from_str("M9").unwrap()), 14);
+	assert_eq!(i8::from(I::from_str("P11").unwrap()), 17);
+	assert_eq!(i8::from(I::from_str("M13").unwrap()), 21);
+	assert_eq!(I::from(Semitones::from(14)), I::from_str("M9").unwrap());
+	assert_eq!(I::from(Semitones::from(12)), I::OCTAVE);
+	// The widest intervals still fit in an i8 of semitones
+	assert_eq!(i8::from(I::from_str("P74").unwrap()), 125);
+	assert_eq!(i8::from(I::from_str("AA74").unwrap()), 127);
+	assert_eq!(I::from_number_and_semitones(99, 0), None);
+	assert_eq!(
+		I::from(Semitones::from(i8::MAX)),
+		I::from_str("AA74").unwrap()
+	);
+	assert_eq!(
+		I::from(Semitones::from(i8::MIN)),
+		I::from_str("AA74").unwrap()
+	);
+}
+
+#[test]
+fn test_compound_intervals() {
+	use Interval as I;
+	let ninth = I::from_str("m9").unwrap();
+	assert!(ninth.is_compound());
+	assert!(!I::OCTAVE.is_compound());
+	assert_eq!(ninth.simple(), I::MIN2);
+	assert_eq!(ninth.octaves(), 1);
+	assert_eq!(I::from_str("P15").unwrap().simple(), I::OCTAVE);
+	assert_eq!(I::OCTAVE + I::MAJ6, I::from_str("M13").unwrap());
+}
+
+#[test]
+fn test_invert_interval() {
+	use Interval as I;
+	assert_eq!(I::MAJ3.invert(), I::MIN6);
+	assert_eq!(I::TRITONE.invert(), I::from_str("d5").unwrap());
+	assert_eq!(I::PERFECT4.invert(), I::PERFECT5);
+	assert_eq!(I::UNISON.invert(), I::OCTAVE);
+	assert_eq!(
+		I::from_str("AA2").unwrap().invert(),
+		I::from_str("dd7").unwrap()
+	);
+	assert_eq!(I::from_str("M10").unwrap().invert(), I::MIN6);
 }
 
 #[test]
 fn test_note_letter_to_interval() {
-	use Interval::*;
+	use Interval as I;
 	use Letter::*;
-	assert_eq!(C.interval_from_c(), Unison);
-	assert_eq!(D.interval_from_c(), Maj2);
-	assert_eq!(E.interval_from_c(), Maj3);
-	assert_eq!(F.interval_from_c(), Perfect4);
-	assert_eq!(G.interval_from_c(), Perfect5);
-	assert_eq!(A.interval_from_c(), Maj6);
-	assert_eq!(B.interval_from_c(), Maj7);
+	assert_eq!(C.interval_from_c(), I::UNISON);
+	assert_eq!(D.interval_from_c(), I::MAJ2);
+	assert_eq!(E.interval_from_c(), I::MAJ3);
+	assert_eq!(F.interval_from_c(), I::PERFECT4);
+	assert_eq!(G.interval_from_c(), I::PERFECT5);
+	assert_eq!(A.interval_from_c(), I::MAJ6);
+	assert_eq!(B.interval_from_c(), I::MAJ7);
 }
 
 #[test]
 fn test_get_note_interval_from_c() {
-	use Interval::*;
-	assert_eq!(Note::from_str("A").unwrap().interval_from_c(), Maj6);
-	assert_eq!(Note::from_str("A#").unwrap().interval_from_c(), Min7);
-	assert_eq!(Note::from_str("Bb").unwrap().interval_from_c(), Min7);
-	assert_eq!(Note::from_str("B").unwrap().interval_from_c(), Maj7);
-	assert_eq!(Note::from_str("C").unwrap().interval_from_c(), Unison);
-	assert_eq!(Note::from_str("C#").unwrap().interval_from_c(), Min2);
-	assert_eq!(Note::from_str("D").unwrap().interval_from_c(), Maj2);
-	assert_eq!(Note::from_str("D#").unwrap().interval_from_c(), Min3);
-	assert_eq!(Note::from_str("E").unwrap().interval_from_c(), Maj3);
-	assert_eq!(Note::from_str("F").unwrap().interval_from_c(), Perfect4);
-	assert_eq!(Note::from_str("F#").unwrap().interval_from_c(), Tritone);
-	assert_eq!(Note::from_str("G").unwrap().interval_from_c(), Perfect5);
-	assert_eq!(Note::from_str("G#").unwrap().interval_from_c(), Min6);
+	use Interval as I;
+	assert_eq!(Note::from_str("A").unwrap().interval_from_c(), I::MAJ6);
+	assert_eq!(
+		Note::from_str("A#").unwrap().interval_from_c(),
+		I::from_str("A6").unwrap()
+	);
+	assert_eq!(Note::from_str("Bb").unwrap().interval_from_c(), I::MIN7);
+	assert_eq!(Note::from_str("B").unwrap().interval_from_c(), I::MAJ7);
+	assert_eq!(Note::from_str("C").unwrap().interval_from_c(), I::UNISON);
+	assert_eq!(
+		Note::from_str("C#").unwrap().interval_from_c(),
+		I::from_str("A1").unwrap()
+	);
+	assert_eq!(Note::from_str("D").unwrap().interval_from_c(), I::MAJ2);
+	assert_eq!(
+		Note::from_str("D#").unwrap().interval_from_c(),
+		I::from_str("A2").unwrap()
+	);
+	assert_eq!(Note::from_str("E").unwrap().interval_from_c(), I::MAJ3);
+	assert_eq!(Note::from_str("F").unwrap().interval_from_c(), I::PERFECT4);
+	assert_eq!(Note::from_str("F#").unwrap().interval_from_c(), I::TRITONE);
+	assert_eq!(Note::from_str("G").unwrap().interval_from_c(), I::PERFECT5);
+	assert_eq!(
+		Note::from_str("G#").unwrap().interval_from_c(),
+		I::from_str("A5").unwrap()
+	);
 }
 
 #[test]
 fn test_get_note_offset() {
-	use Interval::*;
+	use Interval as I;
 	let a = Note::from_str("A").unwrap();
-	assert_eq!(Note::from_str("A").unwrap().get_offset(a), Unison);
-	assert_eq!(Note::from_str("A#").unwrap().get_offset(a), Min2);
-	assert_eq!(Note::from_str("B").unwrap().get_offset(a), Maj2);
-	assert_eq!(Note::from_str("C").unwrap().get_offset(a), Min3);
-	assert_eq!(Note::from_str("C#").unwrap().get_offset(a), Maj3);
-	assert_eq!(Note::from_str("D").unwrap().get_offset(a), Perfect4);
-	assert_eq!(Note::from_str("D#").unwrap().get_offset(a), Tritone);
-	assert_eq!(Note::from_str("E").unwrap().get_offset(a), Perfect5);
-	assert_eq!(Note::from_str("F").unwrap().get_offset(a), Min6);
-	assert_eq!(Note::from_str("F#").unwrap().get_offset(a), Maj6);
-	assert_eq!(Note::from_str("G").unwrap().get_offset(a), Min7);
-	assert_eq!(Note::from_str("G#").unwrap().get_offset(a), Maj7);
+	assert_eq!(Note::from_str("A").unwrap().get_offset(a), I::UNISON);
+	assert_eq!(
+		Note::from_str("A#").unwrap().get_offset(a),
+		I::from_str("A1").unwrap()
+	);
+	assert_eq!(Note::from_str("Bb").unwrap().get_offset(a), I::MIN2);
+	assert_eq!(Note::from_str("B").unwrap().get_offset(a), I::MAJ2);
+	assert_eq!(Note::from_str("C").unwrap().get_offset(a), I::MIN3);
+	assert_eq!(Note::from_str("C#").unwrap().get_offset(a), I::MAJ3);
+	assert_eq!(Note::from_str("D").unwrap().get_offset(a), I::PERFECT4);
+	assert_eq!(Note::from_str("D#").unwrap().get_offset(a), I::TRITONE);
+	assert_eq!(Note::from_str("E").unwrap().get_offset(a), I::PERFECT5);
+	assert_eq!(Note::from_str("F").unwrap().get_offset(a), I::MIN6);
+	assert_eq!(Note::from_str("F#").unwrap().get_offset(a), I::MAJ6);
+	assert_eq!(Note::from_str("G").unwrap().get_offset(a), I::MIN7);
+	assert_eq!(Note::from_str("G#").unwrap().get_offset(a), I::MAJ7);
 }
 
 #[test]
 fn test_add_interval_to_note() {
-	use Interval::*;
+	use Interval as I;
 	let a = Note::from_str("A").unwrap();
-	assert_eq!(a + Unison, a);
-	assert_eq!(a + Min2, Note::from_str("A#").unwrap());
-	assert_eq!(a + Maj2, Note::from_str("B").unwrap());
-	assert_eq!(a + Min3, Note::from_str("C").unwrap());
-	assert_eq!(a + Maj3, Note::from_str("C#").unwrap());
-	assert_eq!(a + Perfect4, Note::from_str("D").unwrap());
-	assert_eq!(a + Tritone, Note::from_str("D#").unwrap());
-	assert_eq!(a + Perfect5, Note::from_str("E").unwrap());
-	assert_eq!(a + Min6, Note::from_str("F").unwrap());
-	assert_eq!(a + Maj6, Note::from_str("F#").unwrap());
-	assert_eq!(a + Min7, Note::from_str("G").unwrap());
-	assert_eq!(a + Maj7, Note::from_str("G#").unwrap());
+	assert_eq!(a + I::UNISON, a);
+	assert_eq!(a + I::MIN2, Note::from_str("Bb").unwrap());
+	assert_eq!(a + I::MAJ2, Note::from_str("B").unwrap());
+	assert_eq!(a + I::MIN3, Note::from_str("C").unwrap());
+	assert_eq!(a + I::MAJ3, Note::from_str("C#").unwrap());
+	assert_eq!(a + I::PERFECT4, Note::from_str("D").unwrap());
+	assert_eq!(a + I::TRITONE, Note::from_str("D#").unwrap());
+	assert_eq!(a + I::PERFECT5, Note::from_str("E").unwrap());
+	assert_eq!(a + I::MIN6, Note::from_str("F").unwrap());
+	assert_eq!(a + I::MAJ6, Note::from_str("F#").unwrap());
+	assert_eq!(a + I::MIN7, Note::from_str("G").unwrap());
+	assert_eq!(a + I::MAJ7, Note::from_str("G#").unwrap());
+}
+
+#[test]
+fn test_add_interval_spells_note() {
+	use Interval as I;
+	let c = Note::from_str("C").unwrap();
+	assert_eq!(c + I::TRITONE, Note::from_str("F#").unwrap());
+	assert_eq!(
+		c + I::from_str("d5").unwrap(),
+		Note::from_str("Gb").unwrap()
+	);
+	assert_eq!(
+		Note::from_str("G#").unwrap() + I::MAJ7,
+		Note::from_str("F##").unwrap()
+	);
+	assert_eq!(
+		Note::from_str("Eb").unwrap() + I::MIN2,
+		Note::from_str("Fb").unwrap()
+	);
+	assert_eq!(c + I::from_str("M9").unwrap(), Note::from_str("D").unwrap());
 }
 
 #[test]
@@ -195,6 +328,1452 @@ fn test_a_minor() {
 	);
 }
 
+#[test]
+fn test_chromatic_spelling() {
+	let chromatic =
+		|base: &str| Key::new(Scale::Chromatic, PianoKey::from_str(base).unwrap(), 1).to_string();
+	assert_eq!(chromatic("C4"), "[ C C# D D# E F F# G G# A A# B C ]");
+	assert_eq!(chromatic("D4"), "[ D D# E F F# G G# A A# B C C# D ]");
+	assert_eq!(chromatic("F#4"), "[ F# G G# A A# B C C# D D# E F F# ]");
+	assert_eq!(chromatic("Eb4"), "[ E♭ E F F# G G# A A# B C C# D E♭ ]");
+	let key = Key::new(Scale::Chromatic, PianoKey::from_str("F#4").unwrap(), 1);
+	let semitones = key
+		.all_keys()
+		.iter()
+		.map(|k| k.semitones_from_c0())
+		.collect::<Vec<_>>();
+	let f_sharp = PianoKey::from_str("F#4").unwrap().semitones_from_c0();
+	assert_eq!(semitones, (f_sharp..=f_sharp + 12).collect::<Vec<_>>());
+	assert_eq!(
+		Scale::Chromatic.intervals_from(Note::from_str("C").unwrap()),
+		Scale::Chromatic.get_intervals()
+	);
+}
+
+#[test]
+fn test_piano_key_add_interval() {
+	let c4 = PianoKey::from_str("C4").unwrap();
+	assert_eq!(c4 + Interval::MAJ3, PianoKey::from_str("E4").unwrap());
+	assert_eq!(c4 + Interval::OCTAVE, PianoKey::from_str("C5").unwrap());
+	let widest = Interval::from_str("P74").unwrap();
+	assert_eq!(
+		(c4 + widest).semitones_from_c0(),
+		c4.semitones_from_c0() + 125
+	);
+	assert_eq!(
+		PianoKey::from_str("A4").unwrap() + Interval::MIN3,
+		PianoKey::from_str("C5").unwrap()
+	);
+	assert_eq!(c4 - Interval::MIN3, PianoKey::from_str("A3").unwrap());
+	assert_eq!(
+		c4 - Interval::from_str("M9").unwrap(),
+		PianoKey::from_str("Bb2").unwrap()
+	);
+}
+
+#[test]
+fn test_piano_key_interval_to() {
+	let interval = |from: &str, to: &str| {
+		let [from, to] = [from, to].map(|k| PianoKey::from_str(k).unwrap());
+		from.interval_to(to).map(|i| i.to_string())
+	};
+	assert_eq!(interval("C4", "E4").as_deref(), Some("M3"));
+	assert_eq!(interval("C5", "E4").as_deref(), Some("m6"));
+	assert_eq!(interval("C4", "D5").as_deref(), Some("M9"));
+	assert_eq!(interval("B#3", "C4").as_deref(), Some("d2"));
+	assert_eq!(interval("C4", "C4").as_deref(), Some("P1"));
+	assert_eq!(interval("Cbb4", "D##4"), None);
+}
+
+#[test]
+fn test_key_signatures() {
+	let note = |s| Note::from_str(s).unwrap();
+	assert_eq!(KeySignature::major(note("C")).unwrap().fifths(), 0);
+	assert_eq!(KeySignature::major(note("D")).unwrap().fifths(), 2);
+	assert_eq!(KeySignature::major(note("Eb")).unwrap().fifths(), -3);
+	assert_eq!(KeySignature::major(note("C#")).unwrap().fifths(), 7);
+	assert_eq!(KeySignature::major(note("Cb")).unwrap().fifths(), -7);
+	assert_eq!(KeySignature::minor(note("F#")).unwrap().fifths(), 3);
+	assert_eq!(KeySignature::minor(note("C")).unwrap().fifths(), -3);
+	assert!(KeySignature::major(note("G#")).is_err());
+	assert_eq!(KeySignature::all().len(), 15);
+	assert_eq!(
+		KeySignature::major(note("A")).unwrap().to_string(),
+		"F# C# G#"
+	);
+	assert_eq!(KeySignature::minor(note("G")).unwrap().to_string(), "B♭ E♭");
+	assert_eq!(KeySignature::default().to_string(), "no sharps or flats");
+}
+
+#[test]
+fn test_key_signature_tonics() {
+	let sig = KeySignature::new(-2).unwrap();
+	assert_eq!(sig.tonic(Mode::Ionian), Note::from_str("Bb").unwrap());
+	assert_eq!(sig.tonic(Mode::Aeolian), Note::from_str("G").unwrap());
+	assert_eq!(sig.tonic(Mode::Dorian), Note::from_str("C").unwrap());
+	assert_eq!(
+		KeySignature::new(7).unwrap().tonic(Mode::Aeolian),
+		Note::from_str("A#").unwrap()
+	);
+	assert!(KeySignature::new(8).is_err());
+}
+
+#[test]
+fn test_key_signature_from_key() {
+	let key = Key::new(
+		Scale::Diatonic(Mode::Dorian),
+		PianoKey::from_str("E4").unwrap(),
+		1,
+	);
+	assert_eq!(KeySignature::try_from(key).unwrap().fifths(), 2);
+	let key = Key::new(Scale::Chromatic, PianoKey::from_str("E4").unwrap(), 1);
+	assert!(KeySignature::try_from(key).is_err());
+}
+
+#[test]
+fn test_relative_and_parallel_keys() {
+	use Mode::*;
+	use Scale::*;
+	let c_major = Key::new(Diatonic(Ionian), PianoKey::from_str("C4").unwrap(), 1);
+	let a_minor = Key::new(Diatonic(Aeolian), PianoKey::from_str("A3").unwrap(), 1);
+	let c_minor = Key::new(Diatonic(Aeolian), PianoKey::from_str("C4").unwrap(), 1);
+	assert_eq!(c_major.relative(), Some(a_minor));
+	assert_eq!(a_minor.relative(), Some(c_major));
+	assert_eq!(c_major.parallel(), Some(c_minor));
+	assert_eq!(c_minor.parallel(), Some(c_major));
+	assert_eq!(
+		Key::new(Diatonic(Dorian), PianoKey::from_str("D4").unwrap(), 1).relative(),
+		None
+	);
+}
+
+#[test]
+fn test_key_signature_markings() {
+	let sig = KeySignature::major(Note::from_str("D").unwrap()).unwrap();
+	let note = |s| Note::from_str(s).unwrap();
+	assert_eq!(sig.marking(note("F#")), None);
+	assert_eq!(sig.marking(note("F")), Some(Marking::Natural));
+	assert_eq!(
+		sig.marking(note("Bb")),
+		Some(Marking::Accidental(Accidental::Flat))
+	);
+	assert_eq!(sig.marking(note("B")), None);
+}
+
+#[test]
+fn test_accidental_context() {
+	let key = |s| PianoKey::from_str(s).unwrap();
+	let mut bar =
+		AccidentalContext::new(KeySignature::major(Note::from_str("G").unwrap()).unwrap());
+	assert_eq!(bar.marking(key("F4")), Some(Marking::Natural));
+	// The natural holds for the rest of the bar
+	assert_eq!(bar.marking(key("F4")), None);
+	// But only on the same line
+	assert_eq!(bar.marking(key("F5")), Some(Marking::Natural));
+	assert_eq!(
+		bar.marking(key("F#4")),
+		Some(Marking::Accidental(Accidental::Sharp))
+	);
+	assert_eq!(
+		bar.marking(key("C#4")),
+		Some(Marking::Accidental(Accidental::Sharp))
+	);
+	bar.bar_line();
+	assert_eq!(bar.marking(key("F#4")), None);
+	assert_eq!(
+		bar.marking(key("C#4")),
+		Some(Marking::Accidental(Accidental::Sharp))
+	);
+}
+
+#[test]
+fn test_key_signature_spelling() {
+	let note = |s| Note::from_str(s).unwrap();
+	let e_flat = KeySignature::major(note("Eb")).unwrap();
+	assert_eq!(e_flat.spell(note("D#")), note("Eb"));
+	assert_eq!(e_flat.spell(note("C#")), note("Db"));
+	let d_major = KeySignature::major(note("D")).unwrap();
+	assert_eq!(d_major.spell(note("Gb")), note("F#"));
+	assert_eq!(d_major.spell(note("Eb")), note("D#"));
+	assert_eq!(d_major.spell(note("E#")), note("F"));
+}
+
+#[test]
+fn test_rank_keys() {
+	let melody = ["G4", "A4", "B4", "C5", "D5", "E5", "F#5", "G5"]
+		.iter()
+		.map(|s| PianoKey::from_str(s).unwrap())
+		.collect::<Vec<_>>();
+	let ranked = rank_keys(melody);
+	let best = &ranked[0];
+	assert_eq!(best.key.base_note.note, Note::from_str("G").unwrap());
+	assert_eq!(best.key.scale, Scale::Diatonic(Mode::Ionian));
+	assert!(best.outside.is_empty());
+	assert!(best.unused.is_empty());
+	// E minor holds the same notes, so it should be close behind
+	assert!(ranked[..7]
+		.iter()
+		.any(|c| c.key.base_note.note == Note::from_str("E").unwrap()
+			&& c.key.scale == Scale::Diatonic(Mode::Aeolian)));
+}
+
+#[test]
+fn test_rank_keys_reports_outside_notes() {
+	let melody = ["C", "E", "G", "Bb", "B"]
+		.iter()
+		.map(|s| Note::from_str(s).unwrap());
+	let ranked = rank_keys(melody);
+	let c_major = ranked
+		.iter()
+		.find(|c| {
+			c.key.base_note.note == Note::from_str("C").unwrap()
+				&& c.key.scale == Scale::Diatonic(Mode::Ionian)
+		})
+		.unwrap();
+	assert_eq!(c_major.outside, vec![Note::from_str("Bb").unwrap()]);
+	assert!(ranked
+		.windows(2)
+		.all(|w| w[0].outside.len() <= w[1].outside.len()));
+}
+
+#[test]
+fn test_rank_keys_spells_tonics() {
+	let melody = ["Eb", "F", "G", "Ab", "Bb", "C", "D", "Eb"]
+		.iter()
+		.map(|s| Note::from_str(s).unwrap());
+	let best = &rank_keys(melody)[0];
+	assert_eq!(best.key.to_string(), "[ E♭ F G A♭ B♭ C D E♭ ]");
+}
+
+#[test]
+fn test_estimate_key() {
+	let note = |s| Note::from_str(s).unwrap();
+	let c_major = [
+		(note("C"), 4.0),
+		(note("D"), 1.0),
+		(note("E"), 2.0),
+		(note("F"), 1.0),
+		(note("G"), 3.0),
+		(note("A"), 1.0),
+		(note("B"), 1.0),
+	];
+	let estimates = estimate_key(c_major);
+	assert_eq!(estimates.len(), 24);
+	assert_eq!(estimates[0].key.base_note.note, note("C"));
+	assert_eq!(estimates[0].key.scale, Scale::Diatonic(Mode::Ionian));
+
+	let a_minor = [
+		(note("A"), 4.0),
+		(note("B"), 1.0),
+		(note("C"), 2.0),
+		(note("D"), 1.0),
+		(note("E"), 3.0),
+		(note("F"), 1.0),
+		(note("G#"), 1.0),
+	];
+	let estimates = estimate_key(a_minor);
+	assert_eq!(estimates[0].key.base_note.note, note("A"));
+	assert_eq!(estimates[0].key.scale, Scale::Diatonic(Mode::Aeolian));
+	assert!(estimates[0].correlation > estimates[1].correlation);
+}
+
+#[test]
+fn test_pitch_class_set_from_notes() {
+	let set = ["C", "E", "G", "B#", "Fb"]
+		.iter()
+		.map(|s| Note::from_str(s).unwrap())
+		.collect::<PitchClassSet>();
+	assert_eq!(set.pitch_classes(), vec![0, 4, 7]);
+	assert_eq!(set.to_string(), "[047]");
+	assert_eq!(PitchClassSet::from_str("[0, 4, 7]").unwrap(), set);
+	assert_eq!(
+		PitchClassSet::from_str("TE").unwrap().pitch_classes(),
+		vec![10, 11]
+	);
+	assert!(PitchClassSet::from_str("01x").is_err());
+	let c_major = Key::new(Scale::default(), PianoKey::default(), 1);
+	assert_eq!(PitchClassSet::from(c_major).len(), 7);
+}
+
+#[test]
+fn test_pitch_class_set_operations() {
+	let set = PitchClassSet::new(&[0, 4, 7]);
+	assert_eq!(set.transpose(Interval::MAJ2).pitch_classes(), vec![2, 6, 9]);
+	assert_eq!(
+		set.transpose(Semitones::from(-1)).pitch_classes(),
+		vec![3, 6, 11]
+	);
+	assert_eq!(set.invert().pitch_classes(), vec![0, 5, 8]);
+	assert_eq!(set.complement().len(), 9);
+}
+
+#[test]
+fn test_normal_and_prime_forms() {
+	assert_eq!(PitchClassSet::new(&[7, 0, 4]).normal_form(), vec![0, 4, 7]);
+	assert_eq!(PitchClassSet::new(&[0, 4, 7]).prime_form(), vec![0, 3, 7]);
+	assert_eq!(
+		PitchClassSet::new(&[10, 11, 2, 3]).normal_form(),
+		vec![10, 11, 2, 3]
+	);
+	assert_eq!(
+		PitchClassSet::new(&[1, 5, 6, 8, 0]).prime_form(),
+		vec![0, 1, 5, 6, 8]
+	);
+	assert_eq!(PitchClassSet::new(&[0, 4, 8]).normal_form(), vec![0, 4, 8]);
+	assert!(PitchClassSet::default().prime_form().is_empty());
+}
+
+#[test]
+fn test_interval_class_vector() {
+	assert_eq!(
+		PitchClassSet::new(&[0, 4, 7]).interval_class_vector(),
+		[0, 0, 1, 1, 1, 0]
+	);
+	let c_major = Key::new(Scale::default(), PianoKey::default(), 1);
+	assert_eq!(
+		PitchClassSet::from(c_major).interval_class_vector(),
+		[2, 5, 4, 3, 6, 1]
+	);
+}
+
+#[test]
+fn test_forte_numbers() {
+	let forte = |pcs: &[u8]| PitchClassSet::new(pcs).forte_number().unwrap().to_string();
+	assert_eq!(forte(&[0, 4, 7]), "3-11");
+	assert_eq!(forte(&[0, 1, 4, 6]), "4-Z15");
+	assert_eq!(forte(&[0, 2, 4, 6, 8, 10]), "6-35");
+	assert_eq!(forte(&[0, 2, 4, 5, 7, 9, 11]), "7-35");
+	assert_eq!(forte(&[0, 1, 3, 4, 6, 7, 9, 10]), "8-28");
+	assert_eq!(forte(&[0, 6]), "2-6");
+	assert_eq!(PitchClassSet::new(&[0]).forte_number(), None);
+}
+
+#[test]
+fn test_forte_catalogue_is_complete() {
+	use std::collections::HashMap;
+	// Every set class gets exactly one name, and no two classes share one
+	let mut names = HashMap::new();
+	for bits in 0..4096u16 {
+		let pcs = (0..12u8)
+			.filter(|pc| bits & (1 << pc) != 0)
+			.collect::<Vec<_>>();
+		let set = PitchClassSet::new(&pcs);
+		if (2..=10).contains(&set.len()) {
+			let name = set.forte_number().unwrap();
+			assert_eq!(usize::from(name.cardinality), set.len());
+			let prime = names.entry(name).or_insert_with(|| set.prime_form());
+			assert_eq!(*prime, set.prime_form(), "{name} names two set classes");
+		}
+	}
+	// Six dyads and their complements, plus Forte's 208 classes from trichords to nonachords
+	assert_eq!(names.len(), 6 + 6 + 208);
+	// Z-related classes are exactly the ones sharing an interval-class vector with another class
+	for (name, prime) in &names {
+		let set = PitchClassSet::new(prime);
+		let twin = names
+			.values()
+			.any(|other| set.is_z_related(PitchClassSet::new(other)));
+		assert_eq!(name.z, twin, "{name}");
+	}
+}
+
+#[test]
+fn test_z_relation() {
+	let all_interval_a = PitchClassSet::new(&[0, 1, 4, 6]);
+	let all_interval_b = PitchClassSet::new(&[0, 1, 3, 7]);
+	assert!(all_interval_a.is_z_related(all_interval_b));
+	assert!(!all_interval_a.is_z_related(all_interval_a.transpose(Interval::MAJ3)));
+	assert!(!all_interval_a.is_z_related(PitchClassSet::new(&[0, 4, 7])));
+	let hexachord = PitchClassSet::new(&[0, 1, 2, 3, 5, 6]);
+	assert!(hexachord.is_z_related(PitchClassSet::new(&[0, 1, 2, 3, 4, 7])));
+}
+
+#[test]
+fn test_all_keys() {
+	let key = |s| PianoKey::from_str(s).unwrap();
+	// Each octave used to be listed as the scale's notes all under one octave
+	// number, so the listing ran A3 B3 C#3 .. G#3 A3 A4 B4 C#4 .., repeating the
+	// base note and never reaching the top one. Now it climbs from A3 to A5.
+	let a_major = Key::new(Scale::default(), key("A3"), 2).all_keys();
+	assert_eq!(a_major.len(), 15);
+	assert_eq!(a_major[0], key("A3"));
+	assert_eq!(a_major[2], key("C#4"));
+	assert_eq!(a_major[7], key("A4"));
+	assert_eq!(a_major[14], key("A5"));
+	assert!(a_major
+		.windows(2)
+		.all(|w| w[0].semitones_from_c0() < w[1].semitones_from_c0()));
+	// A key at the top of the keyboard still has its base note
+	assert_eq!(
+		Key::new(Scale::default(), key("C8"), 1).all_keys(),
+		vec![key("C8")]
+	);
+}
+
+#[test]
+fn test_tone_row_forms() {
+	// Berg's Violin Concerto row
+	let row = ToneRow::from_str("7 10 2 6 9 0 4 8 11 1 3 5").unwrap();
+	assert_eq!(row.form(RowForm::Prime(0)), row.pitch_classes());
+	assert_eq!(
+		row.form(RowForm::Prime(2)),
+		[9, 0, 4, 8, 11, 2, 6, 10, 1, 3, 5, 7]
+	);
+	assert_eq!(
+		row.form(RowForm::Retrograde(0)),
+		[5, 3, 1, 11, 8, 4, 0, 9, 6, 2, 10, 7]
+	);
+	assert_eq!(
+		row.form(RowForm::Inversion(0)),
+		[7, 4, 0, 8, 5, 2, 10, 6, 3, 1, 11, 9]
+	);
+	assert_eq!(
+		row.form(RowForm::RetrogradeInversion(0)),
+		[9, 11, 1, 3, 6, 10, 2, 5, 8, 0, 4, 7]
+	);
+	assert_eq!(row.all_forms().len(), 48);
+	// Transpositions past the octave wrap around instead of overflowing
+	assert_eq!(row.form(RowForm::Prime(254)), row.form(RowForm::Prime(2)));
+	assert_eq!(
+		row.form(RowForm::RetrogradeInversion(240)),
+		row.form(RowForm::RetrogradeInversion(0))
+	);
+	assert_eq!(
+		row.form(RowForm::Inversion(255)),
+		row.form(RowForm::Inversion(3))
+	);
+	assert_eq!(
+		RowForm::from_str("ri11").unwrap(),
+		RowForm::RetrogradeInversion(11)
+	);
+	assert_eq!(RowForm::Inversion(3).to_string(), "I3");
+	assert!(RowForm::from_str("P12").is_err());
+	assert!(RowForm::from_str("Q1").is_err());
+}
+
+#[test]
+fn test_tone_row_parsing() {
+	let numbers = ToneRow::from_str("0 11 7 8 3 1 2 10 6 5 4 9").unwrap();
+	let notes = ToneRow::from_str("C B G Ab Eb Db D Bb Gb F E A").unwrap();
+	assert_eq!(numbers, notes);
+	assert_eq!(numbers.to_string(), "0 11 7 8 3 1 2 10 6 5 4 9");
+	assert!(ToneRow::from_str("0 1 2").is_err());
+	assert!(ToneRow::new(&[0, 0, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]).is_err());
+}
+
+#[test]
+fn test_all_interval_rows() {
+	use rand::{rngs::SmallRng, SeedableRng};
+	let mut rng = SmallRng::seed_from_u64(12);
+	for _ in 0..10 {
+		assert!(ToneRow::all_interval(&mut rng).is_all_interval());
+	}
+	assert!(!ToneRow::from_str("0 1 2 3 4 5 6 7 8 9 10 11")
+		.unwrap()
+		.is_all_interval());
+}
+
+#[test]
+fn test_tone_row_matrix() {
+	let row = ToneRow::from_str("0 11 7 8 3 1 2 10 6 5 4 9").unwrap();
+	let rows = row.matrix().rows();
+	assert_eq!(rows.len(), 12);
+	assert_eq!(rows[0], (RowForm::Prime(0), row.pitch_classes()));
+	assert_eq!(rows[1].0, RowForm::Prime(1));
+	// The left column reads down as the first inversion
+	let column = rows.iter().map(|(_, pcs)| pcs[0]).collect::<Vec<_>>();
+	assert_eq!(column, row.form(RowForm::Inversion(0)).to_vec());
+	let printed = row.matrix().to_string();
+	assert_eq!(printed.lines().count(), 13);
+	assert!(printed.lines().nth(1).unwrap().starts_with("P0"));
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_serial_seed() {
+	let row = ToneRow::from_str("0 11 7 8 3 1 2 10 6 5 4 9").unwrap();
+	let key = Key::new(Scale::default(), PianoKey::from_str("C4").unwrap(), 2);
+	let mut seed = Serial::new(row).with_forms(vec![RowForm::Prime(0), RowForm::Retrograde(0)]);
+	let played = (0..24).map(|_| seed.get_note(key)).collect::<Vec<_>>();
+	let pcs = played
+		.iter()
+		.map(|k| k.note.pitch_class())
+		.collect::<Vec<_>>();
+	assert_eq!(pcs[..12], row.form(RowForm::Prime(0)));
+	assert_eq!(pcs[12..], row.form(RowForm::Retrograde(0)));
+	let low = key.base_note.semitones_from_c0();
+	assert!(played
+		.iter()
+		.all(|k| (low..=low + 24).contains(&k.semitones_from_c0())));
+	// The first note starts at the bottom of the range, then each note moves as little as possible
+	assert_eq!(played[0], PianoKey::from_str("C4").unwrap());
+	assert_eq!(played[1], PianoKey::from_str("B4").unwrap());
+	assert_eq!(played[2], PianoKey::from_str("G4").unwrap());
+}
+
+#[test]
+fn test_midi_note_numbers() {
+	assert_eq!(PianoKey::from_str("C4").unwrap().midi(), Some(60));
+	assert_eq!(PianoKey::from_str("A0").unwrap().midi(), Some(21));
+	assert_eq!(PianoKey::from_str("B#3").unwrap().midi(), Some(60));
+	assert_eq!(PianoKey::from_midi(61), PianoKey::from_str("C#4").ok());
+	assert_eq!(PianoKey::from_midi(12), PianoKey::from_str("C0").ok());
+	assert_eq!(PianoKey::from_midi(11), None);
+	assert_eq!(PianoKey::from_midi(127), None);
+}
+
+#[test]
+fn test_parse_melody() {
+	let melody = parse_melody("C4:2, D4:2 E4").unwrap();
+	assert_eq!(
+		melody,
+		vec![
+			MelodyNote::new(PianoKey::from_str("C4").unwrap(), 2),
+			MelodyNote::new(PianoKey::from_str("D4").unwrap(), 2),
+			MelodyNote::new(PianoKey::from_str("E4").unwrap(), STEPS_PER_BEAT),
+		]
+	);
+	assert_eq!(melody[0].to_string(), "C4:2");
+	let melodies = parse_melodies("# A comment\nC4 D4\n\nE4:8\n").unwrap();
+	assert_eq!(melodies.len(), 2);
+	assert!(parse_melody("C4:0").is_err());
+	assert!(parse_melody("C4:x").is_err());
+}
+
+#[test]
+fn test_read_midi_melodies() {
+	use midly::{
+		Format, Header, MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind,
+	};
+	let note = |delta: u32, key: u8, vel: u8| TrackEvent {
+		delta: delta.into(),
+		kind: TrackEventKind::Midi {
+			channel: 0.into(),
+			message: MidiMessage::NoteOn {
+				key: key.into(),
+				vel: vel.into(),
+			},
+		},
+	};
+	let track = vec![
+		// A chord keeps only its highest note
+		note(0, 60, 64),
+		note(0, 64, 64),
+		note(96, 60, 0),
+		note(0, 64, 0),
+		note(0, 67, 64),
+		note(48, 67, 0),
+		TrackEvent {
+			delta: 0.into(),
+			kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+		},
+	];
+	let smf = Smf {
+		header: Header::new(Format::SingleTrack, Timing::Metrical(96.into())),
+		tracks: vec![track],
+	};
+	let mut bytes = Vec::new();
+	smf.write_std(&mut bytes).unwrap();
+	assert_eq!(
+		crate::midi::parse_melodies(&bytes).unwrap(),
+		vec![parse_melody("E4:4 G4:2").unwrap()]
+	);
+	assert!(crate::midi::parse_melodies(b"not a midi file").is_err());
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_scale_degrees() {
+	let c_major = Key::new(Scale::default(), PianoKey::from_str("C4").unwrap(), 2);
+	let g_major = Key::new(Scale::default(), PianoKey::from_str("G4").unwrap(), 1);
+	for (key, step, alteration) in [
+		("E4", 2, 0),
+		("C5", 7, 0),
+		("B3", -1, 0),
+		("Eb4", 2, -1),
+		("F#4", 3, 1),
+	] {
+		let degree = Degree::of(PianoKey::from_str(key).unwrap(), c_major);
+		assert_eq!(degree, Degree { step, alteration });
+		assert_eq!(degree.place(c_major), PianoKey::from_str(key).unwrap());
+	}
+	let place = |s: &str| Degree::from_str(s).unwrap().place(g_major).to_string();
+	assert_eq!(place("2"), "B4");
+	assert_eq!(place("3#"), "C#5");
+	assert_eq!(place("-1"), "F#4");
+	assert_eq!(place("2b"), "B♭4");
+	assert_eq!(Degree::from_str("-3bb").unwrap().to_string(), "-3bb");
+	assert!(Degree::from_str("x").is_err());
+	assert!(Degree::from_str("3###").is_err());
+	assert!(Degree::from_str(&format!("1{}", "b".repeat(200))).is_err());
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_markov_model() {
+	let c_major = Key::new(Scale::default(), PianoKey::from_str("C4").unwrap(), 1);
+	let mut model = MarkovModel::new(1);
+	model.train_in_key(&parse_melody("C4 D4:2 E4 C4 D4:2 E4").unwrap(), c_major);
+	let text = model.to_string();
+	assert!(text.starts_with("markov 1\n"));
+	assert!(text.contains("\n0/4 -> 1/2:2\n"));
+	assert_eq!(MarkovModel::from_str(&text).unwrap(), model);
+	assert!(MarkovModel::from_str("markov 1\n0/4 1/4 -> 2/4:1").is_err());
+	// The old name for a degree and length still works
+	let note = MarkovNote {
+		degree: Degree::from_str("1").unwrap(),
+		steps: 2,
+	};
+	assert_eq!(note.to_string(), "1/2");
+	assert!(MarkovModel::from_str("0/4 -> 2/4:1").is_err());
+
+	// Every note has a single continuation, so the melody cycles through the training notes in the new key
+	let d_major = Key::new(Scale::default(), PianoKey::from_str("D4").unwrap(), 1);
+	let mut seed = Markov::new(model);
+	let played = (0..7).map(|_| seed.next_note(d_major)).collect::<Vec<_>>();
+	for pair in played.windows(2) {
+		let next = match pair[0].key.to_string().as_str() {
+			"D4" => "E4:2",
+			"E4" => "F#4:4",
+			"F#4" => "D4:4",
+			other => panic!("{other} was never trained"),
+		};
+		assert_eq!(pair[1].to_string(), next);
+	}
+	let mut untrained = Markov::new(MarkovModel::new(2));
+	assert_eq!(untrained.get_note(d_major), d_major.base_note);
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_walk_seed() {
+	let key = Key::new(Scale::default(), PianoKey::from_str("C3").unwrap(), 3);
+	let keys = key.all_keys();
+	let index = |k: PianoKey| keys.iter().position(|x| *x == k).unwrap();
+	let mut walk = Walk::default().with_max_leap(4).with_leap_recovery(Some(2));
+	let played = (0..500)
+		.map(|_| index(walk.get_note(key)))
+		.collect::<Vec<_>>();
+	assert_eq!(played[0], 0);
+	for window in played.windows(3) {
+		let leap = window[1].abs_diff(window[0]);
+		assert!(leap <= 4);
+		// A leap of a fourth or more is followed by a step back
+		if leap > 2 {
+			assert_eq!(window[2].abs_diff(window[1]), 1);
+			assert_eq!(window[2] < window[1], window[1] > window[0]);
+		}
+	}
+
+	// Only steps, pulled hard to the middle of the range
+	let mut walk = Walk::default()
+		.with_steps(vec![0.0, 1.0])
+		.with_gravity(10.0, Center::Middle);
+	let played = (0..200)
+		.map(|_| index(walk.get_note(key)))
+		.collect::<Vec<_>>();
+	assert!(played.iter().all(|i| i.abs_diff(keys.len() / 2) <= 1));
+	assert!(played.windows(2).all(|w| w[0].abs_diff(w[1]) == 1));
+	assert_eq!(Center::from_str("middle").unwrap(), Center::Middle);
+	assert!(Center::from_str("edge").is_err());
+}
+
+#[cfg(feature = "synth")]
+#[test]
+#[allow(clippy::float_cmp)]
+fn test_degree_weights() {
+	let key = Key::new(Scale::default(), PianoKey::from_str("C4").unwrap(), 1);
+	let weights = DegreeWeights::from_str("4, 1 2,1,3").unwrap();
+	assert_eq!(weights.to_string(), "4,1,2,1,3");
+	let weight = |k: &str| weights.weight(PianoKey::from_str(k).unwrap(), key);
+	assert_eq!(weight("C4"), 4.0);
+	assert_eq!(weight("C5"), 4.0);
+	assert_eq!(weight("G4"), 3.0);
+	// Unlisted degrees weigh 1, notes outside the scale weigh nothing
+	assert_eq!(weight("B4"), 1.0);
+	assert_eq!(weight("F#4"), 0.0);
+	assert_eq!(
+		DegreeWeights::from_str("tonal").unwrap(),
+		DegreeWeights::preset("Tonal").unwrap()
+	);
+	assert!(DegreeWeights::presets()
+		.iter()
+		.all(|name| DegreeWeights::preset(name).is_some()));
+	assert!(DegreeWeights::from_str("1,-2").is_err());
+	assert!(DegreeWeights::from_str("loud").is_err());
+
+	let mut seed = Random::default().with_weights(DegreeWeights::preset("pentatonic").unwrap());
+	assert!((0..200)
+		.map(|_| seed.get_note(key).note)
+		.all(|n| n != Note::from_str("F").unwrap() && n != Note::from_str("B").unwrap()));
+
+	// The leading tone always rises to the tonic
+	let weights = DegreeWeights::from_str("1,0,0,0,0,0,1,resolve").unwrap();
+	let mut seed = Random::default().with_weights(weights);
+	let played = (0..200).map(|_| seed.get_note(key)).collect::<Vec<_>>();
+	assert!(played
+		.iter()
+		.all(|k| matches!(k.to_string().as_str(), "C4" | "B4" | "C5")));
+	for pair in played.windows(2) {
+		if pair[0].to_string() == "B4" {
+			assert_eq!(pair[1].to_string(), "C5");
+		}
+	}
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_euclidean_rhythms() {
+	let pattern = |e: &Euclidean| {
+		e.pattern()
+			.iter()
+			.map(|&onset| if onset { 'x' } else { '.' })
+			.collect::<String>()
+	};
+	assert_eq!(pattern(&Euclidean::new(3, 8).unwrap()), "x..x..x.");
+	assert_eq!(pattern(&Euclidean::new(5, 8).unwrap()), "x.xx.xx.");
+	assert_eq!(pattern(&Euclidean::new(4, 16).unwrap()), "x...x...x...x...");
+	assert_eq!(
+		pattern(&Euclidean::new(3, 8).unwrap().with_rotation(1)),
+		"..x..x.x"
+	);
+	assert!(Euclidean::new(5, 4).is_err());
+	assert!(Euclidean::new(0, 0).is_err());
+
+	let mut tresillo = Euclidean::new(3, 8).unwrap();
+	let pulses = (0..6).map(|_| tresillo.next_pulse()).collect::<Vec<_>>();
+	let cycle = [Pulse::Note(3), Pulse::Note(3), Pulse::Note(2)];
+	assert_eq!(pulses[..3], cycle);
+	assert_eq!(pulses[3..], cycle);
+	let mut rotated = Euclidean::new(3, 8).unwrap().with_rotation(1);
+	assert_eq!(
+		(0..4)
+			.map(|_| rotated.next_pulse().to_string())
+			.collect::<Vec<_>>(),
+		vec![".2", "x3", "x2", "x1"]
+	);
+	let mut silent = Euclidean::new(0, 4).unwrap();
+	assert_eq!(silent.next_pulse(), Pulse::Rest(4));
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_probabilistic_rhythms() {
+	let mut rhythm = Probabilistic::new(vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0]).unwrap();
+	assert_eq!(rhythm.next_pulse(), Pulse::Note(4));
+	assert_eq!(rhythm.next_pulse(), Pulse::Note(2));
+	assert_eq!(rhythm.next_pulse(), Pulse::Note(4));
+	assert!(Probabilistic::new(vec![]).is_err());
+	assert!(Probabilistic::new(vec![1.5]).is_err());
+
+	let mut rests = WithRests::new(Steady(2), 1.0).unwrap();
+	assert_eq!(rests.next_pulse(), Pulse::Rest(2));
+	let mut notes = WithRests::new(Steady(2), 0.0).unwrap();
+	assert_eq!(notes.next_pulse(), Pulse::Note(2));
+	assert!(WithRests::new(Steady(2), f64::NAN).is_err());
+	assert!(WithRests::new(Steady(2), f64::INFINITY).is_err());
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_rhythm_sources() {
+	assert_eq!(
+		RhythmSource::from_str("steady").unwrap(),
+		RhythmSource::Steady(STEPS_PER_BEAT)
+	);
+	assert_eq!(
+		RhythmSource::from_str("2").unwrap(),
+		RhythmSource::Steady(2)
+	);
+	assert_eq!(
+		RhythmSource::from_str("E(3,8,2)").unwrap(),
+		RhythmSource::Euclidean {
+			onsets: 3,
+			steps: 8,
+			rotation: 2
+		}
+	);
+	assert_eq!(
+		RhythmSource::from_str("1,0,0.5").unwrap(),
+		RhythmSource::Probabilistic(vec![1.0, 0.0, 0.5])
+	);
+	assert!(RhythmSource::from_str("E(3)").is_err());
+	assert!(RhythmSource::from_str("0").is_err());
+	assert!(RhythmSource::from_str("sometimes").is_err());
+	assert!(RhythmSource::from_str("1,nan").is_err());
+	assert!(RhythmSource::from_str("E(9,8)")
+		.unwrap()
+		.build(0.0)
+		.is_err());
+	assert!(RhythmSource::default().build(f64::NAN).is_err());
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_music_maker_rhythm() {
+	let quarter_second = usize::try_from(u32::try_from(SAMPLE_RATE).unwrap() / 4).unwrap();
+	// At 120 beats per minute "..x." rests for a quarter second, then plays for a quarter second
+	let music = MusicMaker::default()
+		.set_rhythm(Euclidean::new(1, 4).unwrap().with_rotation(2))
+		.set_tempo(120.0);
+	let samples = music.take(quarter_second * 2).collect::<Vec<_>>();
+	assert!(samples[..=quarter_second].iter().all(|s| *s == 0.0));
+	assert!(samples[quarter_second..].iter().any(|s| *s != 0.0));
+	// Silencing every note leaves nothing to hear, not even a first note
+	let music = MusicMaker::default().set_rhythm(WithRests::new(Steady::default(), 1.0).unwrap());
+	assert!(music.take(quarter_second).all(|s| s == 0.0));
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_music_maker_limits() {
+	use std::time::Duration;
+	let rate = usize::try_from(u32::try_from(SAMPLE_RATE).unwrap()).unwrap();
+	// Two bars of four beats at 120 beats per minute last four seconds
+	let music = MusicMaker::default()
+		.set_tempo(120.0)
+		.set_limit(Limit::Bars(2));
+	assert_eq!(music.total_duration(), Some(Duration::from_secs(4)));
+	assert_eq!(music.count(), rate * 4);
+	let music = MusicMaker::default().set_limit(Limit::Duration(Duration::from_millis(1500)));
+	assert_eq!(music.count(), rate * 3 / 2);
+	// The length of a number of notes is only known once the last one starts
+	let mut music = MusicMaker::default()
+		.set_tempo(240.0)
+		.set_limit(Limit::Notes(3));
+	assert_eq!(music.total_duration(), None);
+	let samples = music.by_ref().take(rate / 2 + 1).count();
+	assert_eq!(music.total_duration(), Some(Duration::from_millis(750)));
+	assert_eq!(samples + music.count(), rate * 3 / 4);
+	// The music fades out to nearly nothing, without fading before the fade starts
+	let samples = MusicMaker::default()
+		.set_limit(Limit::Duration(Duration::from_secs(2)))
+		.set_fade(Duration::from_millis(500))
+		.collect::<Vec<_>>();
+	let loudest = |samples: &[f32]| samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+	assert!(loudest(&samples[rate..rate * 3 / 2]) > 0.99);
+	assert!(loudest(&samples[samples.len() - rate / 100..]) < 0.02);
+	// A last note shorter than the fade fades over its own length, rather than starting part way down
+	let samples = MusicMaker::new(PianoKey::from_str("A4").unwrap(), Scale::default(), 1)
+		.set_tempo(240.0)
+		.set_limit(Limit::Notes(3))
+		.collect::<Vec<_>>();
+	let last_note = rate / 2;
+	assert!(loudest(&samples[last_note..last_note + rate / 100]) > 0.95);
+	assert!(loudest(&samples[samples.len() - rate / 100..]) < 0.05);
+	// An accompaniment plays along and stops with the melody
+	let silent = MusicMaker::default().set_rhythm(WithRests::new(Steady::default(), 1.0).unwrap());
+	#[allow(clippy::cast_precision_loss)]
+	let sine = (0..).map(move |i: u64| {
+		super::f64_to_f32(Waveform::Sine.sample(440.0 * i as f64 / rate as f64))
+	});
+	let music = silent
+		.set_accompaniment(sine)
+		.set_limit(Limit::Duration(Duration::from_secs(1)))
+		.set_fade(Duration::ZERO);
+	let samples = music.collect::<Vec<_>>();
+	assert_eq!(samples.len(), rate);
+	assert!(loudest(&samples) > 0.99);
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_music_maker_events() {
+	use std::{sync::mpsc, time::Duration};
+	let (sender, receiver) = mpsc::channel();
+	let melody = parse_melody("C4:4 E4:8 F#4:4").unwrap();
+	let music = MusicMaker::default()
+		.set_composer(Sequence::new(melody).unwrap())
+		.set_tempo(120.0)
+		.set_limit(Limit::Notes(3))
+		.set_events(sender);
+	// Each note is reported when it starts, in audio time
+	assert_eq!(music.count(), 48000 * 2);
+	let events = receiver.iter().collect::<Vec<_>>();
+	let starts = events.iter().map(|e| e.at).collect::<Vec<_>>();
+	assert_eq!(starts, [0, 500, 1500].map(Duration::from_millis));
+	let degrees = events.iter().map(|e| e.degree).collect::<Vec<_>>();
+	assert_eq!(degrees, [Some(1), Some(3), None]);
+	assert_eq!(events[0].to_string(), "C4     261.63 Hz  degree 1");
+	assert_eq!(events[2].to_string(), "F#4    370.00 Hz  outside the scale");
+}
+
+#[cfg(feature = "synth")]
+#[test]
+#[allow(clippy::float_cmp)]
+fn test_waveforms() {
+	// Every wave starts at zero, peaks a quarter of the way through and bottoms out at three quarters
+	for waveform in [Waveform::Sine, Waveform::Triangle] {
+		let samples = [0.0, 0.25, 0.5, 0.75, 1.0].map(|p| (waveform.sample(p) * 1e9).round() / 1e9);
+		assert_eq!(samples, [0.0, 1.0, 0.0, -1.0, 0.0], "{waveform}");
+	}
+	assert_eq!(
+		[0.0, 0.25, 0.75].map(|p| Waveform::Square.sample(p)),
+		[1.0, 1.0, -1.0]
+	);
+	assert_eq!(
+		[0.0, 0.25, 0.75].map(|p| Waveform::Sawtooth.sample(p)),
+		[0.0, 0.5, -0.5]
+	);
+	for waveform in Waveform::all() {
+		assert_eq!(Waveform::from_str(&waveform.to_string()).unwrap(), waveform);
+	}
+	assert_eq!(Waveform::from_str("SAW").unwrap(), Waveform::Sawtooth);
+	assert!(Waveform::from_str("noise").is_err());
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_music_maker_controller() {
+	use std::{sync::mpsc, thread, time::Duration};
+	let (sender, receiver) = mpsc::channel();
+	let mut music = MusicMaker::default()
+		.set_seed(Walk::default())
+		.set_tempo(240.0)
+		.set_events(sender);
+	let controller = music.controller();
+	let quarter = 12000;
+	// Changes from another thread wait for the next note
+	let first = music.by_ref().take(quarter / 2).collect::<Vec<_>>();
+	let handle = controller.clone();
+	thread::spawn(move || {
+		handle
+			.set_base_note(PianoKey::from_str("A4").unwrap())
+			.unwrap();
+		handle
+			.set_scale(Scale::from_str("Aeolian").unwrap())
+			.unwrap();
+		handle.set_tempo(120.0).unwrap();
+		handle.set_waveform(Waveform::Square).unwrap();
+		handle.set_volume(0.5).unwrap();
+	})
+	.join()
+	.unwrap();
+	let rest = music.by_ref().take(quarter / 2).collect::<Vec<_>>();
+	assert!(first.iter().chain(&rest).all(|s| s.abs() <= 1.0));
+	assert!(rest.iter().any(|s| s.abs() > 0.9));
+	let next = music.by_ref().take(quarter * 2).collect::<Vec<_>>();
+	// The opening note starts a sample late, so the first sample here still ends the old note
+	assert!(next[1..]
+		.iter()
+		.all(|s| (s.abs() - 0.5).abs() < 1e-6 || *s == 0.0));
+	assert_eq!(music.key().base_note, PianoKey::from_str("A4").unwrap());
+	// The second note starts a quarter beat in, the third half a second after it at the new tempo
+	music.by_ref().take(1).count();
+	let starts = receiver.try_iter().map(|e| e.at).collect::<Vec<_>>();
+	assert_eq!(starts, [0, 250, 750].map(Duration::from_millis));
+	// Numbers that would spoil every sample after them are turned away
+	assert!(controller.set_volume(f64::NAN).is_err());
+	assert!(controller.set_tempo(f64::INFINITY).is_err());
+	let quiet = MusicMaker::new(PianoKey::from_str("A4").unwrap(), Scale::default(), 1)
+		.set_volume(0.5)
+		.set_volume(f64::NAN)
+		.take(quarter)
+		.collect::<Vec<_>>();
+	assert!(quiet.iter().all(|s| s.abs() <= 0.5) && quiet.iter().any(|s| s.abs() > 0.49));
+	// Once the music is gone there is nothing left to control
+	drop(music);
+	assert!(controller.set_tempo(60.0).is_err());
+	// Bars already played keep the tempo they were played at, so speeding up can't cut the music short
+	let rate = quarter * 4;
+	let mut music = MusicMaker::default()
+		.set_tempo(120.0)
+		.set_limit(Limit::Bars(2));
+	let controller = music.controller();
+	let first = music.by_ref().take(rate - 100).count();
+	controller.set_tempo(240.0).unwrap();
+	// Two beats at 120 and six at 240 take two and a half seconds
+	assert!((first + music.count()).abs_diff(rate * 5 / 2) <= 2);
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_piano_roll() {
+	use std::time::Duration;
+	let key = Key::new(Scale::default(), PianoKey::from_str("C4").unwrap(), 1);
+	let mut roll = PianoRoll::new(key).with_rows(2);
+	for note in ["C4", "E4", "F#4"] {
+		let note = PianoKey::from_str(note).unwrap();
+		roll.push(NoteEvent::new(note, key, Duration::ZERO));
+	}
+	// The oldest note has scrolled away, and the newest is marked on the keyboard
+	assert_eq!(roll.current(), PianoKey::from_str("F#4").ok());
+	let expected = [
+		"          █                       E4     329.64 Hz  degree 3",
+		"                █                 F#4    370.00 Hz  outside the scale",
+		"|  [#] [#]  |  [*] [#] [#]  |   |",
+		"| o | o | o | o | o | o | o | o |",
+		"  C4  D   E   F   G   A   B   C5",
+	];
+	assert_eq!(roll.to_string(), expected.join("\n") + "\n");
+	// Notes outside the key widen the keyboard by whole octaves
+	roll.push(NoteEvent::new(
+		PianoKey::from_str("D3").unwrap(),
+		key,
+		Duration::ZERO,
+	));
+	assert!(roll
+		.keyboard()
+		.lines()
+		.last()
+		.unwrap()
+		.starts_with("  C3  D"));
+	// Fewer rows keep the newest notes
+	let roll = roll.with_rows(1);
+	assert_eq!(roll.current(), PianoKey::from_str("D3").ok());
+	assert_eq!(roll.to_string().matches('█').count(), 1);
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_motif_developments() {
+	let motif = parse_degree_notes("0/4 2/2 1/2 4/8");
+	let develop = |d: Development| {
+		d.apply(&motif)
+			.iter()
+			.map(ToString::to_string)
+			.collect::<Vec<_>>()
+			.join(" ")
+	};
+	assert_eq!(develop(Development::Repeat), "0/4 2/2 1/2 4/8");
+	assert_eq!(develop(Development::Transpose(2)), "2/4 4/2 3/2 6/8");
+	assert_eq!(develop(Development::Invert), "0/4 -2/2 -1/2 -4/8");
+	assert_eq!(develop(Development::Retrograde), "4/8 1/2 2/2 0/4");
+	assert_eq!(develop(Development::Augment), "0/8 2/4 1/4 4/16");
+	assert_eq!(develop(Development::Diminish), "0/2 2/1 1/1 4/4");
+	assert_eq!(develop(Development::Fragment(2)), "0/4 2/2");
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_phrases() {
+	use rand::{rngs::SmallRng, SeedableRng};
+	assert_eq!(Form::from_str("abac").unwrap().to_string(), "ABAC");
+	assert!(Form::from_str("").is_err());
+	assert!(Form::from_str("A-B").is_err());
+
+	let motif = parse_degree_notes("0/4 2/4 1/4 4/4");
+	let mut rng = SmallRng::seed_from_u64(7);
+	let form = Form::from_str("AABA").unwrap();
+	let phrases = Phrases::with_motif(form, motif.clone(), &mut rng).unwrap();
+	assert_eq!(phrases.motif(), &motif[..]);
+	assert_eq!(phrases.sections().len(), 2);
+	let plan = phrases.plan();
+	// Every phrase fills four bars and ends with a cadence of two half notes
+	let mut phrase_ends = Vec::new();
+	let mut total = 0;
+	for (i, note) in plan.iter().enumerate() {
+		total += note.steps;
+		if total % 64 == 0 {
+			phrase_ends.push(i);
+		}
+	}
+	assert_eq!(total, 4 * 64);
+	assert_eq!(phrase_ends.len(), 4);
+	let end = |i: usize| (plan[i - 1].degree.step, plan[i].degree.step);
+	assert_eq!(end(phrase_ends[0]), (5, 4));
+	assert_eq!(end(phrase_ends[2]), (5, 4));
+	assert_eq!(end(phrase_ends[3]), (1, 0));
+	// The A phrases open the same way, starting with the motif itself
+	assert_eq!(plan[..4], motif[..]);
+	let second = phrase_ends[0] + 1;
+	assert_eq!(plan[second..second + 4], motif[..]);
+	assert!(Phrases::with_motif(Form::default(), vec![], &mut rng).is_err());
+
+	// The rhythm and the notes come from the same plan
+	let key = Key::new(Scale::default(), PianoKey::from_str("G3").unwrap(), 2);
+	let mut phrases = Phrases::with_motif(Form::default(), motif, &mut rng).unwrap();
+	assert_eq!(phrases.next_pulse(), Pulse::Note(4));
+	assert_eq!(phrases.get_note(key), PianoKey::from_str("G3").unwrap());
+	assert_eq!(phrases.next_pulse(), Pulse::Note(4));
+	assert_eq!(phrases.get_note(key), PianoKey::from_str("B3").unwrap());
+	assert_eq!(phrases.get_note(key), PianoKey::from_str("A3").unwrap());
+}
+
+#[test]
+fn test_chord_symbols() {
+	let chord = Chord::from_str("Bbm7").unwrap();
+	assert_eq!(
+		chord,
+		Chord::new(Note::from_str("Bb").unwrap(), ChordQuality::Minor7)
+	);
+	let notes = chord
+		.notes()
+		.iter()
+		.map(ToString::to_string)
+		.collect::<Vec<_>>();
+	assert_eq!(notes.join(" "), "B♭ D♭ F A♭");
+	assert_eq!(Chord::from_str("F#dim").unwrap().to_string(), "F#dim");
+	assert_eq!(Chord::from_str("Cmaj7").unwrap().to_string(), "Cmaj7");
+	assert!(Chord::from_str("G7")
+		.unwrap()
+		.contains(Note::from_str("F").unwrap()));
+	assert!(!Chord::from_str("G7")
+		.unwrap()
+		.contains(Note::from_str("F#").unwrap()));
+	assert!(Chord::from_str("H7").is_err());
+}
+
+#[test]
+fn test_diatonic_chords() {
+	let c_major = Key::new(Scale::default(), PianoKey::from_str("C4").unwrap(), 1);
+	let triads = (0..7)
+		.map(|d| Chord::diatonic(c_major, d, false).unwrap().to_string())
+		.collect::<Vec<_>>();
+	assert_eq!(triads.join(" "), "C Dm Em F G Am Bdim");
+	let sevenths = (0..7)
+		.map(|d| Chord::diatonic(c_major, d, true).unwrap().to_string())
+		.collect::<Vec<_>>();
+	assert_eq!(sevenths.join(" "), "Cmaj7 Dm7 Em7 Fmaj7 G7 Am7 Bm7b5");
+	let chromatic = Key::new(Scale::Chromatic, PianoKey::from_str("C4").unwrap(), 1);
+	assert_eq!(Chord::diatonic(chromatic, 0, false), None);
+}
+
+#[test]
+fn test_roman_numerals() {
+	let numeral = RomanNumeral::from_str("viio").unwrap();
+	assert_eq!(numeral.degree, 6);
+	assert_eq!(numeral.quality, ChordQuality::Diminished);
+	assert_eq!(RomanNumeral::from_str("bVII").unwrap().to_string(), "♭VII");
+	assert_eq!(RomanNumeral::from_str("iiø7").unwrap().to_string(), "iiø7");
+	assert!(RomanNumeral::from_str("VIII").is_err());
+	assert!(RomanNumeral::from_str("X").is_err());
+
+	let g_major = Key::new(Scale::default(), PianoKey::from_str("G3").unwrap(), 1);
+	let progression = Progression::from_str("ii7-V7-Imaj7, bVII").unwrap();
+	assert_eq!(progression.to_string(), "ii7 V7 Imaj7 ♭VII");
+	let chords = progression.chords(g_major).unwrap();
+	let chords = chords.iter().map(ToString::to_string).collect::<Vec<_>>();
+	assert_eq!(chords.join(" "), "Am7 D7 Gmaj7 F");
+	assert!(progression
+		.chords(Key::new(Scale::Tetratonic, PianoKey::default(), 1))
+		.is_err());
+	assert!(Progression::from_str(" , ").is_err());
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_chord_melody() {
+	let key = Key::new(Scale::default(), PianoKey::from_str("C4").unwrap(), 2);
+	let progression = Progression::from_str("I IV V7 I").unwrap();
+	let mut melody = ChordMelody::new(progression).with_beats_per_chord(4);
+	let chords = melody.chords(key).unwrap();
+	let scale = key.all_keys();
+	for beat in 0..64 {
+		let note = melody.get_note(key);
+		assert!(scale.contains(&note), "{note} left the key");
+		if beat % 2 == 0 {
+			let chord = chords[beat / 4 % chords.len()];
+			assert!(chord.contains(note.note), "{note} is not in {chord}");
+		}
+	}
+	// The melody keeps time one beat at a time
+	assert_eq!(melody.next_pulse(), Pulse::Note(4));
+	// The harmony starts in root position an octave below, then moves as little as it can
+	let spell = |harmony: &Harmony| {
+		harmony
+			.voicings
+			.iter()
+			.map(|v| {
+				v.iter()
+					.map(ToString::to_string)
+					.collect::<Vec<_>>()
+					.join(" ")
+			})
+			.collect::<Vec<_>>()
+	};
+	let harmony = melody.harmony(key).unwrap();
+	assert_eq!(
+		spell(&harmony),
+		["C3 E3 G3 C4", "C3 F3 A3 C4", "B2 F3 G3 D4", "C3 E3 G3 C4"]
+	);
+	assert!(harmony.restarts.is_empty());
+	// Holding every common tone of V leaves no voice free for the seventh of V7,
+	// so only V7 starts again in root position and the voices lead on from it
+	let melody = ChordMelody::new(Progression::from_str("I V V7 I").unwrap());
+	let harmony = melody.harmony(key).unwrap();
+	assert_eq!(
+		harmony.restarts,
+		[(
+			2,
+			Error::NoVoicing {
+				from: Chord::from_str("G").unwrap(),
+				to: Chord::from_str("G7").unwrap()
+			}
+		)]
+	);
+	assert_eq!(
+		spell(&harmony),
+		["C3 E3 G3 C4", "B2 D3 G3 B3", "G3 B3 D4 F4", "G3 C4 C4 E4"]
+	);
+	// The restart keeps to the ranges of the voices too, so with the bass no higher than C3
+	// V7 starts again on G2
+	let bass = (parse_keys("E1")[0], parse_keys("C3")[0]);
+	let harmony = melody
+		.with_voice_leading(VoiceLeading::default().with_ranges(vec![bass]))
+		.harmony(key)
+		.unwrap();
+	assert_eq!(harmony.restarts.len(), 1);
+	assert_eq!(
+		spell(&harmony),
+		["C3 E3 G3 C4", "B2 D3 G3 B3", "G2 B3 D4 F4", "G2 C4 C4 E4"]
+	);
+}
+
+#[test]
+fn test_voice_leading() {
+	let chord = |s| Chord::from_str(s).unwrap();
+	let start = parse_keys("C3 G3 C4 E4");
+	let leading = VoiceLeading::default();
+	// Common tones hold while the other voices step
+	assert_eq!(
+		leading.lead(chord("C"), &start, chord("Am")).unwrap(),
+		parse_keys("C3 A3 C4 E4")
+	);
+	assert_eq!(
+		leading.lead(chord("C"), &start, chord("G7")).unwrap(),
+		parse_keys("B2 G3 D4 F4")
+	);
+	assert!(leading
+		.clone()
+		.with_max_motion(1)
+		.lead(chord("C"), &start, chord("G7"))
+		.is_err());
+	let range = (parse_keys("C3")[0], parse_keys("G3")[0]);
+	assert_eq!(
+		leading
+			.clone()
+			.with_ranges(vec![range])
+			.lead(chord("C"), &start, chord("G7"))
+			.unwrap(),
+		parse_keys("D3 G3 B3 F4")
+	);
+	// The upper voice has to leap up a fifth unless it may cross below the held B
+	let close = parse_keys("B3 C4");
+	assert_eq!(
+		leading.lead(chord("Cmaj7"), &close, chord("G")).unwrap(),
+		parse_keys("B3 G4")
+	);
+	assert_eq!(
+		leading
+			.clone()
+			.with_crossing(true)
+			.lead(chord("Cmaj7"), &close, chord("G"))
+			.unwrap(),
+		parse_keys("B3 G3")
+	);
+	let chords = ["C", "Am", "F", "G7", "C"].map(chord);
+	let mut voicing = start.clone();
+	for pair in chords.windows(2) {
+		voicing = leading.lead(pair[0], &voicing, pair[1]).unwrap();
+		if pair[1] == chord("G7") {
+			assert_eq!(voicing, parse_keys("B2 G3 D4 F4"));
+		}
+	}
+	assert_eq!(voicing, start);
+	// Root position voicings move by octaves into the ranges of the voices
+	assert_eq!(
+		leading.root_position(chord("G7"), 3).unwrap(),
+		parse_keys("G3 B3 D4 F4")
+	);
+	assert_eq!(
+		leading
+			.clone()
+			.with_ranges(vec![range])
+			.root_position(chord("F"), 4)
+			.unwrap(),
+		parse_keys("F3 A4 C5 F5")
+	);
+	// A bass that has to sit above the tenor would cross it
+	let high_bass = (parse_keys("C5")[0], parse_keys("C6")[0]);
+	assert!(leading
+		.clone()
+		.with_ranges(vec![high_bass])
+		.root_position(chord("F"), 4)
+		.is_err());
+}
+
+#[test]
+fn test_counterpoint_checker() {
+	let cantus = parse_keys("C4 D4 F4 E4 D4 C4");
+	let exercise = Exercise::new(cantus.clone(), Species::First, Placement::Above).unwrap();
+	let good = parse_melody("G4:16 F4:16 A4:16 G4:16 B4:16 C5:16").unwrap();
+	assert_eq!(exercise.check(&good), vec![]);
+	// Fifths in the first two bars, then two fourths, which are dissonant between two voices
+	let bad = parse_melody("G4:16 A4:16 B♭4:16 A4:16 B4:16 C5:16").unwrap();
+	let report = exercise
+		.check(&bad)
+		.iter()
+		.map(ToString::to_string)
+		.collect::<Vec<_>>();
+	assert_eq!(
+		report,
+		vec![
+			"Bar 2: parallel fifths",
+			"Bar 3: dissonance not on a passing or neighbour tone",
+			"Bar 4: dissonance not on a passing or neighbour tone",
+		]
+	);
+	let crossing = parse_melody("C4:16 B3:16 A4:16 G4:16 B4:16 C5:16").unwrap();
+	let rules = exercise
+		.check(&crossing)
+		.iter()
+		.map(|v| v.rule)
+		.collect::<Vec<_>>();
+	assert!(rules.contains(&Rule::Crossing));
+	assert!(rules.contains(&Rule::Leap));
+	let leaping = parse_melody("G4:16 F4:16 D5:16 E5:16 B4:16 C5:16").unwrap();
+	let rules = exercise
+		.check(&leaping)
+		.iter()
+		.map(|v| v.rule)
+		.collect::<Vec<_>>();
+	assert!(rules.contains(&Rule::UnrecoveredLeap));
+	let short = parse_melody("G4:16 F4:16 A4:16 G4:16 C5:16").unwrap();
+	let rules = exercise
+		.check(&short)
+		.iter()
+		.map(|v| v.rule)
+		.collect::<Vec<_>>();
+	assert!(rules.contains(&Rule::Length));
+
+	// A dissonant passing tone on the weak beat is fine
+	let second = Exercise::new(cantus.clone(), Species::Second, Placement::Above).unwrap();
+	let passing = parse_melody("G4:8 A4:8 B4:8 C5:8 D5:8 C5:8 G4:8 C5:8 A4:8 B4:8 C5:16").unwrap();
+	assert_eq!(second.check(&passing), vec![]);
+	// A chain of suspensions, each prepared by a consonance and resolved down by step
+	let fourth = Exercise::new(cantus, Species::Fourth, Placement::Above).unwrap();
+	let suspended = parse_melody("C5:8 G5:16 F5:16 D5:16 C5:16 B4:8 C5:16").unwrap();
+	assert_eq!(fourth.check(&suspended), vec![]);
+	assert!(Exercise::new(parse_keys("C4 D4"), Species::First, Placement::Above).is_err());
+	assert_eq!(Species::from_str("3").unwrap(), Species::Third);
+	assert_eq!(Species::Fifth.to_string(), "fifth species");
+	assert_eq!(Placement::from_str("below").unwrap(), Placement::Below);
+}
+
+#[test]
+fn test_counterpoint_generator() {
+	use rand::{rngs::SmallRng, SeedableRng};
+	// Fux's cantus firmus in the Dorian mode
+	let cantus = parse_keys("D4 F4 E4 D4 G4 F4 A4 G4 F4 E4 D4");
+	let key = Key::new(
+		Scale::Diatonic(Mode::Dorian),
+		PianoKey::from_str("D4").unwrap(),
+		1,
+	);
+	let mut rng = SmallRng::seed_from_u64(3);
+	for species in Species::all() {
+		for placement in [Placement::Above, Placement::Below] {
+			let exercise = Exercise::new(cantus.clone(), species, placement).unwrap();
+			let line = exercise.generate(key, &mut rng).unwrap();
+			assert_eq!(exercise.check(&line), vec![], "{species} {placement}");
+			assert_eq!(line.iter().map(|n| n.steps).sum::<u16>(), 11 * 16);
+			assert_eq!(line.last().unwrap().key.note, Note::from_str("D").unwrap());
+		}
+	}
+	let exercise = Exercise::new(cantus, Species::Second, Placement::Above).unwrap();
+	assert_eq!(exercise.rhythm(&mut rng), [vec![8; 20], vec![16]].concat());
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_sequence_seed() {
+	let melody = parse_melody("C4:16 E4:8 G4:8").unwrap();
+	let mut sequence = Sequence::new(melody.clone()).unwrap();
+	let key = Key::default();
+	for note in melody.iter().chain(&melody) {
+		assert_eq!(sequence.next_pulse(), Pulse::Note(note.steps));
+		assert_eq!(sequence.get_note(key), note.key);
+	}
+	assert!(Sequence::new(vec![]).is_err());
+}
+
+#[test]
+fn test_chorale() {
+	let melody = parse_melody("E4:4 D4:4 C4:4 E4:4 F4:4 E4:4 D4:4 C4:8").unwrap();
+	let chorale = Chorale::harmonize(&melody, Key::default()).unwrap();
+	let voicings = chorale.voicings();
+	assert_eq!(chorale.voice(Voice::Soprano), melody);
+	for voicing in voicings {
+		let semitones = voicing.keys.map(PianoKey::semitones_from_c0);
+		assert!(semitones.windows(2).all(|w| w[0] >= w[1]), "{chorale}");
+		for voice in Voice::all() {
+			let (low, high) = voice.range();
+			let key = voicing.key(voice);
+			let semitones = key.semitones_from_c0();
+			assert!(
+				low.semitones_from_c0() <= semitones && semitones <= high.semitones_from_c0(),
+				"{voice} {key}"
+			);
+			assert!(voicing.chord.contains(key.note));
+		}
+	}
+	for pair in voicings.windows(2) {
+		let [a, b] = [0, 1].map(|i| pair[i].keys.map(PianoKey::semitones_from_c0));
+		for upper in 0..4 {
+			for lower in upper + 1..4 {
+				let before = (a[upper] - a[lower]) % 12;
+				let moved = a[upper] != b[upper] && a[lower] != b[lower];
+				let parallel = moved && before == (b[upper] - b[lower]) % 12;
+				assert!(!(parallel && matches!(before, 0 | 7)), "{chorale}");
+			}
+		}
+	}
+	for voicing in [voicings[0], voicings[voicings.len() - 1]] {
+		assert_eq!(voicing.numeral.to_string(), "I");
+		assert!(voicing.is_root_position());
+	}
+	// Minor keys raise the leading tone in V and vii°, so a cadence can close with V-i
+	let a_minor = Key::new(
+		Scale::Diatonic(Mode::Aeolian),
+		PianoKey::from_str("A4").unwrap(),
+		1,
+	);
+	let minor = parse_melody("A4:4 B4:4 G#4:4 A4:8").unwrap();
+	let chorale = Chorale::harmonize(&minor, a_minor).unwrap();
+	let numerals = chorale
+		.voicings()
+		.iter()
+		.map(|v| v.numeral.to_string())
+		.collect::<Vec<_>>();
+	assert_eq!(numerals.last().map(String::as_str), Some("i"));
+	assert!(matches!(numerals[2].as_str(), "V" | "#viio"), "{chorale}");
+	assert_eq!(Chord::harmonic(a_minor, 4, true).unwrap().to_string(), "E7");
+	assert_eq!(
+		Chord::harmonic(a_minor, 6, false).unwrap().to_string(),
+		"G#dim"
+	);
+	assert_eq!(
+		Chord::harmonic(a_minor, 3, false),
+		Chord::diatonic(a_minor, 3, false)
+	);
+	assert_eq!(
+		Chord::harmonic(Key::default(), 4, true),
+		Chord::diatonic(Key::default(), 4, true)
+	);
+	let chromatic = Key::new(Scale::Chromatic, PianoKey::default(), 1);
+	assert!(Chorale::harmonize(&melody, chromatic).is_err());
+	assert!(Chorale::harmonize(&[], Key::default()).is_err());
+	let bytes = crate::midi::encode_melodies(&chorale.voices(), 90.0).unwrap();
+	assert_eq!(
+		crate::midi::parse_melodies(&bytes).unwrap(),
+		chorale.voices()
+	);
+}
+
+fn parse_keys(s: &str) -> Vec<PianoKey> {
+	s.split_whitespace()
+		.map(|k| PianoKey::from_str(k).unwrap())
+		.collect()
+}
+
+#[cfg(feature = "synth")]
+fn parse_degree_notes(s: &str) -> Vec<DegreeNote> {
+	s.split_whitespace()
+		.map(|n| DegreeNote::from_str(n).unwrap())
+		.collect()
+}
+
 #[test]
 fn test_semitones_to_cents() {
 	assert_eq!(Cents::from(Semitones::from(1)), Cents::from(100.0));
@@ -203,10 +1782,10 @@ fn test_semitones_to_cents() {
 
 #[test]
 fn test_interval_to_cents() {
-	use Interval::*;
-	assert_eq!(Cents::from(Unison), Cents::from(0.0));
-	assert_eq!(Cents::from(Min2), Cents::from(100.0));
-	assert_eq!(Cents::from(Octave), Cents::from(1200.0));
+	use Interval as I;
+	assert_eq!(Cents::from(I::UNISON), Cents::from(0.0));
+	assert_eq!(Cents::from(I::MIN2), Cents::from(100.0));
+	assert_eq!(Cents::from(I::OCTAVE), Cents::from(1200.0));
 }
 
 #[test]
@@ -218,17 +1797,15 @@ fn test_add_cents_to_pitch() {
 
 #[test]
 fn test_add_semitones_to_pitch() {
-	use Interval::Octave;
 	let mut pitch = Pitch::default();
-	pitch += Semitones::from(Octave);
+	pitch += Semitones::from(Interval::OCTAVE);
 	assert_eq!(pitch, Pitch::new(Hertz::from(880.0)));
 }
 
 #[test]
 fn test_add_interval_to_pitch() {
-	use Interval::Min2;
 	let mut pitch = Pitch::default();
-	pitch += Min2;
+	pitch += Interval::MIN2;
 	assert_eq!(pitch, Pitch::new(Hertz::from(466.1)));
 }
 
@@ -237,3 +1814,368 @@ fn test_piano_key_to_pitch() {
 	assert_eq!(Pitch::from(PianoKey::new("A4").unwrap()), Pitch::default());
 	assert_eq!(Pitch::from(PianoKey::default()), Pitch::new(C_ZERO));
 }
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_repl() {
+	let key = |s| PianoKey::from_str(s).unwrap();
+	let command = |s| Command::from_str(s).unwrap();
+	assert_eq!(
+		command("key D dorian"),
+		Command::Key {
+			note: Note::from_str("D").unwrap(),
+			octave: None,
+			scale: Some(Scale::Diatonic(Mode::Dorian)),
+		}
+	);
+	assert_eq!(command("  TEMPO 90 "), Command::Tempo(90.0));
+	assert_eq!(command("wave saw"), Command::Waveform(Waveform::Sawtooth));
+	assert_eq!(command("tone A4"), Command::Tone(key("A4")));
+	for bad in [
+		"",
+		"dance",
+		"key",
+		"key D dorian now",
+		"tempo fast",
+		"tempo inf",
+		"volume NaN",
+		"octaves -1",
+		"play now",
+	] {
+		assert!(Command::from_str(bad).is_err(), "{bad}");
+	}
+
+	let mut session = Session::default();
+	session.run_line("key D dorian").unwrap();
+	session.run_line("octaves 2").unwrap();
+	assert_eq!(
+		session.key(),
+		Key::new(Scale::Diatonic(Mode::Dorian), key("D4"), 2)
+	);
+	let Ok(Action::Sound(keys, _)) = session.run_line("chord Am7") else {
+		panic!("a chord should sound");
+	};
+	assert_eq!(keys, ["A4", "C5", "E5", "G5"].map(key));
+	let Ok(Action::Sound(keys, _)) = session.run_line("chord V") else {
+		panic!("a numeral should sound");
+	};
+	assert_eq!(keys, ["A4", "C#5", "E5"].map(key));
+	assert!(session.run_line("chord H7").is_err());
+
+	// Changes reach the music while it plays
+	let Ok(Action::Play(mut music)) = session.run_line("play") else {
+		panic!("play should start the music");
+	};
+	assert!(session.is_playing());
+	session.run_line("key G3 minor").unwrap();
+	music.by_ref().take(2).count();
+	assert_eq!(
+		music.key(),
+		Key::new(Scale::Diatonic(Mode::Aeolian), key("G3"), 2)
+	);
+	drop(music);
+	session.run_line("tempo 120").unwrap();
+	assert!(!session.is_playing());
+	assert!(matches!(session.run_line("quit"), Ok(Action::Quit)));
+}
+
+#[test]
+fn test_qwerty() {
+	let key = |s| PianoKey::from_str(s).unwrap();
+	let c_major = Key::new(Scale::default(), key("C4"), 1);
+	let mut layout = Qwerty::new(c_major);
+	let typed = "zsxcqi/]".chars().map(|c| layout.key_for(c).unwrap());
+	assert_eq!(
+		typed.collect::<Vec<_>>(),
+		["C4", "C#4", "D4", "E4", "C5", "C6", "E5", "G6"].map(key)
+	);
+	assert_eq!(layout.key_for('Z'), Some(key("C4")));
+	assert_eq!(layout.key_for('a'), None);
+	layout.octave_down();
+	assert_eq!(layout.key_for('z'), Some(key("C3")));
+	for _ in 0..10 {
+		layout.octave_up();
+	}
+	assert_eq!(layout.octave(), 7);
+	assert_eq!(layout.key_for('q'), Some(key("C8")));
+	assert_eq!(layout.key_for('i'), None);
+
+	// Snapping keeps to the key, spelled its way, going down when two notes are as near
+	let f_major = Key::new(Scale::default(), key("F4"), 1);
+	let layout = Qwerty::new(f_major);
+	assert_eq!(layout.key_for('j'), Some(key("Bb4")));
+	assert_eq!(layout.key_for('g'), Some(key("F#4")));
+	let layout = layout.with_snap(true);
+	assert_eq!(layout.key_for('g'), Some(key("F4")));
+	assert_eq!(layout.key_for('s'), Some(key("C4")));
+	assert_eq!(layout.key_for('m'), Some(key("Bb4")));
+}
+
+#[cfg(feature = "synth")]
+#[test]
+#[allow(clippy::float_cmp)]
+fn test_synth_envelope() {
+	let key = PianoKey::from_str("A4").unwrap();
+	let envelope = Envelope {
+		attack: std::time::Duration::from_millis(10),
+		decay: std::time::Duration::from_millis(10),
+		sustain: 0.5,
+		release: std::time::Duration::from_millis(10),
+	};
+	let mut synth = Synth::default()
+		.with_waveform(Waveform::Square)
+		.with_envelope(envelope);
+	let peak = |samples: &[f32]| samples.iter().fold(0.0_f32, |peak, s| peak.max(s.abs()));
+	assert_eq!(peak(&synth.by_ref().take(100).collect::<Vec<_>>()), 0.0);
+	let keys = synth.keys();
+	keys.note_on(key).unwrap();
+	// The attack and decay take about 480 samples each, after which the note holds at the sustain level
+	let attack = synth.by_ref().take(480).collect::<Vec<_>>();
+	assert!(peak(&attack[..240]) < peak(&attack[240..]));
+	let sustain = synth.by_ref().skip(500).take(4800).collect::<Vec<_>>();
+	assert!(sustain.iter().all(|s| (s.abs() - 0.2).abs() < 1e-6));
+	assert_eq!(synth.sounding(), [key]);
+	keys.note_off(key).unwrap();
+	let release = synth.by_ref().take(500).collect::<Vec<_>>();
+	assert!(peak(&release[240..]) < 0.11);
+	assert!(synth.sounding().is_empty());
+	assert_eq!(peak(&synth.by_ref().take(100).collect::<Vec<_>>()), 0.0);
+	drop(synth);
+	assert!(keys.note_on(key).is_err());
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_config() {
+	let config = Config::from_str(
+		r#"
+scale = "dorian"
+tempo = 90
+walk = true
+base_note = "D3"
+
+[presets.drone]
+base-note = "D2"
+release = 0.5
+steps = [1, 2.5, 3]
+"#,
+	)
+	.unwrap();
+	assert_eq!(config.presets(), ["drone"]);
+	let settings = config.settings(None).unwrap();
+	let setting = |settings: &std::collections::BTreeMap<String, String>, name: &str| {
+		settings.get(name).cloned()
+	};
+	assert_eq!(setting(&settings, "base-note").as_deref(), Some("D3"));
+	assert_eq!(setting(&settings, "tempo").as_deref(), Some("90"));
+	assert_eq!(setting(&settings, "walk").as_deref(), Some("true"));
+	assert_eq!(setting(&settings, "release"), None);
+	let drone = config.settings(Some("drone")).unwrap();
+	assert_eq!(setting(&drone, "base-note").as_deref(), Some("D2"));
+	assert_eq!(setting(&drone, "scale").as_deref(), Some("dorian"));
+	assert_eq!(setting(&drone, "steps").as_deref(), Some("1,2.5,3"));
+	assert!(config.settings(Some("loud")).is_err());
+	for bad in [
+		"tempo = ",
+		"[scale]\nmode = 1",
+		"presets = 1",
+		"[presets]\ndrone = 1",
+	] {
+		assert!(Config::from_str(bad).is_err(), "{bad}");
+	}
+}
+
+#[cfg(feature = "synth")]
+#[test]
+fn test_envelope_and_tuning() {
+	use std::time::Duration;
+	let envelope = Envelope {
+		attack: Duration::from_millis(100),
+		decay: Duration::from_millis(100),
+		sustain: 0.5,
+		release: Duration::from_millis(200),
+	};
+	let second = Duration::from_secs(1);
+	let levels = [0, 50, 100, 150, 200, 500, 900, 1000, 1200]
+		.map(|ms| envelope.level(Duration::from_millis(ms), second));
+	let expected = [0.0, 0.5, 1.0, 0.75, 0.5, 0.5, 0.25, 0.0, 0.0];
+	assert!(
+		levels
+			.iter()
+			.zip(expected)
+			.all(|(l, e)| (l - e).abs() < 1e-9),
+		"{levels:?}"
+	);
+
+	let a4 = PianoKey::from_str("A4").unwrap();
+	let tuned = f64::from(Pitch::from(a4).tuned(Hertz::from(432.0)));
+	assert!((tuned - 432.0).abs() < 0.05);
+	// An enveloped note rises from silence instead of starting at full volume
+	let mut music = MusicMaker::default()
+		.set_waveform(Waveform::Square)
+		.set_envelope(envelope);
+	let start = music.by_ref().take(480).collect::<Vec<_>>();
+	assert!(start.iter().all(|s| s.abs() <= 0.1));
+	// Reported notes and accompanying chords follow the tuning too
+	let event = NoteEvent::new(a4, Key::default(), Duration::ZERO).with_tuning(Hertz::from(432.0));
+	assert!((event.frequency - 432.0).abs() < 0.05);
+	// Half a cycle of A4 is 55 samples at 440 Hz, and a sample longer at 432 Hz
+	let half_cycle = |chords: ChordPlayer| chords.skip(1).take_while(|s| *s > 0.0).count() + 1;
+	let chords = || ChordPlayer::new(&[vec![a4]], 4, 60.0).with_amplitude(1.0);
+	assert_eq!(half_cycle(chords()), 55);
+	assert_eq!(half_cycle(chords().with_tuning(Hertz::from(432.0))), 56);
+	let square = chords()
+		.with_waveform(Waveform::Square)
+		.take(10)
+		.collect::<Vec<_>>();
+	assert!(square[1..].iter().all(|s| (s - 1.0).abs() < 1e-6));
+	let swell = chords()
+		.with_envelope(envelope)
+		.take(480)
+		.collect::<Vec<_>>();
+	assert!(swell.iter().all(|s| s.abs() <= 0.1));
+}
+
+#[test]
+fn test_errors() {
+	use std::io;
+	assert!(matches!(PianoKey::from_str("H4"), Err(Error::BadLetter(_))));
+	assert!(matches!(
+		PianoKey::from_str("Cq4"),
+		Err(Error::BadAccidental(_))
+	));
+	assert!(matches!(
+		PianoKey::from_str("C"),
+		Err(Error::MissingOctave(_))
+	));
+	assert_eq!(PianoKey::from_str("C9"), Err(Error::OctaveOutOfRange(9)));
+	assert!(matches!(Note::from_str(""), Err(Error::MissingNote)));
+
+	let Err(Error::UnknownScale { name, suggestions }) = Scale::from_str("dorain") else {
+		panic!("dorain should be an unknown scale");
+	};
+	assert_eq!(name, "dorain");
+	assert_eq!(suggestions.first(), Some(&"dorian"));
+	assert!(Scale::from_str("dorain")
+		.unwrap_err()
+		.to_string()
+		.contains("did you mean dorian"));
+	assert_eq!(
+		Scale::from_str("xyzzy"),
+		Err(Error::UnknownScale {
+			name: "xyzzy".to_string(),
+			suggestions: Vec::new()
+		})
+	);
+
+	assert_eq!(u32::try_from(Hertz::from(-1.0)), Err(Error::NegativeHertz));
+	assert_eq!(u32::try_from(Hertz::from(1.5)), Err(Error::FractionalHertz));
+	let error = io::Error::from(Error::OctaveOutOfRange(9));
+	assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+	assert_eq!(error.to_string(), Error::OctaveOutOfRange(9).to_string());
+
+	// The theory builds on the same errors
+	assert_eq!(
+		Interval::from_str("M5"),
+		Err(Error::ImpossibleInterval {
+			quality: Quality::Major,
+			number: 5
+		})
+	);
+	assert_eq!(
+		Interval::from_str("P0"),
+		Err(Error::IntervalNumberOutOfRange(0))
+	);
+	assert_eq!(
+		KeySignature::major(Note::from_str("G#").unwrap()),
+		Err(Error::TheoreticalKey {
+			tonic: Note::from_str("G#").unwrap(),
+			mode: Mode::Ionian
+		})
+	);
+	assert_eq!(RowForm::from_str("X3"), Err(Error::BadRowForm("X3".into())));
+	assert_eq!(ToneRow::new(&[0; 12]), Err(Error::IncompleteRow(1)));
+	let chromatic = Key::new(Scale::Chromatic, PianoKey::from_str("C4").unwrap(), 1);
+	assert_eq!(
+		RomanNumeral::from_str("V").unwrap().chord(chromatic),
+		Err(Error::NotDiatonic(Scale::Chromatic))
+	);
+	assert!(matches!(
+		Chord::from_str("H7"),
+		Err(Error::BadChordSymbol(_))
+	));
+	let c = Chord::from_str("C").unwrap();
+	let error = io::Error::from(Error::NoRootPosition(c));
+	assert_eq!(error.kind(), io::ErrorKind::NotFound);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() {
+	let key = Key::new(
+		Scale::Diatonic(Mode::Dorian),
+		PianoKey::from_str("Eb4").unwrap(),
+		2,
+	);
+	let json = serde_json::to_string(&key).unwrap();
+	assert_eq!(json, r#"{"base_note":"E♭4","octaves":2,"scale":"Dorian"}"#);
+	assert_eq!(serde_json::from_str::<Key>(&json).unwrap(), key);
+	assert_eq!(
+		serde_json::from_str::<Key>(r#"{"base_note":"D#3","octaves":1,"scale":"minor"}"#).unwrap(),
+		Key::new(
+			Scale::Diatonic(Mode::Aeolian),
+			PianoKey::from_str("D#3").unwrap(),
+			1
+		)
+	);
+	assert!(serde_json::from_str::<PianoKey>(r#""H4""#).is_err());
+	// Keys read back are kept on the piano, like those built with `Key::new`
+	let high =
+		serde_json::from_str::<Key>(r#"{"base_note":"C8","octaves":200,"scale":"major"}"#).unwrap();
+	assert_eq!(high.octaves, 0);
+	assert_eq!(high.all_keys().len(), 1);
+
+	let melody = parse_melody("C4:2 G#4:4").unwrap();
+	let json = serde_json::to_string(&melody).unwrap();
+	assert_eq!(json, r#"["C4:2","G#4:4"]"#);
+	assert_eq!(
+		serde_json::from_str::<Vec<MelodyNote>>(&json).unwrap(),
+		melody
+	);
+	for scale in Scale::all() {
+		let json = serde_json::to_string(&scale).unwrap();
+		assert_eq!(serde_json::from_str::<Scale>(&json).unwrap(), scale);
+	}
+	let interval = Interval::from_str("m7").unwrap();
+	assert_eq!(serde_json::to_string(&interval).unwrap(), r#""m7""#);
+	let chord = Chord::from_str("Bbm7").unwrap();
+	let json = serde_json::to_string(&chord).unwrap();
+	assert_eq!(serde_json::from_str::<Chord>(&json).unwrap(), chord);
+	assert_eq!(serde_json::to_string(&Hertz::from(440.0)).unwrap(), "440.0");
+}
+
+#[cfg(all(feature = "serde", feature = "synth"))]
+#[test]
+fn test_serde_output() {
+	use std::time::Duration;
+	assert_eq!(
+		serde_json::to_string(&Waveform::Square).unwrap(),
+		r#""square""#
+	);
+	let envelope = Envelope::default();
+	let json = serde_json::to_string(&envelope).unwrap();
+	assert_eq!(serde_json::from_str::<Envelope>(&json).unwrap(), envelope);
+
+	let key = Key::new(
+		Scale::Diatonic(Mode::Dorian),
+		PianoKey::from_str("Eb4").unwrap(),
+		2,
+	);
+	let event = NoteEvent::new(
+		PianoKey::from_str("A4").unwrap(),
+		key,
+		Duration::from_millis(1500),
+	);
+	let json = serde_json::to_string(&event).unwrap();
+	assert_eq!(serde_json::from_str::<NoteEvent>(&json).unwrap(), event);
+}
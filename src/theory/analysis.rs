@@ -0,0 +1,196 @@
+//! Analysis works backwards from a collection of notes to the keys and scales they could belong to
+
+use super::{
+	key::Key,
+	key_signature::KeySignature,
+	nearest_alteration,
+	note::{Accidental, Letter, Note},
+	piano_key::PianoKey,
+	scale::{Mode, Scale},
+	semitone::Semitones,
+};
+use std::cmp::Reverse;
+
+/// Krumhansl-Kessler probe-tone ratings for a major key, starting from the tonic
+const MAJOR_PROFILE: [f64; 12] = [
+	6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+
+/// Krumhansl-Kessler probe-tone ratings for a minor key, starting from the tonic
+const MINOR_PROFILE: [f64; 12] = [
+	6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+/// A Candidate is a key that could hold some set of notes, with details on how well it fits
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+	pub key: Key,
+	/// Input notes that fall outside the key
+	pub outside: Vec<Note>,
+	/// Notes of the key that never appear in the input
+	pub unused: Vec<Note>,
+}
+
+/// An Estimate is a major or minor key with its correlation to a weighted pitch profile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+	pub key: Key,
+	/// Pearson correlation between -1 and 1, higher is a better match
+	pub correlation: f64,
+}
+
+/// Rank every key and scale by how well it contains the given notes.
+/// Keys holding every note come first, then tighter scales, then keys whose base note opens or closes the melody.
+#[must_use]
+pub fn rank_keys<N: Into<Note>>(notes: impl IntoIterator<Item = N>) -> Vec<Candidate> {
+	let notes = notes.into_iter().map(Into::into).collect::<Vec<Note>>();
+	let first = notes.first().map(|n| n.pitch_class());
+	let last = notes.last().map(|n| n.pitch_class());
+	let mut ret = Vec::new();
+	for scale in Scale::all() {
+		for pitch_class in 0..12 {
+			let key = Key::new(
+				scale,
+				PianoKey {
+					note: preferred_tonic(pitch_class, scale),
+					octave: 4,
+				},
+				1,
+			);
+			let key_notes = distinct(key.get_notes());
+			let outside = distinct(
+				notes
+					.iter()
+					.copied()
+					.filter(|n| !key_notes.iter().any(|k| k.pitch_class() == n.pitch_class())),
+			);
+			let unused = key_notes
+				.into_iter()
+				.filter(|k| !notes.iter().any(|n| n.pitch_class() == k.pitch_class()))
+				.collect();
+			ret.push(Candidate {
+				key,
+				outside,
+				unused,
+			});
+		}
+	}
+	ret.sort_by_key(|c| {
+		let tonic = c.key.base_note.note.pitch_class();
+		let emphasis = 2 * u8::from(last == Some(tonic)) + u8::from(first == Some(tonic));
+		(
+			c.outside.len(),
+			c.unused.len(),
+			Reverse(emphasis),
+			commonness(c.key.scale),
+		)
+	});
+	ret
+}
+
+/// Estimate the major or minor key of a melody with the Krumhansl-Schmuckler algorithm.
+/// Each note is paired with its duration in any consistent unit, so long notes count for more.
+/// All 24 keys are returned, best match first.
+#[must_use]
+pub fn estimate_key<N: Into<Note>>(notes: impl IntoIterator<Item = (N, f64)>) -> Vec<Estimate> {
+	let mut durations = [0.0; 12];
+	for (note, duration) in notes {
+		durations[usize::from(note.into().pitch_class())] += duration;
+	}
+	let mut ret = Vec::new();
+	for (mode, profile) in [
+		(Mode::Ionian, MAJOR_PROFILE),
+		(Mode::Aeolian, MINOR_PROFILE),
+	] {
+		for pitch_class in 0..12u8 {
+			// Rotate the profile so its tonic lines up with this pitch class
+			let rotated = (0..12)
+				.map(|i| profile[(i + 12 - usize::from(pitch_class)) % 12])
+				.collect::<Vec<_>>();
+			let scale = Scale::Diatonic(mode);
+			ret.push(Estimate {
+				key: Key::new(
+					scale,
+					PianoKey {
+						note: preferred_tonic(pitch_class, scale),
+						octave: 4,
+					},
+					1,
+				),
+				correlation: correlation(&durations, &rotated),
+			});
+		}
+	}
+	ret.sort_by(|a, b| b.correlation.total_cmp(&a.correlation));
+	ret
+}
+
+/// Pearson correlation coefficient between two equal-length series
+fn correlation(xs: &[f64], ys: &[f64]) -> f64 {
+	#[allow(clippy::cast_precision_loss)]
+	let len = xs.len() as f64;
+	let x_mean = xs.iter().sum::<f64>() / len;
+	let y_mean = ys.iter().sum::<f64>() / len;
+	let mut covariance = 0.0;
+	let mut x_variance = 0.0;
+	let mut y_variance = 0.0;
+	for (x, y) in xs.iter().zip(ys) {
+		covariance += (x - x_mean) * (y - y_mean);
+		x_variance += (x - x_mean).powi(2);
+		y_variance += (y - y_mean).powi(2);
+	}
+	let denominator = (x_variance * y_variance).sqrt();
+	if denominator == 0.0 {
+		0.0
+	} else {
+		covariance / denominator
+	}
+}
+
+/// Drop repeated pitch classes, keeping the first spelling seen
+fn distinct(notes: impl IntoIterator<Item = Note>) -> Vec<Note> {
+	let mut ret: Vec<Note> = Vec::new();
+	for note in notes {
+		if !ret.iter().any(|n| n.pitch_class() == note.pitch_class()) {
+			ret.push(note);
+		}
+	}
+	ret
+}
+
+/// Spell a tonic the way its key is usually written - E♭ major rather than D# major
+fn preferred_tonic(pitch_class: u8, scale: Scale) -> Note {
+	let semitones = i8::try_from(pitch_class).expect("Pitch classes are below 12");
+	let default = Note::from(Semitones::from(semitones));
+	let Scale::Diatonic(mode) = scale else {
+		return default;
+	};
+	(0..7)
+		.map(|steps| Letter::C.step(steps))
+		.filter_map(|letter| {
+			let alteration = nearest_alteration(semitones - i8::from(letter.interval_from_c()));
+			// Double sharps and flats never make a sensible tonic
+			if alteration.abs() > 1 {
+				return None;
+			}
+			let note = Note {
+				accidental: Accidental::from_semitones(alteration).ok()?,
+				letter,
+			};
+			let signature = KeySignature::from_tonic(note, mode).ok()?;
+			Some((signature.fifths().abs(), note))
+		})
+		.min_by_key(|(fifths, _)| *fifths)
+		.map_or(default, |(_, note)| note)
+}
+
+/// Order scales so the familiar ones win ties - major, then minor, then the other modes
+fn commonness(scale: Scale) -> u8 {
+	match scale {
+		Scale::Diatonic(Mode::Ionian) => 0,
+		Scale::Diatonic(Mode::Aeolian) => 1,
+		Scale::Diatonic(_) => 2,
+		Scale::Tetratonic => 3,
+		Scale::Chromatic => 4,
+	}
+}
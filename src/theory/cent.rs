@@ -6,6 +6,7 @@ use std::ops::Div;
 const SEMITONE_CENTS: Cents = Cents(100.0);
 
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cents(f64);
 
 impl From<f64> for Cents {
@@ -0,0 +1,361 @@
+//! A Chorale sets a soprano melody for four voices, soprano, alto, tenor and bass.
+//! Each note of the melody gets a chord of the key, with a major V in minor keys, and the voices
+//! move to it as smoothly as they can without crossing, leaving their ranges or moving in parallel
+//! fifths and octaves
+
+use super::{
+	chord::{Chord, ChordQuality, RomanNumeral},
+	interval::Interval,
+	key::Key,
+	melody::MelodyNote,
+	note::{Letter, Note},
+	piano_key::PianoKey,
+	scale::Scale,
+};
+use crate::error::Error;
+use std::fmt;
+
+/// The widest gap between neighbouring upper voices, an octave
+const UPPER_SPACING: i16 = 12;
+
+/// The widest gap between the tenor and the bass, a twelfth
+const LOWER_SPACING: i16 = 19;
+
+/// The four voices of a chorale, from the top down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Voice {
+	Soprano,
+	Alto,
+	Tenor,
+	Bass,
+}
+
+impl Voice {
+	#[must_use]
+	pub fn all() -> [Self; 4] {
+		[Voice::Soprano, Voice::Alto, Voice::Tenor, Voice::Bass]
+	}
+
+	/// Get the lowest and highest keys the voice sings comfortably
+	#[must_use]
+	pub fn range(self) -> (PianoKey, PianoKey) {
+		let key = |letter, octave| PianoKey {
+			note: Note {
+				letter,
+				accidental: None,
+			},
+			octave,
+		};
+		match self {
+			Voice::Soprano => (key(Letter::C, 4), key(Letter::G, 5)),
+			Voice::Alto => (key(Letter::G, 3), key(Letter::D, 5)),
+			Voice::Tenor => (key(Letter::C, 3), key(Letter::G, 4)),
+			Voice::Bass => (key(Letter::E, 2), key(Letter::C, 4)),
+		}
+	}
+
+	/// Get the range in semitones above C0
+	fn semitones(self) -> (i16, i16) {
+		let (low, high) = self.range();
+		(low.semitones_from_c0(), high.semitones_from_c0())
+	}
+}
+
+impl fmt::Display for Voice {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let name = match self {
+			Voice::Soprano => "Soprano",
+			Voice::Alto => "Alto",
+			Voice::Tenor => "Tenor",
+			Voice::Bass => "Bass",
+		};
+		write!(f, "{name}")
+	}
+}
+
+/// A Voicing is one chord of a chorale, spread over the four voices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Voicing {
+	pub numeral: RomanNumeral,
+	pub chord: Chord,
+	/// The keys of each voice, soprano first
+	pub keys: [PianoKey; 4],
+	/// How long the chord lasts, in steps
+	pub steps: u16,
+}
+
+impl Voicing {
+	/// Get the key a voice sings
+	#[must_use]
+	pub fn key(&self, voice: Voice) -> PianoKey {
+		self.keys[voice as usize]
+	}
+
+	/// Check whether the lowest note is the root of the chord
+	#[must_use]
+	pub fn is_root_position(&self) -> bool {
+		self.key(Voice::Bass).note.pitch_class() == self.chord.root.pitch_class()
+	}
+
+	fn semitones(&self) -> [i16; 4] {
+		self.keys.map(PianoKey::semitones_from_c0)
+	}
+}
+
+/// A Chorale is a harmonized melody, one voicing per note of the melody
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Chorale(Vec<Voicing>);
+
+impl Chorale {
+	/// Harmonize a soprano melody in a diatonic key
+	pub fn harmonize(melody: &[MelodyNote], key: Key) -> Result<Self, Error> {
+		let Scale::Diatonic(_) = key.scale else {
+			return Err(Error::NotDiatonic(key.scale));
+		};
+		if melody.is_empty() {
+			return Err(Error::EmptyMelody);
+		}
+		let last = melody.len() - 1;
+		// Every way to voice every note, with the cheapest way to reach it and where that came from
+		let mut layers: Vec<Vec<(Voicing, u32, usize)>> = Vec::new();
+		for (i, note) in melody.iter().enumerate() {
+			let mut layer = Vec::new();
+			for voicing in voicings(*note, key) {
+				let cost = static_cost(&voicing, key, i == 0, i == last);
+				let best = match layers.last() {
+					None => Some((cost, 0)),
+					Some(previous) => previous
+						.iter()
+						.enumerate()
+						.filter_map(|(p, (from, total, _))| {
+							transition_cost(from, &voicing).map(|t| (total + t + cost, p))
+						})
+						.min(),
+				};
+				if let Some((total, back)) = best {
+					layer.push((voicing, total, back));
+				}
+			}
+			if layer.is_empty() {
+				return Err(Error::NoHarmony {
+					note: i + 1,
+					key: note.key,
+					tonic: key.base_note.note,
+					scale: key.scale,
+				});
+			}
+			layers.push(layer);
+		}
+		// Walk back from the cheapest final chord
+		let mut ret = Vec::new();
+		let mut index = layers
+			.last()
+			.and_then(|layer| (0..layer.len()).min_by_key(|&i| layer[i].1));
+		for layer in layers.iter().rev() {
+			let Some(i) = index else {
+				break;
+			};
+			ret.push(layer[i].0);
+			index = Some(layer[i].2);
+		}
+		ret.reverse();
+		Ok(Self(ret))
+	}
+
+	#[must_use]
+	pub fn voicings(&self) -> &[Voicing] {
+		&self.0
+	}
+
+	/// Get the line a single voice sings
+	#[must_use]
+	pub fn voice(&self, voice: Voice) -> Vec<MelodyNote> {
+		self.0
+			.iter()
+			.map(|v| MelodyNote::new(v.key(voice), v.steps))
+			.collect()
+	}
+
+	/// Get the lines of every voice, soprano first
+	#[must_use]
+	pub fn voices(&self) -> Vec<Vec<MelodyNote>> {
+		Voice::all().iter().map(|&v| self.voice(v)).collect()
+	}
+}
+
+impl fmt::Display for Chorale {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:<9}", "")?;
+		for voicing in &self.0 {
+			write!(f, "{:<6}", voicing.numeral.to_string())?;
+		}
+		writeln!(f)?;
+		for voice in Voice::all() {
+			write!(f, "{:<9}", voice.to_string())?;
+			for voicing in &self.0 {
+				write!(f, "{:<6}", voicing.key(voice).to_string())?;
+			}
+			writeln!(f)?;
+		}
+		Ok(())
+	}
+}
+
+/// List every triad of the key, taking V and vii° from the harmonic minor in minor keys
+/// while keeping the natural VII, each with its Roman numeral
+fn triads(key: Key) -> Vec<(RomanNumeral, Chord)> {
+	let notes = key.get_notes();
+	let mut ret = Vec::new();
+	for degree in 0..7u8 {
+		let harmonic = Chord::harmonic(key, usize::from(degree), false);
+		let natural = Chord::diatonic(key, usize::from(degree), false)
+			.filter(|&chord| degree == 6 && Some(chord) != harmonic);
+		for chord in [harmonic, natural].into_iter().flatten() {
+			let numeral = RomanNumeral {
+				degree,
+				alteration: chord.root.semitones_from_c()
+					- notes[usize::from(degree)].semitones_from_c(),
+				quality: chord.quality,
+			};
+			ret.push((numeral, chord));
+		}
+	}
+	ret
+}
+
+/// List every acceptable voicing of every triad holding the soprano note
+fn voicings(soprano: MelodyNote, key: Key) -> Vec<Voicing> {
+	let mut ret = Vec::new();
+	let top = soprano.key.semitones_from_c0();
+	for (numeral, chord) in triads(key) {
+		if !chord.contains(soprano.key.note) {
+			continue;
+		}
+		let notes = chord.notes();
+		let tones = |voice: Voice, from: i16, to: i16| {
+			let (low, high) = voice.semitones();
+			(low.max(from)..=high.min(to))
+				.filter_map(|s| {
+					notes
+						.iter()
+						.find(|n| i16::from(n.pitch_class()) == s.rem_euclid(12))
+						.map(|&n| PianoKey::with_note(n, s))
+				})
+				.collect::<Vec<_>>()
+		};
+		// Root position, or first inversion with the third in the bass
+		for bass in tones(Voice::Bass, i16::MIN, top) {
+			if bass.note != notes[0] && bass.note != notes[1] {
+				continue;
+			}
+			let b = bass.semitones_from_c0();
+			for tenor in tones(Voice::Tenor, b, (b + LOWER_SPACING).min(top)) {
+				let t = tenor.semitones_from_c0();
+				for alto in tones(
+					Voice::Alto,
+					t.max(top - UPPER_SPACING),
+					(t + UPPER_SPACING).min(top),
+				) {
+					let keys = [soprano.key, alto, tenor, bass];
+					// Every chord needs its root and third
+					let complete = notes[..2].iter().all(|n| keys.iter().any(|k| k.note == *n));
+					if complete {
+						ret.push(Voicing {
+							numeral,
+							chord,
+							keys,
+							steps: soprano.steps,
+						});
+					}
+				}
+			}
+		}
+	}
+	ret
+}
+
+/// Score a voicing on its own: doubling, inversion, and where it falls in the phrase
+fn static_cost(voicing: &Voicing, key: Key, first: bool, last: bool) -> u32 {
+	let notes = voicing.chord.notes();
+	let count = |note: Note| {
+		let count = voicing.keys.iter().filter(|k| k.note == note).count();
+		u32::try_from(count).unwrap_or(u32::MAX)
+	};
+	let mut cost = 0;
+	// Double the root, never the leading tone, and try to keep the fifth
+	if count(notes[0]) < 2 {
+		cost += 2;
+	}
+	// The leading tone is a semitone below the tonic, raised in minor keys
+	let leading_tone = key.base_note.note + Interval::MAJ7;
+	if count(leading_tone) > 1 {
+		cost += 20;
+	}
+	if count(notes[2]) == 0 {
+		cost += 3;
+	}
+	if !voicing.is_root_position() {
+		cost += 2;
+	}
+	// Diminished triads only sound in first inversion
+	if voicing.chord.quality == ChordQuality::Diminished && voicing.is_root_position() {
+		cost += 20;
+	}
+	let tonic = voicing.numeral.degree == 0;
+	if (first || last) && !(tonic && voicing.is_root_position()) {
+		cost += 10;
+	}
+	cost
+}
+
+/// Score moving from one voicing to the next, or rule it out with parallel fifths or octaves
+fn transition_cost(from: &Voicing, to: &Voicing) -> Option<u32> {
+	let a = from.semitones();
+	let b = to.semitones();
+	for upper in 0..4 {
+		for lower in upper + 1..4 {
+			let before = (a[upper] - a[lower]) % 12;
+			let after = (b[upper] - b[lower]) % 12;
+			let moved = a[upper] != b[upper] && a[lower] != b[lower];
+			if moved && before == after && matches!(before, 0 | 7) {
+				return None;
+			}
+		}
+	}
+	let mut cost = 0;
+	for voice in 1..4 {
+		let distance = u32::from((b[voice] - a[voice]).unsigned_abs());
+		// The bass is free to leap, the inner voices should hold still or step
+		cost += if voice == 3 { distance / 2 } else { distance };
+		if voice < 3 && distance > 4 {
+			cost += 3;
+		}
+		// A voice should not move past where its neighbour just was
+		if b[voice] > a[voice - 1] || (voice > 1 && b[voice - 1] < a[voice]) {
+			cost += 4;
+		}
+	}
+	cost += progression_cost(from.numeral.degree, to.numeral.degree);
+	Some(cost)
+}
+
+/// Score the step from one chord to the next by function: tonic chords lead anywhere,
+/// predominants move on to dominants, and dominants return to the tonic
+fn progression_cost(from: u8, to: u8) -> u32 {
+	// Tonic, predominant and dominant, by degree
+	let function = |degree: u8| match degree {
+		0 | 2 | 5 => 0,
+		1 | 3 => 1,
+		_ => 2,
+	};
+	if from == to {
+		return 2;
+	}
+	match (function(from), function(to)) {
+		(2, 0) if to == 0 || to == 5 => 0,
+		(0, _) | (1, 1 | 2) => 1,
+		(2, 0) => 3,
+		(2, 2) => 2,
+		_ => 6,
+	}
+}
@@ -0,0 +1,373 @@
+//! A Chord is a root note with a quality that stacks thirds above it.
+//! Roman numerals name chords by the degree of a key they are built on, so a progression can be played in any key
+
+use super::{
+	interval::{Interval, Quality},
+	key::Key,
+	note::{Accidental, Note},
+	scale::{Mode, Scale},
+};
+use crate::error::Error;
+use std::{fmt, str::FromStr};
+
+/// A `ChordQuality` is the set of intervals a chord stacks above its root
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChordQuality {
+	Major,
+	Minor,
+	Diminished,
+	Augmented,
+	Dominant7,
+	Major7,
+	Minor7,
+	HalfDiminished7,
+	Diminished7,
+}
+
+impl ChordQuality {
+	/// Every quality, triads first
+	#[must_use]
+	pub fn all() -> Vec<Self> {
+		use ChordQuality::{
+			Augmented, Diminished, Diminished7, Dominant7, HalfDiminished7, Major, Major7, Minor,
+			Minor7,
+		};
+		vec![
+			Major,
+			Minor,
+			Diminished,
+			Augmented,
+			Dominant7,
+			Major7,
+			Minor7,
+			HalfDiminished7,
+			Diminished7,
+		]
+	}
+
+	/// Get the intervals above the root, lowest first
+	#[must_use]
+	pub fn intervals(self) -> Vec<Interval> {
+		use ChordQuality::{
+			Augmented, Diminished, Diminished7, Dominant7, HalfDiminished7, Major, Major7, Minor,
+			Minor7,
+		};
+		use Interval as I;
+		match self {
+			Major => vec![I::MAJ3, I::PERFECT5],
+			Minor => vec![I::MIN3, I::PERFECT5],
+			Diminished => vec![I::MIN3, I::DIM5],
+			Augmented => vec![I::MAJ3, I::AUG5],
+			Dominant7 => vec![I::MAJ3, I::PERFECT5, I::MIN7],
+			Major7 => vec![I::MAJ3, I::PERFECT5, I::MAJ7],
+			Minor7 => vec![I::MIN3, I::PERFECT5, I::MIN7],
+			HalfDiminished7 => vec![I::MIN3, I::DIM5, I::MIN7],
+			Diminished7 => vec![I::MIN3, I::DIM5, I::DIM7],
+		}
+	}
+
+	/// Check whether the chord has a seventh above its triad
+	#[must_use]
+	pub fn is_seventh(self) -> bool {
+		self.intervals().len() == 3
+	}
+
+	/// Find the quality built from these intervals above the root
+	#[must_use]
+	pub fn from_intervals(intervals: &[Interval]) -> Option<Self> {
+		Self::all().into_iter().find(|q| q.intervals() == intervals)
+	}
+
+	/// Get the suffix written after a chord's root, like "m7"
+	fn symbol(self) -> &'static str {
+		use ChordQuality::{
+			Augmented, Diminished, Diminished7, Dominant7, HalfDiminished7, Major, Major7, Minor,
+			Minor7,
+		};
+		match self {
+			Major => "",
+			Minor => "m",
+			Diminished => "dim",
+			Augmented => "aug",
+			Dominant7 => "7",
+			Major7 => "maj7",
+			Minor7 => "m7",
+			HalfDiminished7 => "m7b5",
+			Diminished7 => "dim7",
+		}
+	}
+}
+
+impl FromStr for ChordQuality {
+	type Err = Error;
+
+	/// Accepts the suffix of a chord symbol, like "", "m", "7", "maj7", "dim" or "ø7"
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		use ChordQuality::{
+			Augmented, Diminished, Diminished7, Dominant7, HalfDiminished7, Major, Major7, Minor,
+			Minor7,
+		};
+		match s {
+			"" | "maj" | "M" => Ok(Major),
+			"m" | "min" | "-" => Ok(Minor),
+			"dim" | "o" | "°" => Ok(Diminished),
+			"aug" | "+" => Ok(Augmented),
+			"7" | "dom7" => Ok(Dominant7),
+			"maj7" | "M7" | "Δ7" | "Δ" => Ok(Major7),
+			"m7" | "min7" | "-7" => Ok(Minor7),
+			"m7b5" | "ø" | "ø7" => Ok(HalfDiminished7),
+			"dim7" | "o7" | "°7" => Ok(Diminished7),
+			_ => Err(Error::BadChordQuality(s.to_string())),
+		}
+	}
+}
+
+/// A Chord is spelled from its root, so the notes of F# major and G♭ major differ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chord {
+	pub root: Note,
+	pub quality: ChordQuality,
+}
+
+impl Chord {
+	#[must_use]
+	pub fn new(root: Note, quality: ChordQuality) -> Self {
+		Self { root, quality }
+	}
+
+	/// Build the triad or seventh chord on a degree of a key, counting from 0 for the base note,
+	/// by stacking the key's own notes in thirds.
+	/// Scales that are not diatonic, and stacks that match no known quality, have no chord
+	#[must_use]
+	pub fn diatonic(key: Key, degree: usize, seventh: bool) -> Option<Self> {
+		let Scale::Diatonic(_) = key.scale else {
+			return None;
+		};
+		let notes = key.get_notes();
+		Self::stack(&notes[..notes.len() - 1], degree, seventh)
+	}
+
+	/// Build the chord on a degree of a key the way harmony treats it.
+	/// In minor keys the dominant and leading-tone chords take the raised seventh of the harmonic minor,
+	/// so V is major and vii° diminished; every other chord is the same as `diatonic`
+	#[must_use]
+	pub fn harmonic(key: Key, degree: usize, seventh: bool) -> Option<Self> {
+		let Scale::Diatonic(mode) = key.scale else {
+			return None;
+		};
+		let mut notes = key.get_notes();
+		notes.pop();
+		if mode == Mode::Aeolian && matches!(degree % 7, 4 | 6) {
+			notes[6] += Interval::new(Quality::Augmented, 1).ok()?;
+		}
+		Self::stack(&notes, degree, seventh)
+	}
+
+	/// Stack the notes of a scale in thirds from a degree
+	fn stack(notes: &[Note], degree: usize, seventh: bool) -> Option<Self> {
+		let root = notes[degree % notes.len()];
+		let tones = if seventh { 3 } else { 2 };
+		let intervals = (1..=tones)
+			.map(|i| notes[(degree + 2 * i) % notes.len()].get_offset(root))
+			.collect::<Vec<_>>();
+		Some(Self::new(root, ChordQuality::from_intervals(&intervals)?))
+	}
+
+	/// Get the notes of the chord from the root up
+	#[must_use]
+	pub fn notes(self) -> Vec<Note> {
+		let mut ret = vec![self.root];
+		ret.extend(self.quality.intervals().into_iter().map(|i| self.root + i));
+		ret
+	}
+
+	/// Check whether a note belongs to the chord, in any spelling
+	#[must_use]
+	pub fn contains(self, note: Note) -> bool {
+		self.notes()
+			.iter()
+			.any(|n| n.pitch_class() == note.pitch_class())
+	}
+}
+
+impl fmt::Display for Chord {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}{}", self.root, self.quality.symbol())
+	}
+}
+
+impl FromStr for Chord {
+	type Err = Error;
+
+	/// Accepts chord symbols like "C", "F#m", "Bb7", "Ebmaj7" or "Bm7b5"
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+		// Try the longest root first, so the flat of "Bbm" belongs to the root
+		let splits = s
+			.char_indices()
+			.map(|(i, _)| i)
+			.skip(1)
+			.chain([s.len()])
+			.take(3)
+			.collect::<Vec<_>>();
+		for split in splits.into_iter().rev() {
+			let (root, quality) = s.split_at(split);
+			if let (Ok(root), Ok(quality)) = (Note::from_str(root), ChordQuality::from_str(quality))
+			{
+				return Ok(Self::new(root, quality));
+			}
+		}
+		Err(Error::BadChordSymbol(s.to_string()))
+	}
+}
+
+/// The numerals for each degree of a diatonic key
+const NUMERALS: [&str; 7] = ["I", "II", "III", "IV", "V", "VI", "VII"];
+
+/// A `RomanNumeral` names a chord by the degree of the key its root sits on.
+/// Upper case numerals are major and lower case are minor, like "IV" and "ii"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RomanNumeral {
+	/// The degree of the key, counting from 0 for the base note
+	pub degree: u8,
+	/// Semitones to raise or lower the root from the key's note, like the flat of "♭VII"
+	pub alteration: i8,
+	pub quality: ChordQuality,
+}
+
+impl RomanNumeral {
+	/// Spell the chord in a diatonic key
+	pub fn chord(self, key: Key) -> Result<Chord, Error> {
+		let Scale::Diatonic(_) = key.scale else {
+			return Err(Error::NotDiatonic(key.scale));
+		};
+		let mut root = key.get_notes()[usize::from(self.degree % 7)];
+		let semitone = Interval::new(Quality::Augmented, 1)?;
+		for _ in 0..self.alteration.abs() {
+			root = if self.alteration > 0 {
+				root + semitone
+			} else {
+				root + semitone.invert()
+			};
+		}
+		Ok(Chord::new(root, self.quality))
+	}
+}
+
+impl fmt::Display for RomanNumeral {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use ChordQuality::{
+			Augmented, Diminished, Diminished7, Dominant7, HalfDiminished7, Major, Major7, Minor,
+			Minor7,
+		};
+		let accidental = match self.alteration {
+			0 => String::new(),
+			a => Accidental::from_semitones(a)
+				.ok()
+				.flatten()
+				.map_or_else(String::new, |a| a.to_string()),
+		};
+		let numeral = NUMERALS[usize::from(self.degree % 7)];
+		let (upper, suffix) = match self.quality {
+			Major => (true, ""),
+			Minor => (false, ""),
+			Diminished => (false, "o"),
+			Augmented => (true, "+"),
+			Dominant7 => (true, "7"),
+			Major7 => (true, "maj7"),
+			Minor7 => (false, "7"),
+			HalfDiminished7 => (false, "ø7"),
+			Diminished7 => (false, "o7"),
+		};
+		let numeral = if upper {
+			numeral.to_string()
+		} else {
+			numeral.to_lowercase()
+		};
+		write!(f, "{accidental}{numeral}{suffix}")
+	}
+}
+
+impl FromStr for RomanNumeral {
+	type Err = Error;
+
+	/// Accepts numerals like "I", "ii", "V7", "viio", "iiø7" or "bVII", with "maj7" marking a major seventh
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		use ChordQuality::{
+			Augmented, Diminished, Diminished7, Dominant7, HalfDiminished7, Major, Major7, Minor,
+			Minor7,
+		};
+		let invalid = || Error::BadNumeral(s.to_string());
+		let s = s.trim();
+		let (alteration, rest) = if let Some(rest) = s.strip_prefix(['b', '♭']) {
+			(-1, rest)
+		} else if let Some(rest) = s.strip_prefix('#') {
+			(1, rest)
+		} else {
+			(0, s)
+		};
+		// Find the longest numeral, so "VII" is not read as "V"
+		let (degree, numeral) = NUMERALS
+			.iter()
+			.enumerate()
+			.filter(|(_, n)| {
+				rest.get(..n.len())
+					.is_some_and(|r| r == **n || r == n.to_lowercase())
+			})
+			.max_by_key(|(_, n)| n.len())
+			.ok_or_else(invalid)?;
+		let upper = rest.starts_with(|c: char| c.is_ascii_uppercase());
+		let quality = match (upper, &rest[numeral.len()..]) {
+			(true, "") => Major,
+			(false, "") => Minor,
+			(_, "o" | "°") => Diminished,
+			(_, "+") => Augmented,
+			(true, "7") => Dominant7,
+			(true, "maj7" | "M7" | "Δ7") => Major7,
+			(false, "7") => Minor7,
+			(_, "ø" | "ø7") => HalfDiminished7,
+			(_, "o7" | "°7") => Diminished7,
+			_ => return Err(invalid()),
+		};
+		Ok(Self {
+			degree: u8::try_from(degree).map_err(|_| invalid())?,
+			alteration,
+			quality,
+		})
+	}
+}
+
+/// A Progression is a series of chords named by Roman numerals, so it can be played in any key
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Progression(pub Vec<RomanNumeral>);
+
+impl Progression {
+	/// Spell every chord in a diatonic key
+	pub fn chords(&self, key: Key) -> Result<Vec<Chord>, Error> {
+		self.0.iter().map(|n| n.chord(key)).collect()
+	}
+}
+
+impl fmt::Display for Progression {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let numerals = self.0.iter().map(ToString::to_string).collect::<Vec<_>>();
+		write!(f, "{}", numerals.join(" "))
+	}
+}
+
+impl FromStr for Progression {
+	type Err = Error;
+
+	/// Accepts Roman numerals separated by spaces, commas or dashes, like "I vi IV V" or "ii-V-I"
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let numerals = s
+			.split(|c: char| c == ',' || c == '-' || c.is_whitespace())
+			.filter(|t| !t.is_empty())
+			.map(RomanNumeral::from_str)
+			.collect::<Result<Vec<_>, _>>()?;
+		if numerals.is_empty() {
+			return Err(Error::EmptyProgression);
+		}
+		Ok(Self(numerals))
+	}
+}
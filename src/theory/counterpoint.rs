@@ -0,0 +1,598 @@
+//! Counterpoint writes a second line against a cantus firmus by the rules of species counterpoint,
+//! and checks lines written by hand against the same rules.
+//! Each note of the cantus fills a bar of sixteen steps
+
+use super::{
+	interval::{Interval, Quality},
+	key::Key,
+	melody::{MelodyNote, STEPS_PER_BEAT},
+	piano_key::PianoKey,
+};
+use crate::error::Error;
+use rand::{seq::SliceRandom, Rng};
+use std::{fmt, str::FromStr};
+
+/// The number of steps in a bar, the length of one note of the cantus
+const BAR: u16 = 4 * STEPS_PER_BEAT;
+
+/// How many times to start the search afresh before giving up
+const ATTEMPTS: usize = 20;
+
+/// How many notes to try in a single search before starting again
+const SEARCH_BUDGET: usize = 5000;
+
+/// The widest the two voices may be apart, a twelfth
+const MAX_DISTANCE: i16 = 19;
+
+/// The widest the counterpoint may stray from the cantus while searching, a tenth
+const SEARCH_DISTANCE: i16 = 16;
+
+/// A Species sets the rhythm of the counterpoint against the whole notes of the cantus
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Species {
+	/// Note against note
+	#[default]
+	First,
+	/// Two half notes to each note of the cantus
+	Second,
+	/// Four quarter notes to each note of the cantus
+	Third,
+	/// Half notes tied over the bar line, forming suspensions
+	Fourth,
+	/// Florid counterpoint, mixing the rhythms of the other species
+	Fifth,
+}
+
+impl Species {
+	#[must_use]
+	pub fn all() -> Vec<Self> {
+		vec![
+			Species::First,
+			Species::Second,
+			Species::Third,
+			Species::Fourth,
+			Species::Fifth,
+		]
+	}
+}
+
+impl fmt::Display for Species {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let name = match self {
+			Species::First => "first",
+			Species::Second => "second",
+			Species::Third => "third",
+			Species::Fourth => "fourth",
+			Species::Fifth => "fifth",
+		};
+		write!(f, "{name} species")
+	}
+}
+
+impl FromStr for Species {
+	type Err = Error;
+
+	/// Accepts a number from 1 to 5 or its name, like "3" or "third"
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim().to_lowercase().trim_end_matches(" species") {
+			"1" | "first" => Ok(Species::First),
+			"2" | "second" => Ok(Species::Second),
+			"3" | "third" => Ok(Species::Third),
+			"4" | "fourth" => Ok(Species::Fourth),
+			"5" | "fifth" | "florid" => Ok(Species::Fifth),
+			_ => Err(Error::BadSpecies(s.to_string())),
+		}
+	}
+}
+
+/// Whether the counterpoint sits above or below the cantus
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Placement {
+	#[default]
+	Above,
+	Below,
+}
+
+impl fmt::Display for Placement {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Placement::Above => write!(f, "above"),
+			Placement::Below => write!(f, "below"),
+		}
+	}
+}
+
+impl FromStr for Placement {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim().to_lowercase().as_str() {
+			"above" | "up" => Ok(Placement::Above),
+			"below" | "down" => Ok(Placement::Below),
+			_ => Err(Error::BadPlacement(s.to_string())),
+		}
+	}
+}
+
+/// A Rule of counterpoint that a line can break
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+	/// Two fifths in a row, with both voices moving the same way
+	ParallelFifths,
+	/// Two octaves or unisons in a row, with both voices moving the same way
+	ParallelOctaves,
+	/// A dissonance on the beat, or off the beat without stepping in and out of it
+	Dissonance,
+	/// A suspension that is not prepared by a consonance, or does not resolve down by step
+	Suspension,
+	/// The first interval is not a perfect consonance
+	Opening,
+	/// The last interval is not a unison or octave reached by step
+	Cadence,
+	/// The counterpoint crosses to the other side of the cantus
+	Crossing,
+	/// The voices are more than a twelfth apart
+	Range,
+	/// A melodic seventh, a leap beyond an octave, or an augmented or diminished interval
+	Leap,
+	/// A leap larger than a fourth that is not followed by a step back the other way
+	UnrecoveredLeap,
+	/// The counterpoint does not last exactly as long as the cantus
+	Length,
+}
+
+impl fmt::Display for Rule {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match self {
+			Rule::ParallelFifths => "parallel fifths",
+			Rule::ParallelOctaves => "parallel octaves",
+			Rule::Dissonance => "dissonance not on a passing or neighbour tone",
+			Rule::Suspension => "suspension not prepared or not resolved down by step",
+			Rule::Opening => "does not open on a perfect consonance",
+			Rule::Cadence => "does not close on a unison or octave reached by step",
+			Rule::Crossing => "voices cross",
+			Rule::Range => "voices more than a twelfth apart",
+			Rule::Leap => "awkward leap",
+			Rule::UnrecoveredLeap => "leap not recovered by a step the other way",
+			Rule::Length => "does not match the length of the cantus",
+		};
+		write!(f, "{description}")
+	}
+}
+
+/// A Violation is a broken rule, found in a bar counted from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Violation {
+	pub bar: usize,
+	pub rule: Rule,
+}
+
+impl fmt::Display for Violation {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "Bar {}: {}", self.bar, self.rule)
+	}
+}
+
+/// An Exercise pairs a cantus firmus with the species and placement of the counterpoint to write against it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Exercise {
+	cantus: Vec<PianoKey>,
+	species: Species,
+	placement: Placement,
+}
+
+impl Exercise {
+	/// Set up an exercise on a cantus of at least three notes
+	pub fn new(
+		cantus: Vec<PianoKey>,
+		species: Species,
+		placement: Placement,
+	) -> Result<Self, Error> {
+		if cantus.len() < 3 {
+			return Err(Error::ShortCantus(cantus.len()));
+		}
+		Ok(Self {
+			cantus,
+			species,
+			placement,
+		})
+	}
+
+	#[must_use]
+	pub fn cantus(&self) -> &[PianoKey] {
+		&self.cantus
+	}
+
+	#[must_use]
+	pub fn species(&self) -> Species {
+		self.species
+	}
+
+	#[must_use]
+	pub fn placement(&self) -> Placement {
+		self.placement
+	}
+
+	/// Get the cantus as a melody of whole notes
+	#[must_use]
+	pub fn cantus_melody(&self) -> Vec<MelodyNote> {
+		self.cantus
+			.iter()
+			.map(|&k| MelodyNote::new(k, BAR))
+			.collect()
+	}
+
+	/// Lay out the lengths of the counterpoint's notes for the species, closing on a whole note.
+	/// Only the fifth species needs the random number generator
+	pub fn rhythm(&self, rng: &mut impl Rng) -> Vec<u16> {
+		let bars = self.cantus.len() - 1;
+		let mut ret = match self.species {
+			Species::First => vec![BAR; bars],
+			Species::Second => vec![BAR / 2; 2 * bars],
+			Species::Third => vec![BAR / 4; 4 * bars],
+			Species::Fourth => {
+				let mut ret = vec![BAR / 2];
+				ret.extend(vec![BAR; bars - 1]);
+				ret.push(BAR / 2);
+				ret
+			},
+			Species::Fifth => florid(bar_start(bars), rng),
+		};
+		ret.push(BAR);
+		ret
+	}
+
+	/// Find every rule the counterpoint breaks, in order of the bars they are broken in
+	#[must_use]
+	pub fn check(&self, counterpoint: &[MelodyNote]) -> Vec<Violation> {
+		self.violations(counterpoint, true)
+	}
+
+	/// Write a counterpoint that breaks no rules, using the notes of a key.
+	/// Minor keys also get the raised leading tone before the final
+	pub fn generate(&self, key: Key, rng: &mut impl Rng) -> Result<Vec<MelodyNote>, Error> {
+		for _ in 0..ATTEMPTS {
+			let rhythm = self.rhythm(rng);
+			let mut line = Vec::new();
+			let mut budget = SEARCH_BUDGET;
+			if self.search(&rhythm, key, &mut line, &mut budget, rng) {
+				return Ok(line);
+			}
+		}
+		Err(Error::NoCounterpoint {
+			species: self.species,
+			placement: self.placement,
+		})
+	}
+
+	/// Depth-first search for the rest of the line, giving up once the budget of notes to try runs out
+	fn search(
+		&self,
+		rhythm: &[u16],
+		key: Key,
+		line: &mut Vec<MelodyNote>,
+		budget: &mut usize,
+		rng: &mut impl Rng,
+	) -> bool {
+		let Some(&steps) = rhythm.get(line.len()) else {
+			return true;
+		};
+		let start = line.iter().map(|n| n.steps).sum::<u16>();
+		for candidate in self.candidates(start, key, line.last().map(|n| n.key), rng) {
+			if *budget == 0 {
+				return false;
+			}
+			*budget -= 1;
+			line.push(MelodyNote::new(candidate, steps));
+			if self.violations(line, line.len() == rhythm.len()).is_empty()
+				&& self.search(rhythm, key, line, budget, rng)
+			{
+				return true;
+			}
+			line.pop();
+		}
+		false
+	}
+
+	/// List the notes worth trying at a point in time, on the right side of the cantus and within a tenth of it.
+	/// Steps mostly come before leaps
+	fn candidates(
+		&self,
+		start: u16,
+		key: Key,
+		previous: Option<PianoKey>,
+		rng: &mut impl Rng,
+	) -> Vec<PianoKey> {
+		let bar = usize::from(start / BAR);
+		let cantus = self.cantus[bar].semitones_from_c0();
+		let (low, high) = match self.placement {
+			Placement::Above => (cantus, cantus + SEARCH_DISTANCE),
+			Placement::Below => (cantus - SEARCH_DISTANCE, cantus),
+		};
+		let mut notes = key.get_notes();
+		if bar + 2 == self.cantus.len() {
+			let last = self.cantus[self.cantus.len() - 1];
+			notes.push(last.note + Interval::MAJ7);
+		}
+		let mut ret = (low..=high)
+			.filter_map(|semitones| {
+				notes
+					.iter()
+					.find(|n| i16::from(n.pitch_class()) == semitones.rem_euclid(12))
+					.map(|&n| PianoKey::with_note(n, semitones))
+			})
+			.filter(|k| previous.is_none_or(|p| distance(p, *k).abs() <= 12))
+			.map(|k| {
+				let leap = previous.is_some_and(|p| distance(p, k).abs() > 2);
+				(leap && rng.gen_bool(0.75), k)
+			})
+			.collect::<Vec<_>>();
+		ret.shuffle(rng);
+		ret.sort_by_key(|(late, _)| *late);
+		ret.into_iter().map(|(_, k)| k).collect()
+	}
+
+	/// Find the rules a line breaks. An incomplete line skips the rules that depend on notes still to come
+	fn violations(&self, line: &[MelodyNote], complete: bool) -> Vec<Violation> {
+		let starts = line
+			.iter()
+			.scan(0, |start, n| {
+				let ret = *start;
+				*start += n.steps;
+				Some(ret)
+			})
+			.collect::<Vec<u16>>();
+		// Every point where either voice moves, with the notes sounding together there
+		let mut verticals = Vec::new();
+		for (note, &start) in line.iter().zip(&starts) {
+			let until = start + note.steps;
+			let bar_lines = (usize::from(start / BAR) + 1..)
+				.map(bar_start)
+				.take_while(|&b| b < until);
+			for position in std::iter::once(start).chain(bar_lines) {
+				if let Some(cantus) = self.cantus_at(position) {
+					verticals.push((position, cantus, note.key));
+				}
+			}
+		}
+		let mut ret = self.vertical_violations(&verticals);
+		ret.extend(melodic_violations(line, &starts));
+		ret.extend(self.dissonance_violations(line, &starts, complete));
+		if complete {
+			let total = line.iter().map(|n| n.steps).sum::<u16>();
+			let end = bar_start(self.cantus.len());
+			if total != end {
+				ret.push(Violation::at(total.min(end - 1), Rule::Length));
+			}
+			if let (Some(last), Some(&cantus)) = (line.last(), self.cantus.last()) {
+				let stepped = line
+					.len()
+					.checked_sub(2)
+					.map(|p| distance(line[p].key, last.key).abs())
+					.is_some_and(|step| (1..=2).contains(&step));
+				if perfect(cantus, last.key) != Some(0) || !stepped {
+					ret.push(Violation::at(end - 1, Rule::Cadence));
+				}
+			}
+		}
+		ret.sort_by_key(|v| v.bar);
+		ret
+	}
+
+	/// Find the note of the cantus sounding at a position
+	fn cantus_at(&self, position: u16) -> Option<PianoKey> {
+		self.cantus.get(usize::from(position / BAR)).copied()
+	}
+
+	/// Check the intervals between the voices: crossings, range, the opening and parallels
+	fn vertical_violations(&self, verticals: &[Vertical]) -> Vec<Violation> {
+		let mut ret = Vec::new();
+		for &(position, cantus, key) in verticals {
+			let apart = distance(cantus, key);
+			let crossed = match self.placement {
+				Placement::Above => apart < 0,
+				Placement::Below => apart > 0,
+			};
+			if crossed {
+				ret.push(Violation::at(position, Rule::Crossing));
+			}
+			if apart.abs() > MAX_DISTANCE {
+				ret.push(Violation::at(position, Rule::Range));
+			}
+		}
+		if let Some(&(_, cantus, key)) = verticals.first() {
+			// A fifth below the cantus would put the music in the wrong key
+			let opening = matches!(
+				(self.placement, perfect(cantus, key)),
+				(Placement::Above, Some(_)) | (Placement::Below, Some(0))
+			);
+			if !opening {
+				ret.push(Violation::at(0, Rule::Opening));
+			}
+		}
+		for pair in verticals.windows(2) {
+			if let Some(rule) = parallel(pair[0], pair[1]) {
+				ret.push(Violation::at(pair[1].0, rule));
+			}
+		}
+		// Accented parallels between the downbeats of neighbouring bars
+		if matches!(self.species, Species::Second | Species::Third) {
+			let downbeats = verticals
+				.iter()
+				.filter(|(position, ..)| position % BAR == 0)
+				.collect::<Vec<_>>();
+			for pair in downbeats.windows(2) {
+				// Downbeats with nothing between them were already compared above
+				let apart = verticals.iter().any(|v| v.0 > pair[0].0 && v.0 < pair[1].0);
+				if let Some(rule) = parallel(*pair[0], *pair[1]).filter(|_| apart) {
+					ret.push(Violation::at(pair[1].0, rule));
+				}
+			}
+		}
+		ret
+	}
+
+	/// Check that dissonances are passing or neighbour tones, and that suspensions are prepared and resolved
+	fn dissonance_violations(
+		&self,
+		line: &[MelodyNote],
+		starts: &[u16],
+		complete: bool,
+	) -> Vec<Violation> {
+		let mut ret = Vec::new();
+		for (i, (note, &start)) in line.iter().zip(starts).enumerate() {
+			let previous = i.checked_sub(1).map(|p| line[p].key);
+			let next = line.get(i + 1).map(|n| n.key);
+			let Some(cantus) = self.cantus_at(start) else {
+				continue;
+			};
+			if !consonant(cantus, note.key) {
+				if start % BAR == 0 || self.species == Species::Fourth {
+					ret.push(Violation::at(start, Rule::Dissonance));
+				} else if let (Some(previous), Some(next)) = (previous, next) {
+					let into = distance(previous, note.key);
+					let out_of = distance(note.key, next);
+					let steps = (1..=2).contains(&into.abs()) && (1..=2).contains(&out_of.abs());
+					let passing = into.signum() == out_of.signum();
+					let neighbours_allowed =
+						matches!(self.species, Species::Third | Species::Fifth);
+					if !(steps && (passing || neighbours_allowed)) {
+						ret.push(Violation::at(start, Rule::Dissonance));
+					}
+				} else if complete {
+					ret.push(Violation::at(start, Rule::Dissonance));
+				}
+			}
+			// A note struck off the beat and held over the bar line into a dissonance is a suspension
+			let bar_line = bar_start(usize::from(start / BAR) + 1);
+			let held_over = self
+				.cantus_at(bar_line)
+				.filter(|_| start % BAR != 0 && start + note.steps > bar_line);
+			if held_over.is_none_or(|c| consonant(c, note.key)) {
+				continue;
+			}
+			if !consonant(cantus, note.key) {
+				ret.push(Violation::at(bar_line, Rule::Suspension));
+			}
+			let resolves = next.map(|next| {
+				(-2..=-1).contains(&distance(note.key, next))
+					&& self
+						.cantus_at(start + note.steps)
+						.is_some_and(|c| consonant(c, next))
+			});
+			if resolves == Some(false) || (resolves.is_none() && complete) {
+				ret.push(Violation::at(bar_line, Rule::Suspension));
+			}
+		}
+		ret
+	}
+}
+
+/// A moment where the voices sound together: the position, the cantus and the counterpoint
+type Vertical = (u16, PianoKey, PianoKey);
+
+impl Violation {
+	/// Record a rule broken at a position in steps
+	fn at(position: u16, rule: Rule) -> Self {
+		Self {
+			bar: usize::from(position / BAR) + 1,
+			rule,
+		}
+	}
+}
+
+/// Check the leaps of the line on its own
+fn melodic_violations(line: &[MelodyNote], starts: &[u16]) -> Vec<Violation> {
+	let mut ret = Vec::new();
+	for (i, pair) in line.windows(2).enumerate() {
+		let (previous, note) = (pair[0].key, pair[1].key);
+		let start = starts[i + 1];
+		let leap = distance(previous, note);
+		let augmented = leap != 0
+			&& matches!(
+				note.note.get_offset(previous.note).quality(),
+				Quality::Augmented
+					| Quality::Diminished
+					| Quality::DoublyAugmented
+					| Quality::DoublyDiminished
+			);
+		if augmented || matches!(leap.abs(), 10 | 11) || leap.abs() > 12 {
+			ret.push(Violation::at(start, Rule::Leap));
+		}
+		if let Some(next) = line.get(i + 2) {
+			let recovery = distance(note, next.key);
+			if leap.abs() > 5 && !(recovery.signum() == -leap.signum() && recovery.abs() <= 2) {
+				ret.push(Violation::at(start, Rule::UnrecoveredLeap));
+			}
+		}
+	}
+	ret
+}
+
+/// Get the position in steps where a bar starts
+fn bar_start(bar: usize) -> u16 {
+	u16::try_from(bar).unwrap_or(u16::MAX).saturating_mul(BAR)
+}
+
+/// Count the semitones from one key up to another, negative when the second is lower
+fn distance(from: PianoKey, to: PianoKey) -> i16 {
+	to.semitones_from_c0() - from.semitones_from_c0()
+}
+
+/// Thirds, sixths and perfect intervals are consonant. Fourths count as dissonant between two voices
+fn consonant(a: PianoKey, b: PianoKey) -> bool {
+	matches!(distance(a, b).rem_euclid(12), 0 | 3 | 4 | 7 | 8 | 9)
+}
+
+/// Find which perfect consonance two keys make, 0 for unisons and octaves or 7 for fifths
+fn perfect(a: PianoKey, b: PianoKey) -> Option<i16> {
+	Some(distance(a, b).abs() % 12).filter(|class| matches!(class, 0 | 7))
+}
+
+/// Spot parallel perfect intervals between two moments, with both voices moving the same way
+fn parallel(
+	(_, cantus_from, from): (u16, PianoKey, PianoKey),
+	(_, cantus_to, to): (u16, PianoKey, PianoKey),
+) -> Option<Rule> {
+	let class = perfect(cantus_from, from)?;
+	let moved = distance(from, to);
+	let cantus_moved = distance(cantus_from, cantus_to);
+	if perfect(cantus_to, to) != Some(class)
+		|| moved == 0
+		|| moved.signum() != cantus_moved.signum()
+	{
+		return None;
+	}
+	Some(if class == 7 {
+		Rule::ParallelFifths
+	} else {
+		Rule::ParallelOctaves
+	})
+}
+
+/// Lay out a florid rhythm up to a point: half and quarter notes, pairs of eighths on the off beats,
+/// and half notes tied over the bar line
+fn florid(until: u16, rng: &mut impl Rng) -> Vec<u16> {
+	let mut ret = Vec::new();
+	let mut position = 0;
+	while position < until {
+		let half = BAR / 2;
+		let quarter = BAR / 4;
+		let beat = position % BAR;
+		let choices = if beat == half && position + BAR <= until {
+			vec![half, quarter, BAR, BAR]
+		} else if beat.is_multiple_of(half) {
+			vec![half, quarter]
+		} else if beat.is_multiple_of(quarter) {
+			vec![quarter, quarter, quarter / 2]
+		} else {
+			vec![quarter / 2]
+		};
+		let steps = *choices
+			.choose(rng)
+			.expect("There are lengths to choose from");
+		ret.push(steps);
+		position += steps;
+	}
+	ret
+}
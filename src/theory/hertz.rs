@@ -1,9 +1,11 @@
 //! A Hertz measures the rate of a recurring phenomena in cycles per second
 //! This module also houses several standardized constants in Hertz
 
+use crate::error::Error;
 use std::ops::{MulAssign, Sub};
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Hertz(f64);
 
 impl Hertz {
@@ -34,16 +36,16 @@ impl From<f64> for Hertz {
 }
 
 impl TryFrom<Hertz> for u32 {
-	type Error = &'static str;
+	type Error = Error;
 	fn try_from(h: Hertz) -> Result<Self, Self::Error> {
 		// Catch sign loss.
 		if h.0 < 0.0 {
-			return Err("Cannot convert negative Hertz to u32");
+			return Err(Error::NegativeHertz);
 		}
 
 		// Catch non-integer Hertz.
 		if h.0.fract() != 0.0 {
-			return Err("Cannot convert non-integer Hertz to u32");
+			return Err(Error::FractionalHertz);
 		}
 
 		#[allow(clippy::cast_possible_truncation)]
@@ -1,48 +1,323 @@
-//! An Interval represents a ratio between two Notes
+//! An Interval represents a ratio between two Notes.
+//! It is identified by a number - the count of letter names it spans - and a quality, so an
+//! augmented fourth and a diminished fifth are distinct even though they span the same semitones
 
 use super::semitone::Semitones;
-use std::ops::{Add, AddAssign, Sub};
-
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
-pub enum Interval {
-	Unison = 0,
-	Min2,
-	Maj2,
-	Min3,
-	Maj3,
-	Perfect4,
-	Tritone,
-	Perfect5,
-	Min6,
-	Maj6,
-	Min7,
-	Maj7,
-	Octave,
+use crate::error::Error;
+use std::{
+	fmt,
+	ops::{Add, AddAssign, Sub},
+	str::FromStr,
+};
+
+/// The number of semitones spanned by each simple major or perfect interval, unison through seventh
+const NATURAL_SEMITONES: [i8; 7] = [0, 2, 4, 5, 7, 9, 11];
+
+/// The number of semitones in an octave
+const OCTAVE_SEMITONES: i8 = 12;
+
+/// The widest interval number, ten octaves and a fourth, so every quality of it fits in an i8 of semitones
+pub(crate) const MAX_NUMBER: u8 = 74;
+
+/// A Quality describes how an interval is altered from its major or perfect form
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Quality {
+	DoublyDiminished,
+	Diminished,
+	Minor,
+	Perfect,
+	Major,
+	Augmented,
+	DoublyAugmented,
+}
+
+impl Quality {
+	/// Get the semitone offset from the major or perfect form of an interval with this quality.
+	/// Returns `None` when the quality does not apply - e.g. a perfect third or a minor fifth
+	fn offset(self, perfect: bool) -> Option<i8> {
+		use Quality::{
+			Augmented, Diminished, DoublyAugmented, DoublyDiminished, Major, Minor, Perfect,
+		};
+		match (self, perfect) {
+			(DoublyDiminished, false) => Some(-3),
+			(DoublyDiminished, true) | (Diminished, false) => Some(-2),
+			(Diminished, true) | (Minor, false) => Some(-1),
+			(Perfect, true) | (Major, false) => Some(0),
+			(Augmented, _) => Some(1),
+			(DoublyAugmented, _) => Some(2),
+			(Minor, true) | (Perfect | Major, _) => None,
+		}
+	}
+
+	/// Find the quality for a semitone offset from the major or perfect form
+	fn from_offset(offset: i8, perfect: bool) -> Option<Self> {
+		use Quality::{
+			Augmented, Diminished, DoublyAugmented, DoublyDiminished, Major, Minor, Perfect,
+		};
+		match (offset, perfect) {
+			(-3, false) | (-2, true) => Some(DoublyDiminished),
+			(-2, false) | (-1, true) => Some(Diminished),
+			(-1, false) => Some(Minor),
+			(0, true) => Some(Perfect),
+			(0, false) => Some(Major),
+			(1, _) => Some(Augmented),
+			(2, _) => Some(DoublyAugmented),
+			_ => None,
+		}
+	}
+
+	/// Get the quality of the inverted interval
+	#[must_use]
+	pub fn invert(self) -> Self {
+		use Quality::{
+			Augmented, Diminished, DoublyAugmented, DoublyDiminished, Major, Minor, Perfect,
+		};
+		match self {
+			DoublyDiminished => DoublyAugmented,
+			Diminished => Augmented,
+			Minor => Major,
+			Perfect => Perfect,
+			Major => Minor,
+			Augmented => Diminished,
+			DoublyAugmented => DoublyDiminished,
+		}
+	}
+}
+
+impl fmt::Display for Quality {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use Quality::{
+			Augmented, Diminished, DoublyAugmented, DoublyDiminished, Major, Minor, Perfect,
+		};
+		let s = match self {
+			DoublyDiminished => "dd",
+			Diminished => "d",
+			Minor => "m",
+			Perfect => "P",
+			Major => "M",
+			Augmented => "A",
+			DoublyAugmented => "AA",
+		};
+		write!(f, "{s}")
+	}
+}
+
+impl FromStr for Quality {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		use Quality::{
+			Augmented, Diminished, DoublyAugmented, DoublyDiminished, Major, Minor, Perfect,
+		};
+		// The single-letter forms are case-sensitive, "M" and "m" mean different things
+		match s {
+			"dd" => return Ok(DoublyDiminished),
+			"d" => return Ok(Diminished),
+			"m" => return Ok(Minor),
+			"P" => return Ok(Perfect),
+			"M" => return Ok(Major),
+			"A" => return Ok(Augmented),
+			"AA" => return Ok(DoublyAugmented),
+			_ => {},
+		}
+		match s.to_lowercase().as_str() {
+			"dim" | "diminished" => Ok(Diminished),
+			"min" | "minor" => Ok(Minor),
+			"perf" | "perfect" => Ok(Perfect),
+			"maj" | "major" => Ok(Major),
+			"aug" | "augmented" => Ok(Augmented),
+			_ => Err(Error::BadQuality(s.to_string())),
+		}
+	}
+}
+
+/// An Interval spans a number of letter names - 1 for a unison, 8 for an octave and beyond for
+/// compound intervals - with a quality picking the exact semitone size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval {
+	number: u8,
+	quality: Quality,
+}
+
+impl Interval {
+	pub const UNISON: Self = Self::simple_const(1, Quality::Perfect);
+	pub const MIN2: Self = Self::simple_const(2, Quality::Minor);
+	pub const MAJ2: Self = Self::simple_const(2, Quality::Major);
+	pub const MIN3: Self = Self::simple_const(3, Quality::Minor);
+	pub const MAJ3: Self = Self::simple_const(3, Quality::Major);
+	pub const PERFECT4: Self = Self::simple_const(4, Quality::Perfect);
+	/// The tritone is spelled as an augmented fourth
+	pub const TRITONE: Self = Self::simple_const(4, Quality::Augmented);
+	pub const DIM5: Self = Self::simple_const(5, Quality::Diminished);
+	pub const PERFECT5: Self = Self::simple_const(5, Quality::Perfect);
+	pub const AUG5: Self = Self::simple_const(5, Quality::Augmented);
+	pub const MIN6: Self = Self::simple_const(6, Quality::Minor);
+	pub const MAJ6: Self = Self::simple_const(6, Quality::Major);
+	pub const DIM7: Self = Self::simple_const(7, Quality::Diminished);
+	pub const MIN7: Self = Self::simple_const(7, Quality::Minor);
+	pub const MAJ7: Self = Self::simple_const(7, Quality::Major);
+	pub const OCTAVE: Self = Self::simple_const(8, Quality::Perfect);
+
+	/// Only used for the constants above, which are known to be valid
+	const fn simple_const(number: u8, quality: Quality) -> Self {
+		Self { number, quality }
+	}
+
+	/// Build an interval, checking that the quality makes sense for the number.
+	pub fn new(quality: Quality, number: u8) -> Result<Self, Error> {
+		if number == 0 || number > MAX_NUMBER {
+			return Err(Error::IntervalNumberOutOfRange(number));
+		}
+		let ret = Self { number, quality };
+		if quality.offset(ret.is_perfect_class()).is_none() {
+			return Err(Error::ImpossibleInterval { quality, number });
+		}
+		Ok(ret)
+	}
+
+	/// Get the interval number - 1 for a unison, 3 for a third, 10 for a tenth
+	#[must_use]
+	pub fn number(self) -> u8 {
+		self.number
+	}
+
+	#[must_use]
+	pub fn quality(self) -> Quality {
+		self.quality
+	}
+
+	/// Unisons, fourths, fifths and their compounds take perfect qualities instead of major/minor
+	#[must_use]
+	pub fn is_perfect_class(self) -> bool {
+		matches!(self.degree(), 0 | 3 | 4)
+	}
+
+	/// Compound intervals span more than an octave
+	#[must_use]
+	pub fn is_compound(self) -> bool {
+		self.number > 8
+	}
+
+	/// Zero-based scale step within a single octave, 0 for unisons and octaves, 1 for seconds, etc.
+	fn degree(self) -> usize {
+		usize::from(self.number - 1) % 7
+	}
+
+	/// Get the number of whole octaves this interval spans beyond its simple form.
+	/// Octaves and double octaves reduce to an octave rather than a unison.
+	#[must_use]
+	pub fn octaves(self) -> u8 {
+		self.number.saturating_sub(2) / 7
+	}
+
+	/// Reduce a compound interval to its equivalent within an octave - a major ninth becomes a major second
+	#[must_use]
+	pub fn simple(self) -> Self {
+		Self {
+			number: self.number - 7 * self.octaves(),
+			quality: self.quality,
+		}
+	}
+
+	/// Invert the interval - a major third becomes a minor sixth.
+	/// Compound intervals are reduced to their simple form first
+	#[must_use]
+	pub fn invert(self) -> Self {
+		let simple = self.simple();
+		Self {
+			number: 9 - simple.number,
+			quality: simple.quality.invert(),
+		}
+	}
+
+	/// Build an interval from a number and an exact span in semitones, if such an interval exists.
+	/// Returns `None` when the semitones are more than doubly augmented or diminished from the number,
+	/// or the number is too wide.
+	#[must_use]
+	pub fn from_number_and_semitones(number: u8, semitones: i8) -> Option<Self> {
+		if number == 0 || number > MAX_NUMBER {
+			return None;
+		}
+		let degree = usize::from(number - 1) % 7;
+		let octaves = i8::try_from((number - 1) / 7).ok()?;
+		let natural = octaves
+			.checked_mul(OCTAVE_SEMITONES)?
+			.checked_add(NATURAL_SEMITONES[degree])?;
+		let perfect = matches!(degree, 0 | 3 | 4);
+		let quality = Quality::from_offset(semitones.checked_sub(natural)?, perfect)?;
+		Some(Self { number, quality })
+	}
+
+	/// Stack two intervals, or get `None` if the sum is too heavily altered to name or too wide
+	#[must_use]
+	pub fn checked_add(self, rhs: Self) -> Option<Self> {
+		let number = self.number.checked_add(rhs.number)? - 1;
+		let semitones = i8::from(self).checked_add(i8::from(rhs))?;
+		Self::from_number_and_semitones(number, semitones)
+	}
+
+	/// Find the interval left after removing `rhs`, wrapping up by an octave if `rhs` is larger,
+	/// or get `None` if it is too heavily altered to name
+	#[must_use]
+	pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+		let (number, semitones) = self.difference(rhs);
+		Self::from_number_and_semitones(u8::try_from(number).ok()?, i8::try_from(semitones).ok()?)
+	}
+
+	/// The number and semitones left after removing `rhs`, wrapped up into whole octaves until positive
+	fn difference(self, rhs: Self) -> (i16, i16) {
+		let mut number = i16::from(self.number) - i16::from(rhs.number) + 1;
+		let mut semitones = i16::from(i8::from(self)) - i16::from(i8::from(rhs));
+		while number < 1 {
+			number += 7;
+			semitones += i16::from(OCTAVE_SEMITONES);
+		}
+		(number, semitones)
+	}
+
+	/// Spell a span of semitones the usual way, clamped to what an interval can hold
+	fn respell(semitones: i16) -> Self {
+		let semitones =
+			i8::try_from(semitones).unwrap_or(if semitones < 0 { i8::MIN } else { i8::MAX });
+		Self::from(Semitones::from(semitones))
+	}
+}
+
+impl Default for Interval {
+	fn default() -> Self {
+		Self::UNISON
+	}
 }
 
 impl From<Semitones> for Interval {
+	/// Spell a semitone count with its most common name, using an augmented fourth for the tritone.
+	/// Counts past an octave become compound intervals, and descending counts are measured by size.
 	fn from(s: Semitones) -> Self {
-		use Interval::{
-			Maj2, Maj3, Maj6, Maj7, Min2, Min3, Min6, Min7, Octave, Perfect4, Perfect5, Tritone,
-			Unison,
-		};
-		let int_semitones = i8::from(s);
-		match int_semitones {
-			0 => Unison,
-			1 => Min2,
-			2 => Maj2,
-			3 => Min3,
-			4 => Maj3,
-			5 => Perfect4,
-			6 => Tritone,
-			7 => Perfect5,
-			8 => Min6,
-			9 => Maj6,
-			10 => Min7,
-			11 => Maj7,
-			// If 12 or higher, will wrap around - intervals are octave-agnostic
-			_ => Interval::from(Semitones::from(int_semitones % Octave as i8)),
+		// Numbers for each semitone count within an octave
+		const NUMBERS: [u8; 12] = [1, 2, 2, 3, 3, 4, 4, 5, 6, 6, 7, 7];
+		// The size of i8::MIN doesn't fit in an i8, so it comes out a semitone smaller
+		let int_semitones = i8::try_from(i8::from(s).unsigned_abs()).unwrap_or(i8::MAX);
+		if int_semitones == OCTAVE_SEMITONES {
+			return Self::OCTAVE;
 		}
+		let octaves = int_semitones / OCTAVE_SEMITONES;
+		let within = int_semitones % OCTAVE_SEMITONES;
+		#[allow(clippy::cast_sign_loss)]
+		let number = NUMBERS[within as usize] + 7 * octaves as u8;
+		// The widest counts are spelled as altered forms of the widest number instead
+		Self::from_number_and_semitones(number.min(MAX_NUMBER), int_semitones)
+			.expect("Every semitone count has a default spelling")
+	}
+}
+
+impl From<Interval> for Semitones {
+	fn from(i: Interval) -> Self {
+		let octaves = i8::try_from((i.number - 1) / 7).expect("Interval numbers fit in an i8");
+		let offset = i
+			.quality
+			.offset(i.is_perfect_class())
+			.expect("Intervals are validated on construction");
+		Semitones::from(NATURAL_SEMITONES[i.degree()] + octaves * OCTAVE_SEMITONES + offset)
 	}
 }
 
@@ -52,23 +327,48 @@ impl From<Interval> for i8 {
 	}
 }
 
+impl fmt::Display for Interval {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}{}", self.quality, self.number)
+	}
+}
+
+impl FromStr for Interval {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+		let split = s
+			.find(|c: char| c.is_ascii_digit())
+			.ok_or_else(|| Error::MissingIntervalNumber(s.to_string()))?;
+		let quality = Quality::from_str(&s[..split])?;
+		let number = s[split..]
+			.parse::<u8>()
+			.map_err(|_| Error::BadIntervalNumber(s[split..].to_string()))?;
+		Self::new(quality, number)
+	}
+}
+
 impl Add for Interval {
 	type Output = Self;
+
+	/// Stack two intervals - a major third on a minor third makes a perfect fifth.
+	/// A sum too heavily altered to name, like two doubly augmented fourths, is spelled the usual way for its semitones
 	fn add(self, rhs: Self) -> Self {
-		Interval::from(Semitones::from(
-			i8::from(self) + i8::from(rhs) % Interval::Octave as i8,
-		))
+		self.checked_add(rhs)
+			.unwrap_or_else(|| Self::respell(i16::from(i8::from(self)) + i16::from(i8::from(rhs))))
 	}
 }
 
 impl Sub for Interval {
 	type Output = Self;
+
+	/// Find the interval left after removing `rhs`.
+	/// If `rhs` is the larger interval, the result wraps up by an octave.
+	/// A difference too heavily altered to name is spelled the usual way for its semitones
 	fn sub(self, rhs: Self) -> Self {
-		let mut delta = i8::from(self) - i8::from(rhs);
-		if delta < 0 {
-			delta += Interval::Octave as i8;
-		};
-		Interval::from(Semitones::from(delta))
+		self.checked_sub(rhs)
+			.unwrap_or_else(|| Self::respell(self.difference(rhs).1))
 	}
 }
 
@@ -1,10 +1,17 @@
 //! A Key dictates which notes are currently allowed to be played.
 //! It is defined by a base note, a scale up to the next octave, and a number of octaves
 
-use super::{interval::Interval, note::Note, piano_key::PianoKey, scale::Scale};
-use std::{fmt, str::FromStr};
+use super::{
+	interval::Interval,
+	note::Note,
+	piano_key::PianoKey,
+	scale::{Mode, Scale},
+};
+use std::fmt;
 
-#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "crate::serde_impls::KeyFields"))]
 pub struct Key {
 	pub base_note: PianoKey,
 	pub octaves: u8,
@@ -14,7 +21,7 @@ pub struct Key {
 impl Key {
 	#[must_use]
 	pub fn new(scale: Scale, base_note: PianoKey, octaves: u8) -> Self {
-		let octaves = if base_note.octave + octaves > 8 {
+		let octaves = if base_note.octave.saturating_add(octaves) > PianoKey::max_octave() {
 			PianoKey::max_octave() - base_note.octave
 		} else {
 			octaves
@@ -26,47 +33,82 @@ impl Key {
 		}
 	}
 
-	/// Get a listing of all possible `PianoKeys` in this Key.
-	///
-	/// # Panics
-	///
-	/// This function would panic if a bad value is passed to `PianoKey::from_str`.
+	/// Get a listing of all possible `PianoKeys` in this Key, lowest first.
+	/// The range runs from the base note up to the same note `octaves` higher.
 	#[must_use]
 	pub fn all_keys(self) -> Vec<PianoKey> {
-		let notes = self.get_notes();
+		let intervals = self.scale.intervals_from(self.base_note.note);
 		let mut ret = Vec::new();
-		for i in 0..self.octaves {
-			for n in &notes {
-				ret.push(
-					PianoKey::from_str(&format!("{}{}", *n, i + self.base_note.octave))
-						.unwrap_or_else(|_| {
-							PianoKey::from_str(&format!("{}{}", *n, PianoKey::max_octave()))
-								.unwrap()
-						}),
-				);
+		let mut octave_base = self.base_note;
+		for _ in 0..self.octaves {
+			let mut offset = Interval::UNISON;
+			ret.push(octave_base);
+			// The last step lands on the next octave's base note
+			for i in &intervals[..intervals.len() - 1] {
+				offset += *i;
+				ret.push(octave_base + offset);
 			}
+			octave_base = octave_base + Interval::OCTAVE;
 		}
+		ret.push(octave_base);
 		ret
 	}
 
 	#[must_use]
 	pub fn get_notes(self) -> Vec<Note> {
 		let mut ret = vec![self.base_note.note];
-		let mut offset = Interval::Unison;
-		self.scale.get_intervals().iter().for_each(|i| {
-			offset += *i;
-			ret.push(self.base_note.note + offset);
-		});
+		let mut offset = Interval::UNISON;
+		self.scale
+			.intervals_from(self.base_note.note)
+			.iter()
+			.for_each(|i| {
+				offset += *i;
+				ret.push(self.base_note.note + offset);
+			});
 		ret
 	}
+
+	/// Get the relative major or minor, which shares this key's signature.
+	/// Only major and minor keys have one, and the relative minor is found below the major.
+	#[must_use]
+	pub fn relative(self) -> Option<Self> {
+		use Mode::{Aeolian, Ionian};
+		use Scale::Diatonic;
+		match self.scale {
+			Diatonic(Ionian) => Some(Self::new(
+				Diatonic(Aeolian),
+				self.base_note - Interval::MIN3,
+				self.octaves,
+			)),
+			Diatonic(Aeolian) => Some(Self::new(
+				Diatonic(Ionian),
+				self.base_note + Interval::MIN3,
+				self.octaves,
+			)),
+			_ => None,
+		}
+	}
+
+	/// Get the parallel major or minor, which shares this key's base note.
+	/// Only major and minor keys have one.
+	#[must_use]
+	pub fn parallel(self) -> Option<Self> {
+		use Mode::{Aeolian, Ionian};
+		use Scale::Diatonic;
+		match self.scale {
+			Diatonic(Ionian) => Some(Self::new(Diatonic(Aeolian), self.base_note, self.octaves)),
+			Diatonic(Aeolian) => Some(Self::new(Diatonic(Ionian), self.base_note, self.octaves)),
+			_ => None,
+		}
+	}
 }
 
 impl fmt::Display for Key {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		let notes = self.get_notes();
-		let mut ret = String::from("[ ");
-		notes.iter().for_each(|n| ret.push_str(&format!("{} ", *n)));
-		ret.push(']');
-		write!(f, "{ret}")
+		write!(f, "[ ")?;
+		for n in self.get_notes() {
+			write!(f, "{n} ")?;
+		}
+		write!(f, "]")
 	}
 }
@@ -0,0 +1,276 @@
+//! A `KeySignature` lists the sharps or flats that apply to every note of a key.
+//! It also decides which accidentals have to be written out when a note strays from it
+
+use super::{
+	key::Key,
+	nearest_alteration,
+	note::{Accidental, Letter, Note},
+	piano_key::PianoKey,
+	scale::{Mode, Scale},
+};
+use crate::error::Error;
+use std::{collections::HashMap, fmt};
+
+/// Letters in the order sharps are added to a signature. Flats are added in the reverse order
+const ORDER_OF_SHARPS: [Letter; 7] = [
+	Letter::F,
+	Letter::C,
+	Letter::G,
+	Letter::D,
+	Letter::A,
+	Letter::E,
+	Letter::B,
+];
+
+/// The most sharps or flats a signature can hold
+pub(crate) const MAX_ACCIDENTALS: i8 = 7;
+
+/// A signature is stored as its position on the circle of fifths.
+/// Positive values count sharps and negative values count flats
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeySignature(i8);
+
+impl KeySignature {
+	/// Build a signature from a count of fifths from C - 2 is D major's two sharps, -3 is E♭ major's three flats
+	pub fn new(fifths: i8) -> Result<Self, Error> {
+		if fifths.abs() > MAX_ACCIDENTALS {
+			return Err(Error::TooManyAccidentals(fifths));
+		}
+		Ok(Self(fifths))
+	}
+
+	/// Every playable signature, from seven flats to seven sharps
+	#[must_use]
+	pub fn all() -> Vec<Self> {
+		(-MAX_ACCIDENTALS..=MAX_ACCIDENTALS).map(Self).collect()
+	}
+
+	/// Find the signature for a tonic in the given mode - D Dorian has no sharps or flats.
+	/// Keys like G# major that would need double sharps are rejected.
+	pub fn from_tonic(tonic: Note, mode: Mode) -> Result<Self, Error> {
+		Self::new(major_fifths(tonic) + mode_fifths(mode))
+			.map_err(|_| Error::TheoreticalKey { tonic, mode })
+	}
+
+	/// Find the signature of a major key
+	pub fn major(tonic: Note) -> Result<Self, Error> {
+		Self::from_tonic(tonic, Mode::Ionian)
+	}
+
+	/// Find the signature of a natural minor key
+	pub fn minor(tonic: Note) -> Result<Self, Error> {
+		Self::from_tonic(tonic, Mode::Aeolian)
+	}
+
+	/// Get the position on the circle of fifths, positive for sharps and negative for flats
+	#[must_use]
+	pub fn fifths(self) -> i8 {
+		self.0
+	}
+
+	/// Get the tonic of the given mode using this signature - the major key for Ionian, the relative minor for Aeolian
+	#[must_use]
+	pub fn tonic(self, mode: Mode) -> Note {
+		note_from_fifths(self.0 - mode_fifths(mode))
+	}
+
+	/// Get the altered notes in the order they are written on the staff
+	#[must_use]
+	pub fn accidentals(self) -> Vec<Note> {
+		let count = usize::from(self.0.unsigned_abs());
+		if self.0 >= 0 {
+			ORDER_OF_SHARPS
+				.iter()
+				.take(count)
+				.map(|&letter| Note {
+					accidental: Some(Accidental::Sharp),
+					letter,
+				})
+				.collect()
+		} else {
+			ORDER_OF_SHARPS
+				.iter()
+				.rev()
+				.take(count)
+				.map(|&letter| Note {
+					accidental: Some(Accidental::Flat),
+					letter,
+				})
+				.collect()
+		}
+	}
+
+	/// Get the accidental this signature applies to every note with the given letter
+	#[must_use]
+	pub fn accidental(self, letter: Letter) -> Option<Accidental> {
+		self.accidentals()
+			.iter()
+			.find(|n| n.letter == letter)
+			.and_then(|n| n.accidental)
+	}
+
+	/// Decide what to print in front of a note standing alone against this signature.
+	/// Returns `None` when the signature already implies the right accidental.
+	#[must_use]
+	pub fn marking(self, note: Note) -> Option<Marking> {
+		marking_against(self.accidental(note.letter), note)
+	}
+
+	/// Respell a note to read naturally in this key.
+	/// Notes in the key keep the key's spelling, and chromatic notes prefer sharps in sharp keys and flats in flat keys.
+	#[must_use]
+	pub fn spell(self, note: Note) -> Note {
+		let semitones = note.semitones_from_c();
+		let key_direction = if self.0 >= 0 {
+			Accidental::Sharp
+		} else {
+			Accidental::Flat
+		};
+		(0..7)
+			.map(|steps| Letter::C.step(steps))
+			.filter_map(|letter| {
+				let natural = i8::from(letter.interval_from_c());
+				let accidental =
+					Accidental::from_semitones(nearest_alteration(semitones - natural)).ok()?;
+				let cost = if accidental == self.accidental(letter) {
+					0
+				} else {
+					match accidental {
+						None => 1,
+						Some(a) if a == key_direction => 2,
+						Some(Accidental::Sharp | Accidental::Flat) => 3,
+						Some(_) => 4,
+					}
+				};
+				Some((cost, Note { accidental, letter }))
+			})
+			.min_by_key(|(cost, _)| *cost)
+			.map_or(note, |(_, n)| n)
+	}
+}
+
+impl TryFrom<Key> for KeySignature {
+	type Error = Error;
+
+	fn try_from(key: Key) -> Result<Self, Self::Error> {
+		match key.scale {
+			Scale::Diatonic(mode) => Self::from_tonic(key.base_note.note, mode),
+			_ => Err(Error::NoKeySignature(key.scale)),
+		}
+	}
+}
+
+impl fmt::Display for KeySignature {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let accidentals = self.accidentals();
+		if accidentals.is_empty() {
+			return write!(f, "no sharps or flats");
+		}
+		let notes = accidentals
+			.iter()
+			.map(ToString::to_string)
+			.collect::<Vec<_>>()
+			.join(" ");
+		write!(f, "{notes}")
+	}
+}
+
+/// A Marking is the symbol printed in front of a note on the staff
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Marking {
+	/// Cancels an accidental from the signature or from earlier in the bar
+	Natural,
+	Accidental(Accidental),
+}
+
+impl fmt::Display for Marking {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Marking::Natural => write!(f, "♮"),
+			Marking::Accidental(a) => write!(f, "{a}"),
+		}
+	}
+}
+
+/// Tracks the accidentals written so far in a bar.
+/// An accidental holds for the rest of the bar on the same line or space, so only changes need printing
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccidentalContext {
+	signature: KeySignature,
+	in_effect: HashMap<(Letter, u8), Option<Accidental>>,
+}
+
+impl AccidentalContext {
+	#[must_use]
+	pub fn new(signature: KeySignature) -> Self {
+		Self {
+			signature,
+			in_effect: HashMap::new(),
+		}
+	}
+
+	/// Decide what to print in front of the next note in the bar, and remember it for the notes that follow
+	pub fn marking(&mut self, key: PianoKey) -> Option<Marking> {
+		let position = (key.note.letter, key.octave);
+		let current = self
+			.in_effect
+			.get(&position)
+			.copied()
+			.unwrap_or_else(|| self.signature.accidental(key.note.letter));
+		let ret = marking_against(current, key.note);
+		if ret.is_some() {
+			self.in_effect.insert(position, key.note.accidental);
+		}
+		ret
+	}
+
+	/// Start a new bar, returning to the key signature
+	pub fn bar_line(&mut self) {
+		self.in_effect.clear();
+	}
+}
+
+/// Compare a note's accidental to the one already in effect for its letter
+fn marking_against(current: Option<Accidental>, note: Note) -> Option<Marking> {
+	if note.accidental == current {
+		None
+	} else {
+		Some(
+			note.accidental
+				.map_or(Marking::Natural, Marking::Accidental),
+		)
+	}
+}
+
+/// Count the fifths from C to a major tonic - G is 1, F is -1, F# is 6
+fn major_fifths(tonic: Note) -> i8 {
+	let position = ORDER_OF_SHARPS
+		.iter()
+		.position(|&l| l == tonic.letter)
+		.expect("Every letter is in the order of sharps");
+	let position = i8::try_from(position).expect("There are only seven letters");
+	position - 1 + MAX_ACCIDENTALS * tonic.accidental.map_or(0, Accidental::semitones)
+}
+
+/// Count the fifths a mode's signature sits from the major key on the same tonic
+fn mode_fifths(mode: Mode) -> i8 {
+	use Mode::{Aeolian, Dorian, Ionian, Locrian, Lydian, Mixolydian, Phrygian};
+	match mode {
+		Lydian => 1,
+		Ionian => 0,
+		Mixolydian => -1,
+		Dorian => -2,
+		Aeolian => -3,
+		Phrygian => -4,
+		Locrian => -5,
+	}
+}
+
+/// Find the major tonic a number of fifths from C
+fn note_from_fifths(fifths: i8) -> Note {
+	#[allow(clippy::cast_sign_loss)]
+	let letter = ORDER_OF_SHARPS[(fifths + 1).rem_euclid(MAX_ACCIDENTALS) as usize];
+	let accidental = Accidental::from_semitones((fifths + 1).div_euclid(MAX_ACCIDENTALS))
+		.expect("Signatures are at most seven fifths from C");
+	Note { accidental, letter }
+}
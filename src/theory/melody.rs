@@ -0,0 +1,68 @@
+//! A Melody is a line of piano keys, each held for a number of steps.
+//! Steps are sixteenth notes, so a quarter-note beat is four steps
+
+use super::piano_key::PianoKey;
+use crate::error::Error;
+use std::{fmt, str::FromStr};
+
+/// The number of steps in one beat
+pub const STEPS_PER_BEAT: u16 = 4;
+
+/// A `MelodyNote` is a single key held for a length of time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MelodyNote {
+	pub key: PianoKey,
+	/// How long the note lasts, in sixteenth-note steps
+	pub steps: u16,
+}
+
+impl MelodyNote {
+	#[must_use]
+	pub fn new(key: PianoKey, steps: u16) -> Self {
+		Self { key, steps }
+	}
+}
+
+impl fmt::Display for MelodyNote {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}:{}", self.key, self.steps)
+	}
+}
+
+impl FromStr for MelodyNote {
+	type Err = Error;
+
+	/// Accepts a piano key with an optional length in steps, like "E4:2".
+	/// Without a length the note lasts one beat
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (key, steps) = match s.split_once(':') {
+			Some((key, steps)) => (
+				key,
+				steps
+					.parse::<u16>()
+					.ok()
+					.filter(|n| *n > 0)
+					.ok_or_else(|| Error::BadLength(steps.to_string()))?,
+			),
+			None => (s, STEPS_PER_BEAT),
+		};
+		Ok(Self::new(PianoKey::from_str(key)?, steps))
+	}
+}
+
+/// Read a melody written as notes separated by spaces or commas, like "C4:2 D4:2 E4:4 C4"
+pub fn parse_melody(s: &str) -> Result<Vec<MelodyNote>, Error> {
+	s.split(|c: char| c == ',' || c.is_whitespace())
+		.filter(|t| !t.is_empty())
+		.map(MelodyNote::from_str)
+		.collect()
+}
+
+/// Read one melody per line, skipping blank lines and comments starting with #
+pub fn parse_melodies(s: &str) -> Result<Vec<Vec<MelodyNote>>, Error> {
+	s.lines()
+		.map(str::trim)
+		.filter(|l| !l.is_empty() && !l.starts_with('#'))
+		.map(parse_melody)
+		.collect()
+}
@@ -1,14 +1,23 @@
 //! Theory is the toolkit for working with notes in terms of piano keys and intervals
 
+pub mod analysis;
 pub mod cent;
+pub mod chorale;
+pub mod chord;
+pub mod counterpoint;
 pub mod hertz;
 pub mod interval;
 pub mod key;
+pub mod key_signature;
+pub mod melody;
 pub mod note;
 pub mod piano_key;
 pub mod pitch;
+pub mod pitch_class_set;
 pub mod scale;
 pub mod semitone;
+pub mod tone_row;
+pub mod voice_leading;
 
 /// Helper function to split a string into a vector of strings, one per character
 /// Example: "hello" => vec!["h", "e", "l", "l", "o"]
@@ -16,6 +25,16 @@ fn char_strs(s: &str) -> impl Iterator<Item = &str> {
 	s.split("").skip(1).take_while(|c| !c.is_empty())
 }
 
+/// Helper function to reduce a semitone difference to the equivalent closest to zero, between -6 and 6
+fn nearest_alteration(semitones: i8) -> i8 {
+	let ret = semitones.rem_euclid(12);
+	if ret > 6 {
+		ret - 12
+	} else {
+		ret
+	}
+}
+
 #[cfg(test)]
 mod test {
 	use super::*;
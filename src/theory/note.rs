@@ -1,14 +1,17 @@
 //! A Note is the high-level abstraction for working with specific pitches
 
-use super::{char_strs, interval::Interval, scale::Scale, semitone::Semitones};
+use super::{
+	interval::Interval, nearest_alteration, piano_key::PianoKey, scale::Scale, semitone::Semitones,
+};
+use crate::error::Error;
 use std::{
-	fmt, io,
+	fmt,
 	ops::{Add, AddAssign},
 	str::FromStr,
 };
 
 /// Notes are one of these letters, with C as the base note
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub enum Letter {
 	#[default]
 	C = 0,
@@ -21,7 +24,7 @@ pub enum Letter {
 }
 
 impl FromStr for Letter {
-	type Err = io::Error;
+	type Err = Error;
 
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
 		match s.to_uppercase().as_str() {
@@ -32,10 +35,7 @@ impl FromStr for Letter {
 			"E" => Ok(Letter::E),
 			"F" => Ok(Letter::F),
 			"G" => Ok(Letter::G),
-			_ => Err(io::Error::new(
-				io::ErrorKind::InvalidInput,
-				format!("{s} is not a valid note"),
-			)),
+			_ => Err(Error::BadLetter(s.to_string())),
 		}
 	}
 }
@@ -55,96 +55,126 @@ impl Letter {
 		}
 	}
 
+	/// Get the letter a number of steps higher, wrapping around after B
+	#[must_use]
+	pub fn step(self, steps: usize) -> Self {
+		(0..steps % 7).fold(self, |acc, _| acc.inc())
+	}
+
 	/// Get the distance from the base - C - as an Interval
 	#[must_use]
 	pub fn interval_from_c(self) -> Interval {
-		use Interval::Unison;
 		Scale::default()
 			.get_intervals()
 			.iter()
 			.take(self as usize)
-			.fold(Unison, |acc, i| acc + *i)
+			.fold(Interval::UNISON, |acc, i| acc + *i)
 	}
 }
 
-/// An Accidental adjust the tone of a note by one semitone.
+/// An Accidental adjusts the tone of a note by one or two semitones.
 /// Flats go down, sharps go up
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Accidental {
+	DoubleFlat,
 	Flat,
 	Sharp,
+	DoubleSharp,
+}
+
+impl Accidental {
+	/// Get the number of semitones this accidental moves a note
+	#[must_use]
+	pub fn semitones(self) -> i8 {
+		use Accidental::{DoubleFlat, DoubleSharp, Flat, Sharp};
+		match self {
+			DoubleFlat => -2,
+			Flat => -1,
+			Sharp => 1,
+			DoubleSharp => 2,
+		}
+	}
+
+	/// Find the accidental that moves a note by the given semitones, or `None` for no movement
+	pub fn from_semitones(semitones: i8) -> Result<Option<Self>, Error> {
+		use Accidental::{DoubleFlat, DoubleSharp, Flat, Sharp};
+		match semitones {
+			-2 => Ok(Some(DoubleFlat)),
+			-1 => Ok(Some(Flat)),
+			0 => Ok(None),
+			1 => Ok(Some(Sharp)),
+			2 => Ok(Some(DoubleSharp)),
+			_ => Err(Error::NoAccidental(semitones)),
+		}
+	}
 }
 
 impl fmt::Display for Accidental {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		use Accidental::{Flat, Sharp};
+		use Accidental::{DoubleFlat, DoubleSharp, Flat, Sharp};
 		let acc_str = match self {
+			DoubleFlat => "𝄫",
 			Flat => "♭",
 			Sharp => "#",
+			DoubleSharp => "𝄪",
 		};
 		write!(f, "{acc_str}")
 	}
 }
 
 impl FromStr for Accidental {
-	type Err = io::Error;
+	type Err = Error;
 
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
 		match s {
+			"bb" | "♭♭" | "𝄫" => Ok(Accidental::DoubleFlat),
 			"b" | "♭" => Ok(Accidental::Flat),
 			"#" => Ok(Accidental::Sharp),
-			_ => Err(io::Error::new(
-				io::ErrorKind::InvalidInput,
-				format!("{s} is not a valid accidental"),
-			)),
+			"##" | "x" | "𝄪" => Ok(Accidental::DoubleSharp),
+			_ => Err(Error::BadAccidental(s.to_string())),
 		}
 	}
 }
 
-#[derive(Default, Debug, Clone, Copy, PartialEq)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Note {
 	pub accidental: Option<Accidental>,
 	pub letter: Letter,
 }
 
 impl Note {
+	/// Get the number of semitones above C, which may be negative for C♭ or past 11 for B#
+	#[must_use]
+	pub fn semitones_from_c(self) -> i8 {
+		i8::from(self.letter.interval_from_c()) + self.accidental.map_or(0, Accidental::semitones)
+	}
+	/// Get the pitch class - semitones above C within a single octave, so enharmonic notes match
+	#[must_use]
+	pub fn pitch_class(self) -> u8 {
+		#[allow(clippy::cast_sign_loss)]
+		let ret = self.semitones_from_c().rem_euclid(12) as u8;
+		ret
+	}
+	/// Get the distance from C as an Interval, keeping the spelling - C# is an augmented unison
+	///
+	/// # Panics
+	///
+	/// This function would panic if an accidental could alter a note past a doubly-altered interval.
 	#[must_use]
 	pub fn interval_from_c(self) -> Interval {
-		use Accidental::{Flat, Sharp};
 		let ret = self.letter.interval_from_c();
-		if let Some(acc) = self.accidental {
-			match acc {
-				Flat => return Interval::from(Semitones::from(i8::from(Semitones::from(ret)) - 1)),
-				Sharp => return ret + Interval::Min2,
-			}
-		};
-		ret
+		Interval::from_number_and_semitones(ret.number(), self.semitones_from_c())
+			.expect("Accidentals alter a note by at most two semitones")
 	}
+	/// Get the Interval from `other` up to this note, within a single octave
 	#[must_use]
 	pub fn get_offset(self, other: Self) -> Interval {
-		let self_interval_from_c = self.interval_from_c();
-		let other_interval_from_c = other.interval_from_c();
-		self_interval_from_c - other_interval_from_c
-	}
-	fn inc(&mut self) {
-		use Accidental::{Flat, Sharp};
-		use Letter::{B, E};
-		if let Some(acc) = self.accidental {
-			self.accidental = None;
-			match acc {
-				Sharp => {
-					self.letter = self.letter.inc();
-				},
-				Flat => {},
-			}
-		} else {
-			// check for special cases
-			if self.letter == B || self.letter == E {
-				self.letter = self.letter.inc();
-			} else {
-				self.accidental = Some(Sharp);
-			}
-		}
+		let steps = (self.letter as u8 + 7 - other.letter as u8) % 7;
+		let natural = i8::from(Letter::C.step(usize::from(steps)).interval_from_c());
+		let semitones = natural
+			+ nearest_alteration(self.semitones_from_c() - other.semitones_from_c() - natural);
+		Interval::from_number_and_semitones(steps + 1, semitones)
+			.unwrap_or_else(|| Interval::from(Semitones::from(semitones)))
 	}
 }
 
@@ -160,54 +190,74 @@ impl fmt::Display for Note {
 }
 
 impl FromStr for Note {
-	type Err = io::Error;
+	type Err = Error;
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
-		let mut char_strs = char_strs(s);
-		// note will be first
-		if let Some(letter) = char_strs.next() {
+		// note will be first, anything after it is the accidental
+		if let Some(letter) = s.chars().next() {
+			let (letter, accidental) = s.split_at(letter.len_utf8());
 			let letter = Letter::from_str(letter)?;
-			if let Some(accidental) = char_strs.next() {
-				// check if it's valid
-				let accidental = Accidental::from_str(accidental)?;
-				return Ok(Self {
-					letter,
-					accidental: Some(accidental),
-				});
-			}
-			return Ok(Self {
-				letter,
-				accidental: None,
-			});
+			let accidental = if accidental.is_empty() {
+				None
+			} else {
+				Some(Accidental::from_str(accidental)?)
+			};
+			return Ok(Self { accidental, letter });
 		}
-		Err(io::Error::new(
-			io::ErrorKind::InvalidInput,
-			format!("{s} is not a valid note"),
-		))
+		Err(Error::MissingNote)
+	}
+}
+
+impl From<Semitones> for Note {
+	/// Spell a number of semitones above C, preferring sharps
+	fn from(s: Semitones) -> Self {
+		use Accidental::Sharp;
+		use Letter::{A, B, C, D, E, F, G};
+		const SPELLINGS: [(Letter, Option<Accidental>); 12] = [
+			(C, None),
+			(C, Some(Sharp)),
+			(D, None),
+			(D, Some(Sharp)),
+			(E, None),
+			(F, None),
+			(F, Some(Sharp)),
+			(G, None),
+			(G, Some(Sharp)),
+			(A, None),
+			(A, Some(Sharp)),
+			(B, None),
+		];
+		#[allow(clippy::cast_sign_loss)]
+		let (letter, accidental) = SPELLINGS[i8::from(s).rem_euclid(12) as usize];
+		Self { accidental, letter }
+	}
+}
+
+impl From<PianoKey> for Note {
+	fn from(pk: PianoKey) -> Self {
+		pk.note
 	}
 }
 
 impl From<Interval> for Note {
 	// Take an interval from C
 	fn from(i: Interval) -> Self {
-		use Interval::Unison;
-		let mut offset = Unison;
-		// That's a series of Min2
-		let scale = Scale::Chromatic.get_intervals();
-		scale.iter().take(i as usize).for_each(|i| offset += *i);
-		Note::default() + offset
+		Note::default() + i
 	}
 }
 
 impl Add<Interval> for Note {
 	type Output = Self;
 
+	/// Spell the note the given interval above this one - A plus a major third is C#.
+	/// Falls back to a sharp spelling if the exact one would need more than a double accidental.
 	fn add(self, rhs: Interval) -> Self {
-		let semitones = Semitones::from(rhs);
-		let mut ret = self;
-		for _ in 0..i8::from(semitones) {
-			ret.inc();
+		let letter = self.letter.step(usize::from(rhs.number() - 1));
+		let target = self.semitones_from_c() + i8::from(rhs);
+		let natural = i8::from(letter.interval_from_c());
+		match Accidental::from_semitones(nearest_alteration(target - natural)) {
+			Ok(accidental) => Self { accidental, letter },
+			Err(_) => Note::from(Semitones::from(target)),
 		}
-		ret
 	}
 }
 
@@ -1,9 +1,14 @@
 //! A `PianoKey` represents a single key on a piano (aptly named, huh?)
 
-use super::{char_strs, note::Note};
-use std::{fmt, io, str::FromStr};
+use super::{char_strs, interval::Interval, note::Note, semitone::Semitones};
+use crate::error::Error;
+use std::{
+	fmt,
+	ops::{Add, Sub},
+	str::FromStr,
+};
 
-#[derive(Default, Debug, Clone, Copy, PartialEq)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct PianoKey {
 	pub note: Note,
 	pub octave: u8,
@@ -16,38 +21,27 @@ impl fmt::Display for PianoKey {
 }
 
 impl FromStr for PianoKey {
-	type Err = io::Error;
+	type Err = Error;
 
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
 		// It makes sense to get the letter to Intervals
-		if let Some(octave) = char_strs(s).last() {
-			if let Ok(octave) = octave.parse::<u8>() {
-				let note = Note::from_str(&s[0..s.len() - 1])?;
-				if octave <= 8 {
-					Ok(Self { note, octave })
-				} else {
-					Err(io::Error::new(
-						io::ErrorKind::InvalidInput,
-						format!("{octave} is too high!"),
-					))
-				}
-			} else {
-				Err(io::Error::new(
-					io::ErrorKind::InvalidInput,
-					format!("{octave} is too high for this keyboard"),
-				))
-			}
+		let Some(octave) = char_strs(s).last() else {
+			return Err(Error::MissingNote);
+		};
+		let Ok(octave) = octave.parse::<u8>() else {
+			return Err(Error::MissingOctave(s.to_string()));
+		};
+		let note = Note::from_str(&s[0..s.len() - 1])?;
+		if octave <= Self::max_octave() {
+			Ok(Self { note, octave })
 		} else {
-			Err(io::Error::new(
-				io::ErrorKind::InvalidInput,
-				format!("{s} is not a valid note"),
-			))
+			Err(Error::OctaveOutOfRange(octave))
 		}
 	}
 }
 
 impl PianoKey {
-	pub fn new(s: &str) -> Result<Self, io::Error> {
+	pub fn new(s: &str) -> Result<Self, Error> {
 		Self::from_str(s)
 	}
 	/// Get the highest allowed octave - hard-coded to be 8
@@ -55,4 +49,76 @@ impl PianoKey {
 	pub fn max_octave() -> u8 {
 		8
 	}
+	/// Get the number of semitones above C0, so enharmonic keys like B#3 and C4 compare equal
+	#[must_use]
+	pub fn semitones_from_c0(self) -> i16 {
+		i16::from(self.octave) * 12 + i16::from(self.note.semitones_from_c())
+	}
+	/// Get the MIDI note number, where C4 is 60. Keys below C-1 have no number
+	#[must_use]
+	pub fn midi(self) -> Option<u8> {
+		u8::try_from(self.semitones_from_c0() + 12)
+			.ok()
+			.filter(|n| *n <= 127)
+	}
+	/// Find the key for a MIDI note number, spelled with sharps.
+	/// Numbers outside this keyboard's octaves have no key
+	#[must_use]
+	pub fn from_midi(number: u8) -> Option<Self> {
+		let octave = (number / 12).checked_sub(1)?;
+		if octave > Self::max_octave() {
+			return None;
+		}
+		Some(Self {
+			note: Note::from(Semitones::from(i8::try_from(number % 12).ok()?)),
+			octave,
+		})
+	}
+	/// Get the interval between two keys, measured from the lower up, compound past an octave.
+	/// Spellings too far apart to name, like Cbb4 to D##4, have no interval
+	#[must_use]
+	pub fn interval_to(self, other: Self) -> Option<Interval> {
+		let steps = |k: Self| i16::from(k.octave) * 7 + i16::from(k.note.letter as u8);
+		let (low, high) = if steps(other) < steps(self) {
+			(other, self)
+		} else {
+			(self, other)
+		};
+		let number = u8::try_from(steps(high) - steps(low) + 1).ok()?;
+		let semitones = i8::try_from(high.semitones_from_c0() - low.semitones_from_c0()).ok()?;
+		Interval::from_number_and_semitones(number, semitones)
+	}
+	/// Build a key from its spelled note and its distance from C0, working out the octave
+	#[must_use]
+	pub fn with_note(note: Note, semitones_from_c0: i16) -> Self {
+		let octave = (semitones_from_c0 - i16::from(note.semitones_from_c())).div_euclid(12);
+		Self {
+			note,
+			octave: u8::try_from(octave.max(0)).unwrap_or(u8::MAX),
+		}
+	}
+}
+
+impl Add<Interval> for PianoKey {
+	type Output = Self;
+
+	/// Spell the key the given interval higher, moving into the next octave as needed
+	fn add(self, rhs: Interval) -> Self {
+		Self::with_note(
+			self.note + rhs,
+			self.semitones_from_c0() + i16::from(i8::from(rhs)),
+		)
+	}
+}
+
+impl Sub<Interval> for PianoKey {
+	type Output = Self;
+
+	/// Spell the key the given interval lower, stopping at octave 0
+	fn sub(self, rhs: Interval) -> Self {
+		Self::with_note(
+			self.note + rhs.simple().invert(),
+			self.semitones_from_c0() - i16::from(i8::from(rhs)),
+		)
+	}
 }
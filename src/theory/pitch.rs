@@ -1,5 +1,6 @@
 //! A Pitch is a domain-specific wrapper around Hertz, representing a musical pitch at a set frequency
 
+#[cfg(feature = "playback")]
 use crate::f64_to_f32;
 
 use super::{
@@ -9,10 +10,12 @@ use super::{
 	piano_key::PianoKey,
 	semitone::Semitones,
 };
+#[cfg(feature = "playback")]
 use rodio::source::SineWave;
 use std::ops::AddAssign;
 
 #[derive(Debug, Clone, Copy, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pitch(Hertz);
 
 impl Pitch {
@@ -20,6 +23,14 @@ impl Pitch {
 	pub fn new(frequency: Hertz) -> Self {
 		Self(frequency)
 	}
+
+	/// Move a pitch from standard tuning to a tuning where A4 sounds at another frequency, like 432 Hz
+	#[must_use]
+	pub fn tuned(self, a4: Hertz) -> Self {
+		let mut frequency = self.0;
+		frequency *= f64::from(a4) / f64::from(STANDARD_PITCH);
+		Self(frequency)
+	}
 }
 
 impl Default for Pitch {
@@ -39,7 +50,7 @@ impl AddAssign<Cents> for Pitch {
 	// You actually do mean it here, though
 	#[allow(clippy::suspicious_op_assign_impl)]
 	fn add_assign(&mut self, rhs: Cents) {
-		self.0 *= 2.0f64.powf((rhs / Cents::from(Interval::Octave)).into());
+		self.0 *= 2.0f64.powf((rhs / Cents::from(Interval::OCTAVE)).into());
 	}
 }
 
@@ -57,6 +68,7 @@ impl AddAssign<Semitones> for Pitch {
 	}
 }
 
+#[cfg(feature = "playback")]
 impl From<Pitch> for SineWave {
 	fn from(p: Pitch) -> Self {
 		SineWave::new(f64_to_f32(p.into()))
@@ -71,14 +83,13 @@ impl AddAssign<Interval> for Pitch {
 
 impl From<PianoKey> for Pitch {
 	fn from(sp: PianoKey) -> Self {
-		use Interval::Octave;
 		let mut ret = Pitch::new(C_ZERO);
 		// Add octaves
 		for _ in 0..sp.octave {
-			ret += Octave;
+			ret += Interval::OCTAVE;
 		}
-		// Add note offset
-		ret += sp.note.letter.interval_from_c();
+		// Add note offset, including any accidental
+		ret += sp.note.interval_from_c();
 		ret
 	}
 }
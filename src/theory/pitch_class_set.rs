@@ -0,0 +1,401 @@
+//! A `PitchClassSet` is an unordered collection of pitch classes, for post-tonal analysis.
+//! Octaves and spelling are ignored, so C, B# and D𝄫 are all pitch class 0
+
+use super::{key::Key, note::Note, piano_key::PianoKey, semitone::Semitones};
+use crate::error::Error;
+use std::{fmt, str::FromStr};
+
+/// The number of pitch classes in an octave
+const PITCH_CLASSES: u8 = 12;
+
+/// Forte's catalogue of set classes, listed by cardinality as (ordinal, Z-related, a member of the class).
+/// Sets with seven or more members are named after their complements, so only sizes up to six are stored.
+/// Any member of a class works here since lookups compare prime forms.
+const FORTE_DYADS: [(u8, bool, &[u8]); 6] = [
+	(1, false, &[0, 1]),
+	(2, false, &[0, 2]),
+	(3, false, &[0, 3]),
+	(4, false, &[0, 4]),
+	(5, false, &[0, 5]),
+	(6, false, &[0, 6]),
+];
+
+const FORTE_TRICHORDS: [(u8, bool, &[u8]); 12] = [
+	(1, false, &[0, 1, 2]),
+	(2, false, &[0, 1, 3]),
+	(3, false, &[0, 1, 4]),
+	(4, false, &[0, 1, 5]),
+	(5, false, &[0, 1, 6]),
+	(6, false, &[0, 2, 4]),
+	(7, false, &[0, 2, 5]),
+	(8, false, &[0, 2, 6]),
+	(9, false, &[0, 2, 7]),
+	(10, false, &[0, 3, 6]),
+	(11, false, &[0, 3, 7]),
+	(12, false, &[0, 4, 8]),
+];
+
+const FORTE_TETRACHORDS: [(u8, bool, &[u8]); 29] = [
+	(1, false, &[0, 1, 2, 3]),
+	(2, false, &[0, 1, 2, 4]),
+	(3, false, &[0, 1, 3, 4]),
+	(4, false, &[0, 1, 2, 5]),
+	(5, false, &[0, 1, 2, 6]),
+	(6, false, &[0, 1, 2, 7]),
+	(7, false, &[0, 1, 4, 5]),
+	(8, false, &[0, 1, 5, 6]),
+	(9, false, &[0, 1, 6, 7]),
+	(10, false, &[0, 2, 3, 5]),
+	(11, false, &[0, 1, 3, 5]),
+	(12, false, &[0, 2, 3, 6]),
+	(13, false, &[0, 1, 3, 6]),
+	(14, false, &[0, 2, 3, 7]),
+	(15, true, &[0, 1, 4, 6]),
+	(16, false, &[0, 1, 5, 7]),
+	(17, false, &[0, 3, 4, 7]),
+	(18, false, &[0, 1, 4, 7]),
+	(19, false, &[0, 1, 4, 8]),
+	(20, false, &[0, 1, 5, 8]),
+	(21, false, &[0, 2, 4, 6]),
+	(22, false, &[0, 2, 4, 7]),
+	(23, false, &[0, 2, 5, 7]),
+	(24, false, &[0, 2, 4, 8]),
+	(25, false, &[0, 2, 6, 8]),
+	(26, false, &[0, 3, 5, 8]),
+	(27, false, &[0, 2, 5, 8]),
+	(28, false, &[0, 3, 6, 9]),
+	(29, true, &[0, 1, 3, 7]),
+];
+
+const FORTE_PENTACHORDS: [(u8, bool, &[u8]); 38] = [
+	(1, false, &[0, 1, 2, 3, 4]),
+	(2, false, &[0, 1, 2, 3, 5]),
+	(3, false, &[0, 1, 2, 4, 5]),
+	(4, false, &[0, 1, 2, 3, 6]),
+	(5, false, &[0, 1, 2, 3, 7]),
+	(6, false, &[0, 1, 2, 5, 6]),
+	(7, false, &[0, 1, 2, 6, 7]),
+	(8, false, &[0, 2, 3, 4, 6]),
+	(9, false, &[0, 1, 2, 4, 6]),
+	(10, false, &[0, 1, 3, 4, 6]),
+	(11, false, &[0, 2, 3, 4, 7]),
+	(12, true, &[0, 1, 3, 5, 6]),
+	(13, false, &[0, 1, 2, 4, 8]),
+	(14, false, &[0, 1, 2, 5, 7]),
+	(15, false, &[0, 1, 2, 6, 8]),
+	(16, false, &[0, 1, 3, 4, 7]),
+	(17, true, &[0, 1, 3, 4, 8]),
+	(18, true, &[0, 1, 4, 5, 7]),
+	(19, false, &[0, 1, 3, 6, 7]),
+	(20, false, &[0, 1, 3, 7, 8]),
+	(21, false, &[0, 1, 4, 5, 8]),
+	(22, false, &[0, 1, 4, 7, 8]),
+	(23, false, &[0, 2, 3, 5, 7]),
+	(24, false, &[0, 1, 3, 5, 7]),
+	(25, false, &[0, 2, 3, 5, 8]),
+	(26, false, &[0, 2, 4, 5, 8]),
+	(27, false, &[0, 1, 3, 5, 8]),
+	(28, false, &[0, 2, 3, 6, 8]),
+	(29, false, &[0, 1, 3, 6, 8]),
+	(30, false, &[0, 1, 4, 6, 8]),
+	(31, false, &[0, 1, 3, 6, 9]),
+	(32, false, &[0, 1, 4, 6, 9]),
+	(33, false, &[0, 2, 4, 6, 8]),
+	(34, false, &[0, 2, 4, 6, 9]),
+	(35, false, &[0, 2, 4, 7, 9]),
+	(36, true, &[0, 1, 2, 4, 7]),
+	(37, true, &[0, 3, 4, 5, 8]),
+	(38, true, &[0, 1, 2, 5, 8]),
+];
+
+const FORTE_HEXACHORDS: [(u8, bool, &[u8]); 50] = [
+	(1, false, &[0, 1, 2, 3, 4, 5]),
+	(2, false, &[0, 1, 2, 3, 4, 6]),
+	(3, true, &[0, 1, 2, 3, 5, 6]),
+	(4, true, &[0, 1, 2, 4, 5, 6]),
+	(5, false, &[0, 1, 2, 3, 6, 7]),
+	(6, true, &[0, 1, 2, 5, 6, 7]),
+	(7, false, &[0, 1, 2, 6, 7, 8]),
+	(8, false, &[0, 2, 3, 4, 5, 7]),
+	(9, false, &[0, 1, 2, 3, 5, 7]),
+	(10, true, &[0, 1, 3, 4, 5, 7]),
+	(11, true, &[0, 1, 2, 4, 5, 7]),
+	(12, true, &[0, 1, 2, 4, 6, 7]),
+	(13, true, &[0, 1, 3, 4, 6, 7]),
+	(14, false, &[0, 1, 3, 4, 5, 8]),
+	(15, false, &[0, 1, 2, 4, 5, 8]),
+	(16, false, &[0, 1, 4, 5, 6, 8]),
+	(17, true, &[0, 1, 2, 4, 7, 8]),
+	(18, false, &[0, 1, 2, 5, 7, 8]),
+	(19, true, &[0, 1, 3, 4, 7, 8]),
+	(20, false, &[0, 1, 4, 5, 8, 9]),
+	(21, false, &[0, 2, 3, 4, 6, 8]),
+	(22, false, &[0, 1, 2, 4, 6, 8]),
+	(23, true, &[0, 2, 3, 5, 6, 8]),
+	(24, true, &[0, 1, 3, 4, 6, 8]),
+	(25, true, &[0, 1, 3, 5, 6, 8]),
+	(26, true, &[0, 1, 3, 5, 7, 8]),
+	(27, false, &[0, 1, 3, 4, 6, 9]),
+	(28, true, &[0, 1, 3, 5, 6, 9]),
+	(29, true, &[0, 1, 3, 6, 8, 9]),
+	(30, false, &[0, 1, 3, 6, 7, 9]),
+	(31, false, &[0, 1, 3, 5, 8, 9]),
+	(32, false, &[0, 2, 4, 5, 7, 9]),
+	(33, false, &[0, 2, 3, 5, 7, 9]),
+	(34, false, &[0, 1, 3, 5, 7, 9]),
+	(35, false, &[0, 2, 4, 6, 8, 10]),
+	(36, true, &[0, 1, 2, 3, 4, 7]),
+	(37, true, &[0, 1, 2, 3, 4, 8]),
+	(38, true, &[0, 1, 2, 3, 7, 8]),
+	(39, true, &[0, 2, 3, 4, 5, 8]),
+	(40, true, &[0, 1, 2, 3, 5, 8]),
+	(41, true, &[0, 1, 2, 3, 6, 8]),
+	(42, true, &[0, 1, 2, 3, 6, 9]),
+	(43, true, &[0, 1, 2, 5, 6, 8]),
+	(44, true, &[0, 1, 2, 5, 6, 9]),
+	(45, true, &[0, 2, 3, 4, 6, 9]),
+	(46, true, &[0, 1, 2, 4, 6, 9]),
+	(47, true, &[0, 1, 2, 4, 7, 9]),
+	(48, true, &[0, 1, 2, 5, 7, 9]),
+	(49, true, &[0, 1, 3, 4, 7, 9]),
+	(50, true, &[0, 1, 4, 6, 7, 9]),
+];
+
+/// A `ForteNumber` names a set class, like 4-Z15: the cardinality, an ordinal, and whether it has a Z-related twin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ForteNumber {
+	pub cardinality: u8,
+	pub ordinal: u8,
+	pub z: bool,
+}
+
+impl fmt::Display for ForteNumber {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let z = if self.z { "Z" } else { "" };
+		write!(f, "{}-{z}{}", self.cardinality, self.ordinal)
+	}
+}
+
+/// A `PitchClassSet` stores each of the twelve pitch classes as one bit
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PitchClassSet(u16);
+
+impl PitchClassSet {
+	/// Build a set from pitch class numbers, wrapping anything past 11 back into the octave
+	#[must_use]
+	pub fn new(pitch_classes: &[u8]) -> Self {
+		pitch_classes.iter().copied().collect()
+	}
+
+	/// Get the members in ascending order
+	#[must_use]
+	pub fn pitch_classes(self) -> Vec<u8> {
+		(0..PITCH_CLASSES).filter(|&pc| self.contains(pc)).collect()
+	}
+
+	#[must_use]
+	pub fn contains(self, pitch_class: u8) -> bool {
+		self.0 & (1 << (pitch_class % PITCH_CLASSES)) != 0
+	}
+
+	/// Get the number of members
+	#[must_use]
+	pub fn len(self) -> usize {
+		self.0.count_ones() as usize
+	}
+
+	#[must_use]
+	pub fn is_empty(self) -> bool {
+		self.0 == 0
+	}
+
+	/// Get every pitch class missing from this set
+	#[must_use]
+	pub fn complement(self) -> Self {
+		Self(!self.0 & 0x0FFF)
+	}
+
+	/// Transpose every member up by an Interval or a number of Semitones - the Tn operation
+	#[must_use]
+	pub fn transpose(self, by: impl Into<Semitones>) -> Self {
+		let by = i8::from(by.into());
+		self.pitch_classes()
+			.into_iter()
+			.map(|pc| wrap(i16::from(pc) + i16::from(by)))
+			.collect()
+	}
+
+	/// Invert every member around C - the I operation.
+	/// Follow it with `transpose` to reach every other inversion of the set.
+	#[must_use]
+	pub fn invert(self) -> Self {
+		self.pitch_classes()
+			.into_iter()
+			.map(|pc| wrap(-i16::from(pc)))
+			.collect()
+	}
+
+	/// Get the most compact ordering of the members, packed to the left.
+	/// Ties are broken by comparing the spans from the first member to each later member, right to left.
+	#[must_use]
+	pub fn normal_form(self) -> Vec<u8> {
+		let pcs = self.pitch_classes();
+		(0..pcs.len())
+			.map(|start| {
+				pcs.iter()
+					.cycle()
+					.skip(start)
+					.take(pcs.len())
+					.copied()
+					.collect::<Vec<_>>()
+			})
+			.min_by_key(|rotation| packing(rotation))
+			.unwrap_or_default()
+	}
+
+	/// Get the prime form - the best-packed normal form of this set or its inversion, transposed to start on 0
+	#[must_use]
+	pub fn prime_form(self) -> Vec<u8> {
+		let zeroed = |set: Self| {
+			let normal = set.normal_form();
+			let first = normal.first().copied().unwrap_or_default();
+			normal
+				.iter()
+				.map(|&pc| wrap(i16::from(pc) - i16::from(first)))
+				.collect::<Vec<_>>()
+		};
+		let original = zeroed(self);
+		let inverted = zeroed(self.invert());
+		std::cmp::min_by_key(original, inverted, |form| packing(form))
+	}
+
+	/// Count each interval class, from 1 (semitones and major sevenths) through 6 (tritones)
+	#[must_use]
+	pub fn interval_class_vector(self) -> [u8; 6] {
+		let pcs = self.pitch_classes();
+		let mut ret = [0; 6];
+		for (i, a) in pcs.iter().enumerate() {
+			for b in &pcs[i + 1..] {
+				let difference = b - a;
+				let class = difference.min(PITCH_CLASSES - difference);
+				ret[usize::from(class) - 1] += 1;
+			}
+		}
+		ret
+	}
+
+	/// Look up the set class in Forte's catalogue.
+	/// Only sets of two to ten pitch classes are catalogued.
+	#[must_use]
+	pub fn forte_number(self) -> Option<ForteNumber> {
+		let cardinality = u8::try_from(self.len()).ok()?;
+		// Larger sets share an ordinal with their complement
+		let lookup = if cardinality > 6 {
+			self.complement()
+		} else {
+			self
+		};
+		let table: &[(u8, bool, &[u8])] = match lookup.len() {
+			2 => &FORTE_DYADS,
+			3 => &FORTE_TRICHORDS,
+			4 => &FORTE_TETRACHORDS,
+			5 => &FORTE_PENTACHORDS,
+			6 => &FORTE_HEXACHORDS,
+			_ => return None,
+		};
+		let prime = lookup.prime_form();
+		table
+			.iter()
+			.find(|(_, _, members)| Self::new(members).prime_form() == prime)
+			.map(|&(ordinal, z, _)| ForteNumber {
+				cardinality,
+				ordinal,
+				z,
+			})
+	}
+
+	/// Check whether two sets share an interval-class vector without being related by transposition or inversion
+	#[must_use]
+	pub fn is_z_related(self, other: Self) -> bool {
+		self.interval_class_vector() == other.interval_class_vector()
+			&& self.prime_form() != other.prime_form()
+	}
+}
+
+/// Reduce any whole number of semitones to a pitch class
+fn wrap(semitones: i16) -> u8 {
+	u8::try_from(semitones.rem_euclid(i16::from(PITCH_CLASSES)))
+		.expect("Pitch classes are below 12")
+}
+
+/// Build the comparison key for an ordering of pitch classes, smaller is better packed.
+/// It lists the span from the first member to each later member, from the last member inwards.
+fn packing(ordering: &[u8]) -> Vec<u8> {
+	let first = ordering.first().copied().unwrap_or_default();
+	ordering
+		.iter()
+		.rev()
+		.map(|&pc| wrap(i16::from(pc) - i16::from(first)))
+		.collect()
+}
+
+impl FromIterator<u8> for PitchClassSet {
+	fn from_iter<T: IntoIterator<Item = u8>>(iter: T) -> Self {
+		Self(
+			iter.into_iter()
+				.fold(0, |acc, pc| acc | (1 << (pc % PITCH_CLASSES))),
+		)
+	}
+}
+
+impl FromIterator<Note> for PitchClassSet {
+	fn from_iter<T: IntoIterator<Item = Note>>(iter: T) -> Self {
+		iter.into_iter().map(Note::pitch_class).collect()
+	}
+}
+
+impl FromIterator<PianoKey> for PitchClassSet {
+	fn from_iter<T: IntoIterator<Item = PianoKey>>(iter: T) -> Self {
+		iter.into_iter().map(Note::from).collect()
+	}
+}
+
+impl From<Key> for PitchClassSet {
+	fn from(key: Key) -> Self {
+		key.get_notes().into_iter().collect()
+	}
+}
+
+impl fmt::Display for PitchClassSet {
+	/// Written in the usual shorthand, with T and E standing in for 10 and 11
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "[")?;
+		for pc in self.pitch_classes() {
+			match pc {
+				10 => write!(f, "T")?,
+				11 => write!(f, "E")?,
+				_ => write!(f, "{pc}")?,
+			}
+		}
+		write!(f, "]")
+	}
+}
+
+impl FromStr for PitchClassSet {
+	type Err = Error;
+
+	/// Accepts shorthand like "014" or "[0,1,4]", with T or A for 10 and E or B for 11
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		s.chars()
+			.filter(|c| !matches!(c, '[' | ']' | '{' | '}' | '(' | ')' | ',') && !c.is_whitespace())
+			.map(|c| match c.to_ascii_uppercase() {
+				'T' | 'A' => Ok(10),
+				'E' | 'B' => Ok(11),
+				_ => c
+					.to_digit(10)
+					.and_then(|d| u8::try_from(d).ok())
+					.ok_or(Error::BadPitchClass(c)),
+			})
+			.collect()
+	}
+}
@@ -1,10 +1,27 @@
 //! A Scale is a series of notes in a single octave, along a given set of intervals
 
-use super::{interval::Interval, key::Key, note::Note, piano_key::PianoKey};
-use std::{fmt, io, str::FromStr};
+use super::{interval::Interval, key::Key, note::Note, piano_key::PianoKey, semitone::Semitones};
+use crate::error::Error;
+use std::{fmt, str::FromStr};
+
+/// Every name `Scale::from_str` knows
+const NAMES: [&str; 11] = [
+	"ionian",
+	"major",
+	"dorian",
+	"phrygian",
+	"lydian",
+	"mixolydian",
+	"aeolian",
+	"minor",
+	"locrian",
+	"chromatic",
+	"tetratonic",
+];
 
 /// A Mode is an offset starting note along the line of Diatonic intervals
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Mode {
 	Ionian = 0,
 	Dorian,
@@ -16,9 +33,25 @@ pub enum Mode {
 }
 
 impl Mode {
+	/// Every mode, in order from Ionian
+	#[must_use]
+	pub fn all() -> Vec<Self> {
+		use Mode::{Aeolian, Dorian, Ionian, Locrian, Lydian, Mixolydian, Phrygian};
+		vec![
+			Ionian, Dorian, Phrygian, Lydian, Mixolydian, Aeolian, Locrian,
+		]
+	}
 	fn base_intervals() -> Vec<Interval> {
-		use Interval::{Maj2, Min2};
-		vec![Maj2, Maj2, Min2, Maj2, Maj2, Maj2, Min2]
+		use Interval as I;
+		vec![
+			I::MAJ2,
+			I::MAJ2,
+			I::MIN2,
+			I::MAJ2,
+			I::MAJ2,
+			I::MAJ2,
+			I::MIN2,
+		]
 	}
 }
 
@@ -29,7 +62,7 @@ pub enum Length {
 	Dodecatonic = 12,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Scale {
 	Chromatic,
 	Diatonic(Mode),
@@ -43,6 +76,14 @@ impl Default for Scale {
 }
 
 impl Scale {
+	/// Every supported scale, with each diatonic mode listed separately
+	#[must_use]
+	pub fn all() -> Vec<Self> {
+		let mut ret = vec![Scale::Chromatic];
+		ret.extend(Mode::all().into_iter().map(Scale::Diatonic));
+		ret.push(Scale::Tetratonic);
+		ret
+	}
 	/// Produce a scale for each member of the circle of fifths
 	///
 	/// # Panics
@@ -60,22 +101,36 @@ impl Scale {
 				PianoKey::from_str(&format!("{current_base}4")).unwrap(),
 				1,
 			));
-			current_base += Interval::Perfect5;
+			current_base += Interval::PERFECT5;
 		}
 		ret
 	}
-	/// Produce the intervals that make up this scale
+	/// Produce the intervals that make up this scale, spelled up from C
 	#[must_use]
 	pub fn get_intervals(self) -> Vec<Interval> {
-		use Interval::{Maj2, Maj3, Min2};
+		self.intervals_from(Note::default())
+	}
+
+	/// Produce the intervals that make up this scale, spelled up from a base note.
+	/// Only the chromatic scale changes from one base to another, taking each natural it reaches
+	/// and sharpening the letter below where there is none
+	#[must_use]
+	pub fn intervals_from(self, base: Note) -> Vec<Interval> {
+		use Interval as I;
 		use Scale::{Chromatic, Diatonic, Tetratonic};
 		match self {
-			Chromatic => [Min2]
-				.iter()
-				.cycle()
-				.take(Length::Dodecatonic as usize)
-				.copied()
-				.collect::<Vec<Interval>>(),
+			Chromatic => {
+				let mut last = base;
+				(1..Length::Dodecatonic as i8)
+					.map(|step| Note::from(Semitones::from(base.semitones_from_c() + step)))
+					.chain([base])
+					.map(|note| {
+						let ret = note.get_offset(last);
+						last = note;
+						ret
+					})
+					.collect()
+			},
 			Diatonic(mode) => Mode::base_intervals()
 				.iter()
 				.cycle()
@@ -83,13 +138,13 @@ impl Scale {
 				.take(Length::Heptatonic as usize)
 				.copied()
 				.collect::<Vec<Interval>>(),
-			Tetratonic => vec![Min2, Maj2, Maj3],
+			Tetratonic => vec![I::MIN2, I::MAJ2, I::MAJ3],
 		}
 	}
 }
 
 impl FromStr for Scale {
-	type Err = io::Error;
+	type Err = Error;
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
 		use Mode::{Aeolian, Dorian, Ionian, Locrian, Lydian, Mixolydian, Phrygian};
 		use Scale::{Chromatic, Diatonic, Tetratonic};
@@ -103,9 +158,41 @@ impl FromStr for Scale {
 			"LOCRIAN" => Ok(Diatonic(Locrian)),
 			"CHROMATIC" => Ok(Chromatic),
 			"TETRATONIC" => Ok(Tetratonic),
-			_ => Err(io::Error::new(io::ErrorKind::InvalidInput, "Unknown scale")),
+			_ => Err(Error::UnknownScale {
+				name: s.to_string(),
+				suggestions: suggestions(s),
+			}),
+		}
+	}
+}
+
+/// Find the scale names a misspelled one was most likely meant to be, closest first.
+/// A name is close if it begins with what was typed, or is at most two letters off
+fn suggestions(name: &str) -> Vec<&'static str> {
+	let name = name.to_lowercase();
+	let mut close = NAMES
+		.iter()
+		.map(|&n| (n, edit_distance(&name, n)))
+		.filter(|&(n, distance)| distance <= 2 || (name.len() >= 3 && n.starts_with(&name)))
+		.collect::<Vec<_>>();
+	close.sort_by_key(|&(_, distance)| distance);
+	close.into_iter().map(|(n, _)| n).collect()
+}
+
+/// Count the letters to insert, delete or change to turn one word into another
+fn edit_distance(a: &str, b: &str) -> usize {
+	let b = b.chars().collect::<Vec<_>>();
+	let mut row = (0..=b.len()).collect::<Vec<_>>();
+	for (i, ca) in a.chars().enumerate() {
+		let mut diagonal = row[0];
+		row[0] = i + 1;
+		for (j, &cb) in b.iter().enumerate() {
+			let substitution = diagonal + usize::from(ca != cb);
+			diagonal = row[j + 1];
+			row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
 		}
 	}
+	row[b.len()]
 }
 
 impl fmt::Display for Scale {
@@ -1,7 +1,5 @@
 //! A Semitone divides an octave into 12 equal parts
 
-use super::interval::Interval;
-
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct Semitones(i8);
 
@@ -16,9 +14,3 @@ impl From<Semitones> for i8 {
 		s.0
 	}
 }
-
-impl From<Interval> for Semitones {
-	fn from(i: Interval) -> Self {
-		Semitones(i as i8)
-	}
-}
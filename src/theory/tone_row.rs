@@ -0,0 +1,267 @@
+//! A `ToneRow` orders all twelve pitch classes for serial composition.
+//! Its 48 transformations are laid out in the familiar twelve-by-twelve matrix
+
+use super::{note::Note, semitone::Semitones};
+use crate::error::Error;
+use rand::{seq::SliceRandom, Rng};
+use std::{fmt, str::FromStr};
+
+/// The number of pitch classes in a row
+pub(crate) const ROW_LENGTH: usize = 12;
+
+/// A `RowForm` names one of the 48 transformations of a row.
+/// The number counts semitones of transposition from the row as given, so P0 is the original row
+/// and I0 is its inversion starting on the same pitch class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RowForm {
+	Prime(u8),
+	Retrograde(u8),
+	Inversion(u8),
+	RetrogradeInversion(u8),
+}
+
+impl fmt::Display for RowForm {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use RowForm::{Inversion, Prime, Retrograde, RetrogradeInversion};
+		match self {
+			Prime(n) => write!(f, "P{n}"),
+			Retrograde(n) => write!(f, "R{n}"),
+			Inversion(n) => write!(f, "I{n}"),
+			RetrogradeInversion(n) => write!(f, "RI{n}"),
+		}
+	}
+}
+
+impl FromStr for RowForm {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		use RowForm::{Inversion, Prime, Retrograde, RetrogradeInversion};
+		let s = s.trim().to_uppercase();
+		let split = s.find(|c: char| c.is_ascii_digit()).unwrap_or(s.len());
+		let number = s[split..]
+			.parse::<u8>()
+			.ok()
+			.filter(|n| usize::from(*n) < ROW_LENGTH)
+			.ok_or_else(|| Error::BadTransposition(s.clone()))?;
+		match &s[..split] {
+			"P" => Ok(Prime(number)),
+			"R" => Ok(Retrograde(number)),
+			"I" => Ok(Inversion(number)),
+			"RI" => Ok(RetrogradeInversion(number)),
+			_ => Err(Error::BadRowForm(s)),
+		}
+	}
+}
+
+/// A `ToneRow` holds each pitch class exactly once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ToneRow([u8; ROW_LENGTH]);
+
+impl ToneRow {
+	/// Build a row from twelve distinct pitch classes
+	pub fn new(pitch_classes: &[u8]) -> Result<Self, Error> {
+		let row: [u8; ROW_LENGTH] = pitch_classes
+			.try_into()
+			.map_err(|_| Error::RowLength(pitch_classes.len()))?;
+		for pc in 0..12 {
+			if !row.contains(&pc) {
+				return Err(Error::IncompleteRow(pc));
+			}
+		}
+		Ok(Self(row))
+	}
+
+	/// Shuffle the twelve pitch classes into a random row
+	pub fn random(rng: &mut impl Rng) -> Self {
+		let mut row = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+		row.shuffle(rng);
+		Self(row)
+	}
+
+	/// Build a random all-interval row, where each of the eleven intervals between neighbours appears once
+	///
+	/// # Panics
+	///
+	/// This function would panic if the search failed, but all-interval rows exist from every starting pitch class.
+	pub fn all_interval(rng: &mut impl Rng) -> Self {
+		let mut row = vec![rng.gen_range(0..12)];
+		let found = extend_all_interval(&mut row, rng);
+		assert!(found, "No all-interval row found");
+		Self::new(&row).expect("The search only places each pitch class once")
+	}
+
+	/// Check whether every interval between neighbouring pitch classes is different
+	#[must_use]
+	pub fn is_all_interval(self) -> bool {
+		let mut seen = [false; ROW_LENGTH];
+		for pair in self.0.windows(2) {
+			let interval = usize::from((pair[1] + 12 - pair[0]) % 12);
+			if seen[interval] {
+				return false;
+			}
+			seen[interval] = true;
+		}
+		true
+	}
+
+	/// Get the pitch classes of the row as given
+	#[must_use]
+	pub fn pitch_classes(self) -> [u8; ROW_LENGTH] {
+		self.0
+	}
+
+	/// Get the pitch classes of one of the 48 transformations.
+	/// Transpositions of 12 or more wrap around the octave, so P14 is P2
+	#[must_use]
+	pub fn form(self, form: RowForm) -> [u8; ROW_LENGTH] {
+		use RowForm::{Inversion, Prime, Retrograde, RetrogradeInversion};
+		let first = self.0[0];
+		let mut ret = self.0;
+		match form {
+			Prime(n) | Retrograde(n) => {
+				for pc in &mut ret {
+					*pc = (*pc + n % 12) % 12;
+				}
+			},
+			Inversion(n) | RetrogradeInversion(n) => {
+				for pc in &mut ret {
+					*pc = (2 * first + 24 - *pc + n % 12) % 12;
+				}
+			},
+		}
+		if matches!(form, Retrograde(_) | RetrogradeInversion(_)) {
+			ret.reverse();
+		}
+		ret
+	}
+
+	/// Get a row form as notes, spelled with sharps
+	#[must_use]
+	pub fn notes(self, form: RowForm) -> Vec<Note> {
+		self.form(form)
+			.iter()
+			.map(|&pc| Note::from(Semitones::from(i8::try_from(pc).unwrap_or_default())))
+			.collect()
+	}
+
+	/// Get every one of the 48 forms, primes first then retrogrades, inversions and retrograde inversions
+	#[must_use]
+	pub fn all_forms(self) -> Vec<RowForm> {
+		use RowForm::{Inversion, Prime, Retrograde, RetrogradeInversion};
+		(0..12)
+			.map(Prime)
+			.chain((0..12).map(Retrograde))
+			.chain((0..12).map(Inversion))
+			.chain((0..12).map(RetrogradeInversion))
+			.collect()
+	}
+
+	/// Build the twelve-by-twelve matrix of this row
+	#[must_use]
+	pub fn matrix(self) -> Matrix {
+		Matrix(self)
+	}
+}
+
+/// Depth-first search for the rest of an all-interval row, trying candidates in a random order
+fn extend_all_interval(row: &mut Vec<u8>, rng: &mut impl Rng) -> bool {
+	if row.len() == ROW_LENGTH {
+		return true;
+	}
+	let last = row[row.len() - 1];
+	let mut intervals = (1..12).collect::<Vec<u8>>();
+	intervals.shuffle(rng);
+	for interval in intervals {
+		let next = (last + interval) % 12;
+		let interval_used = row
+			.windows(2)
+			.any(|pair| (pair[1] + 12 - pair[0]) % 12 == interval);
+		if row.contains(&next) || interval_used {
+			continue;
+		}
+		row.push(next);
+		if extend_all_interval(row, rng) {
+			return true;
+		}
+		row.pop();
+	}
+	false
+}
+
+impl fmt::Display for ToneRow {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let pcs = self.0.iter().map(ToString::to_string).collect::<Vec<_>>();
+		write!(f, "{}", pcs.join(" "))
+	}
+}
+
+impl FromStr for ToneRow {
+	type Err = Error;
+
+	/// Accepts pitch class numbers or note names separated by spaces or commas,
+	/// like "0 11 7 8 3 1 2 10 6 5 4 9" or "C B G Ab Eb Db D Bb Gb F E A"
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let pcs = s
+			.split(|c: char| c == ',' || c.is_whitespace())
+			.filter(|t| !t.is_empty())
+			.map(|token| {
+				token
+					.parse::<u8>()
+					.ok()
+					.filter(|pc| usize::from(*pc) < ROW_LENGTH)
+					.map_or_else(|| Note::from_str(token).map(Note::pitch_class), Ok)
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+		Self::new(&pcs)
+	}
+}
+
+/// Write a pitch class in the usual shorthand, with T and E for 10 and 11
+fn pitch_class_name(pc: u8) -> String {
+	match pc {
+		10 => "T".into(),
+		11 => "E".into(),
+		_ => pc.to_string(),
+	}
+}
+
+/// The `Matrix` lays out every prime form as a row and every inversion as a column.
+/// Read right to left for retrogrades and bottom to top for retrograde inversions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Matrix(ToneRow);
+
+impl Matrix {
+	/// Get the twelve prime forms, in the order of the first inversion down the left side
+	#[must_use]
+	pub fn rows(self) -> Vec<(RowForm, [u8; ROW_LENGTH])> {
+		let row = self.0;
+		let first = row.pitch_classes()[0];
+		row.form(RowForm::Inversion(0))
+			.iter()
+			.map(|&start| {
+				let form = RowForm::Prime((start + 12 - first) % 12);
+				(form, row.form(form))
+			})
+			.collect()
+	}
+}
+
+impl fmt::Display for Matrix {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let first = self.0.pitch_classes()[0];
+		write!(f, "{:<5}", "")?;
+		for pc in self.0.pitch_classes() {
+			write!(f, "{:>4}", format!("I{}", (pc + 12 - first) % 12))?;
+		}
+		writeln!(f)?;
+		for (form, pcs) in self.rows() {
+			write!(f, "{:<5}", form.to_string())?;
+			for pc in pcs {
+				write!(f, "{:>4}", pitch_class_name(pc))?;
+			}
+			writeln!(f)?;
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,212 @@
+//! Voice leading moves each voice of one chord to a note of the next as little as it can.
+//! Voicings list their keys from the lowest voice up
+
+use super::{chord::Chord, interval::Interval, note::Note, piano_key::PianoKey};
+use crate::error::Error;
+
+/// The constraints that shape how voices move from chord to chord
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VoiceLeading {
+	hold_common_tones: bool,
+	max_motion: u8,
+	allow_crossing: bool,
+	/// The lowest and highest key of each voice, from the lowest voice up. Voices without one range freely
+	ranges: Vec<(PianoKey, PianoKey)>,
+}
+
+impl Default for VoiceLeading {
+	/// Hold common tones, move no voice more than a fifth, and never cross
+	fn default() -> Self {
+		Self {
+			hold_common_tones: true,
+			max_motion: 7,
+			allow_crossing: false,
+			ranges: Vec::new(),
+		}
+	}
+}
+
+impl VoiceLeading {
+	/// Keep voices on notes both chords share where they are
+	#[must_use]
+	pub fn with_common_tones(mut self, hold: bool) -> Self {
+		self.hold_common_tones = hold;
+		self
+	}
+
+	/// Move no voice further than this many semitones
+	#[must_use]
+	pub fn with_max_motion(mut self, semitones: u8) -> Self {
+		self.max_motion = semitones;
+		self
+	}
+
+	/// Let a voice move above the one over it
+	#[must_use]
+	pub fn with_crossing(mut self, allow: bool) -> Self {
+		self.allow_crossing = allow;
+		self
+	}
+
+	/// Keep each voice between a lowest and highest key, from the lowest voice up
+	#[must_use]
+	pub fn with_ranges(mut self, ranges: Vec<(PianoKey, PianoKey)>) -> Self {
+		self.ranges = ranges;
+		self
+	}
+
+	/// Find the voicing of the next chord that moves the voices the fewest semitones in total.
+	/// Every voice sings a tone of the next chord, and the chord keeps its root and third,
+	/// then its seventh and fifth as there are voices to spare
+	pub fn lead(
+		&self,
+		from: Chord,
+		voicing: &[PianoKey],
+		to: Chord,
+	) -> Result<Vec<PianoKey>, Error> {
+		let candidates = voicing
+			.iter()
+			.enumerate()
+			.map(|(i, &key)| self.candidates(i, key, from, to))
+			.collect::<Vec<_>>();
+		let required = required_tones(to, voicing.len());
+		let mut search = Search {
+			candidates: &candidates,
+			required: &required,
+			allow_crossing: self.allow_crossing,
+			current: Vec::with_capacity(voicing.len()),
+			best: None,
+		};
+		search.run(0);
+		search
+			.best
+			.map(|(voicing, _)| voicing)
+			.ok_or(Error::NoVoicing { from, to })
+	}
+
+	/// Voice a chord in four parts in root position, from its root in the given octave up,
+	/// doubling the root an octave higher when the chord has only three tones.
+	/// Voices outside their ranges move by octaves into them
+	pub fn root_position(&self, chord: Chord, octave: u8) -> Result<Vec<PianoKey>, Error> {
+		let root = PianoKey {
+			note: chord.root,
+			octave,
+		};
+		let mut voicing = vec![root];
+		voicing.extend(chord.quality.intervals().into_iter().map(|i| root + i));
+		if voicing.len() < 4 {
+			voicing.push(root + Interval::OCTAVE);
+		}
+		for (key, (low, high)) in voicing.iter_mut().zip(&self.ranges) {
+			let (low, high) = (low.semitones_from_c0(), high.semitones_from_c0());
+			let mut semitones = key.semitones_from_c0();
+			if semitones < low {
+				semitones = low + (semitones - low).rem_euclid(12);
+			} else if semitones > high {
+				semitones = high - (high - semitones).rem_euclid(12);
+			}
+			if semitones < low || semitones > high {
+				return Err(Error::NoRootPosition(chord));
+			}
+			*key = PianoKey::with_note(key.note, semitones);
+		}
+		let crossed = voicing
+			.windows(2)
+			.any(|pair| pair[0].semitones_from_c0() > pair[1].semitones_from_c0());
+		if crossed && !self.allow_crossing {
+			return Err(Error::NoRootPosition(chord));
+		}
+		Ok(voicing)
+	}
+
+	/// List the keys a voice could move to with how far each is, nearest first
+	fn candidates(
+		&self,
+		voice: usize,
+		key: PianoKey,
+		from: Chord,
+		to: Chord,
+	) -> Vec<(PianoKey, u32)> {
+		let (low, high) = self.ranges.get(voice).map_or(
+			(0, i16::from(PianoKey::max_octave()) * 12 + 11),
+			|(low, high)| (low.semitones_from_c0(), high.semitones_from_c0()),
+		);
+		let semitones = key.semitones_from_c0();
+		let in_range = |s: i16| low <= s && s <= high;
+		if self.hold_common_tones
+			&& from.contains(key.note)
+			&& to.contains(key.note)
+			&& in_range(semitones)
+		{
+			return vec![(key, 0)];
+		}
+		let notes = to.notes();
+		let motion = i16::from(self.max_motion);
+		let mut ret = (semitones - motion..=semitones + motion)
+			.filter(|&s| in_range(s))
+			.filter_map(|s| {
+				notes
+					.iter()
+					.find(|n| i16::from(n.pitch_class()) == s.rem_euclid(12))
+					.map(|&n| {
+						(
+							PianoKey::with_note(n, s),
+							u32::from((s - semitones).unsigned_abs()),
+						)
+					})
+			})
+			.collect::<Vec<_>>();
+		ret.sort_by_key(|&(_, distance)| distance);
+		ret
+	}
+}
+
+/// The tones a chord must keep with this many voices, most important first
+fn required_tones(chord: Chord, voices: usize) -> Vec<Note> {
+	let notes = chord.notes();
+	let mut ret = notes[..2].to_vec();
+	ret.extend(notes.get(3));
+	ret.push(notes[2]);
+	ret.truncate(voices);
+	ret
+}
+
+/// A depth-first search for the cheapest voicing, giving up on any branch that already costs more
+struct Search<'a> {
+	candidates: &'a [Vec<(PianoKey, u32)>],
+	required: &'a [Note],
+	allow_crossing: bool,
+	current: Vec<PianoKey>,
+	best: Option<(Vec<PianoKey>, u32)>,
+}
+
+impl Search<'_> {
+	fn run(&mut self, cost: u32) {
+		if self.best.as_ref().is_some_and(|(_, best)| cost >= *best) {
+			return;
+		}
+		let voice = self.current.len();
+		if voice == self.candidates.len() {
+			let complete = self
+				.required
+				.iter()
+				.all(|n| self.current.iter().any(|k| k.note == *n));
+			if complete {
+				self.best = Some((self.current.clone(), cost));
+			}
+			return;
+		}
+		for &(key, distance) in &self.candidates[voice] {
+			let crossed = self
+				.current
+				.last()
+				.is_some_and(|below| below.semitones_from_c0() > key.semitones_from_c0());
+			if crossed && !self.allow_crossing {
+				continue;
+			}
+			self.current.push(key);
+			self.run(cost + distance);
+			self.current.pop();
+		}
+	}
+}